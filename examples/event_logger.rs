@@ -0,0 +1,41 @@
+//! Log charon events as JSON lines, e.g. for a SIEM.
+//!
+//! ```text
+//! cargo run --example event_logger -- [-u URI] [-f FILE [--max-bytes N] [--keep N]] [EVENT]...
+//! ```
+//!
+//! Without events, the SA lifecycle events are logged; without `-f`, lines
+//! go to stdout.
+
+use rustici::jsonlog::{EventLogger, JsonLinesWriter, DEFAULT_EVENTS};
+use rustici::Client;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut builder = Client::builder();
+    let mut file = None;
+    let mut max_bytes = 16 << 20;
+    let mut keep = 5;
+    let mut events = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+        match arg.as_str() {
+            "-u" | "--uri" => builder = builder.uri(&value()?)?,
+            "-f" | "--file" => file = Some(value()?),
+            "--max-bytes" => max_bytes = value()?.parse()?,
+            "--keep" => keep = value()?.parse()?,
+            _ => events.push(arg),
+        }
+    }
+    let writer = match file {
+        Some(path) => JsonLinesWriter::file(path)?.rotate(max_bytes, keep),
+        None => JsonLinesWriter::stdout(),
+    };
+    let events: Vec<&str> = if events.is_empty() {
+        DEFAULT_EVENTS.to_vec()
+    } else {
+        events.iter().map(String::as_str).collect()
+    };
+    EventLogger::new(builder.build_reconnecting()?, &events, writer)?.run()?;
+    Ok(())
+}
@@ -0,0 +1,23 @@
+//! Serve charon metrics to Prometheus.
+//!
+//! ```text
+//! cargo run --example prometheus_exporter -- [LISTEN] [URI]
+//! ```
+
+use std::time::Duration;
+
+use rustici::exporter::Exporter;
+use rustici::Client;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let listen = args.next().unwrap_or_else(|| "127.0.0.1:9814".into());
+    let mut builder = Client::builder();
+    if let Some(uri) = args.next() {
+        builder = builder.uri(&uri)?;
+    }
+    let exporter = Exporter::bind(&listen, builder, Duration::from_secs(15))?;
+    println!("serving http://{}/metrics", exporter.local_addr());
+    exporter.run()?;
+    Ok(())
+}
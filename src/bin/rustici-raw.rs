@@ -0,0 +1,115 @@
+//! `rustici-raw`: call any VICI command with a message literal (feature `cli`).
+//!
+//! ```text
+//! rustici-raw load-conn 'conn { version = 2 local_addrs = [10.0.0.1] }'
+//! rustici-raw --stream list-sa list-sas 'noblock = yes'
+//! ```
+//!
+//! The message uses the notation of `Message`'s alternate `Display` form,
+//! which is also how responses and streamed events are printed.
+
+use std::env;
+use std::process::ExitCode;
+
+use rustici::error::Result;
+use rustici::{Client, Message};
+
+const USAGE: &str = "\
+Usage: rustici-raw [OPTIONS] COMMAND [MESSAGE]
+
+Send COMMAND with MESSAGE (e.g. 'conn { version = 2 local_addrs = [10.0.0.1] }')
+and print the response.
+
+Options:
+  -u, --uri URI         VICI socket, unix:///path or tcp://host:port
+                        (default unix:///var/run/charon.vici)
+  -s, --stream EVENT    register for EVENT and print it while the command runs
+  -j, --json            print JSON instead of the message notation
+  -h, --help            show this help";
+
+struct Args {
+    uri: Option<String>,
+    streams: Vec<String>,
+    json: bool,
+    command: String,
+    message: Message,
+}
+
+fn parse_args(mut argv: impl Iterator<Item = String>) -> std::result::Result<Option<Args>, String> {
+    let mut uri = None;
+    let mut streams = Vec::new();
+    let mut json = false;
+    let mut positional = Vec::new();
+    while let Some(arg) = argv.next() {
+        match arg.as_str() {
+            "-u" | "--uri" => uri = Some(argv.next().ok_or("--uri needs a value")?),
+            "-s" | "--stream" => streams.push(argv.next().ok_or("--stream needs a value")?),
+            "-j" | "--json" => json = true,
+            "-h" | "--help" => return Ok(None),
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                return Err(format!("unknown option {arg}"))
+            }
+            _ => positional.push(arg),
+        }
+    }
+    let mut positional = positional.into_iter();
+    let command = positional.next().ok_or("missing COMMAND")?;
+    let message = positional.collect::<Vec<_>>().join(" ");
+    let message = message.parse::<Message>().map_err(|e| e.to_string())?;
+    Ok(Some(Args {
+        uri,
+        streams,
+        json,
+        command,
+        message,
+    }))
+}
+
+fn print(json: bool, header: &str, msg: &Message) {
+    if json {
+        println!("{}", msg.to_json());
+    } else {
+        println!("{header} {{");
+        for line in format!("{msg:#}").lines() {
+            println!("  {line}");
+        }
+        println!("}}");
+    }
+}
+
+fn run(args: &Args) -> Result<()> {
+    let mut builder = Client::builder();
+    if let Some(uri) = &args.uri {
+        builder = builder.uri(uri)?;
+    }
+    let mut client = builder.build()?;
+    for event in &args.streams {
+        client.register_event(event)?;
+    }
+    let resp = client.call_streaming(&args.command, &args.message, |name, msg| {
+        print(args.json, &format!("{name} event"), msg);
+    })?;
+    print(args.json, &format!("{} reply", args.command), &resp);
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("rustici-raw: {e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("rustici-raw: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,339 @@
+//! `rustici`: a swanctl-compatible command-line client (feature `cli`).
+//!
+//! Implements the common swanctl commands on top of [`rustici::Client`] with
+//! no dependencies beyond std, so it can be linked statically for minimal
+//! container images.
+
+use std::env;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use rustici::config::{SwanctlConf, DEFAULT_CONF};
+use rustici::control::{InitiateRequest, TerminateRequest};
+use rustici::error::Result;
+use rustici::{Client, Message};
+
+const USAGE: &str = "\
+Usage: rustici [OPTIONS] COMMAND
+
+Commands:
+  -l, --list-sas      list IKE_SAs (--ike NAME)
+  -L, --list-conns    list loaded connections (--ike NAME)
+  -i, --initiate      initiate a connection (--child NAME [--ike NAME] [--timeout S])
+  -t, --terminate     terminate a connection (--ike/--child NAME, --ike-id/--child-id ID,
+                      [--force] [--timeout S])
+  -q, --load-all      load credentials, pools and connections (--file PATH)
+  -x, --list-certs    list certificates (--type TYPE, --subject DN)
+  -S, --stats         show daemon statistics
+  -C, --counters      show IKE counters (--name NAME | --all)
+  -T, --log           stream daemon logs until interrupted
+
+Options:
+  -u, --uri URI       VICI socket, unix:///path or tcp://host:port
+                      (default unix:///var/run/charon.vici)
+  -r, --raw           one line per message
+  -P, --pretty        indented messages (default)
+  -j, --json          one JSON object per message
+  -h, --help          show this help
+  -v, --version       show the version";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Pretty,
+    Raw,
+    Json,
+}
+
+#[derive(Debug, Default)]
+struct Args {
+    command: Option<&'static str>,
+    uri: Option<String>,
+    format: Option<Format>,
+    ike: Option<String>,
+    child: Option<String>,
+    ike_id: Option<u32>,
+    child_id: Option<u32>,
+    timeout: Option<u64>,
+    force: bool,
+    file: Option<String>,
+    name: Option<String>,
+    all: bool,
+    cert_type: Option<String>,
+    subject: Option<String>,
+}
+
+const COMMANDS: &[(&str, &str, &str)] = &[
+    ("-l", "--list-sas", "list-sas"),
+    ("-L", "--list-conns", "list-conns"),
+    ("-i", "--initiate", "initiate"),
+    ("-t", "--terminate", "terminate"),
+    ("-q", "--load-all", "load-all"),
+    ("-x", "--list-certs", "list-certs"),
+    ("-S", "--stats", "stats"),
+    ("-C", "--counters", "counters"),
+    ("-T", "--log", "log"),
+];
+
+fn parse_args(mut argv: impl Iterator<Item = String>) -> std::result::Result<Args, String> {
+    let mut args = Args::default();
+    while let Some(arg) = argv.next() {
+        let mut value = |name: &str| argv.next().ok_or(format!("{name} needs a value"));
+        if let Some(&(_, _, cmd)) = COMMANDS.iter().find(|(s, l, _)| arg == *s || arg == *l) {
+            if args.command.replace(cmd).is_some() {
+                return Err("only one command may be given".into());
+            }
+            continue;
+        }
+        match arg.as_str() {
+            "-u" | "--uri" => args.uri = Some(value(&arg)?),
+            "-r" | "--raw" => args.format = Some(Format::Raw),
+            "-P" | "--pretty" => args.format = Some(Format::Pretty),
+            "-j" | "--json" => args.format = Some(Format::Json),
+            "--ike" => args.ike = Some(value(&arg)?),
+            "--child" => args.child = Some(value(&arg)?),
+            "--ike-id" => args.ike_id = Some(number(&arg, value(&arg)?)?),
+            "--child-id" => args.child_id = Some(number(&arg, value(&arg)?)?),
+            "--timeout" => args.timeout = Some(number(&arg, value(&arg)?)?),
+            "--force" => args.force = true,
+            "--file" => args.file = Some(value(&arg)?),
+            "--name" => args.name = Some(value(&arg)?),
+            "--all" => args.all = true,
+            "--type" => args.cert_type = Some(value(&arg)?),
+            "--subject" => args.subject = Some(value(&arg)?),
+            "-h" | "--help" => args.command = Some("help"),
+            "-v" | "--version" => args.command = Some("version"),
+            _ => return Err(format!("unknown option {arg}")),
+        }
+    }
+    Ok(args)
+}
+
+fn number<T: std::str::FromStr>(name: &str, v: String) -> std::result::Result<T, String> {
+    v.parse().map_err(|_| format!("{name}: invalid number {v}"))
+}
+
+/// Prints messages in the selected format.
+struct Output {
+    format: Format,
+}
+
+impl Output {
+    /// Print message `msg` of `kind` (`event` or `reply`) named `name`.
+    fn message(&self, kind: &str, name: &str, msg: &Message) {
+        match self.format {
+            Format::Pretty => {
+                println!("{name} {kind} {{");
+                for line in format!("{msg:#}").lines() {
+                    println!("  {line}");
+                }
+                println!("}}");
+            }
+            Format::Raw => {
+                let body: Vec<_> = format!("{msg:#}")
+                    .lines()
+                    .map(str::trim)
+                    .map(str::to_string)
+                    .collect();
+                println!("{name} {kind} {{{}}}", body.join(" "));
+            }
+            Format::Json => {
+                let mut line = Message::new()
+                    .kv_str("type", kind)
+                    .kv_str("name", name)
+                    .section_start("message");
+                for el in msg.elements() {
+                    line.push(el.clone());
+                }
+                println!("{}", line.section_end().to_json());
+            }
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("rustici: {e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("rustici: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Run the command; `Ok(false)` means charon reported a failure.
+fn run(args: &Args) -> Result<bool> {
+    let command = match args.command {
+        None | Some("help") => {
+            println!("{USAGE}");
+            return Ok(args.command.is_some());
+        }
+        Some("version") => {
+            println!("rustici {}", env!("CARGO_PKG_VERSION"));
+            return Ok(true);
+        }
+        Some(command) => command,
+    };
+    let out = Output {
+        format: args.format.unwrap_or(Format::Pretty),
+    };
+    let mut builder = Client::builder();
+    if let Some(uri) = &args.uri {
+        builder = builder.uri(uri)?;
+    }
+    let mut client = builder.build()?;
+
+    match command {
+        "list-sas" => streamed(&mut client, &out, "list-sas", "list-sa", ike_filter(args)),
+        "list-conns" => streamed(
+            &mut client,
+            &out,
+            "list-conns",
+            "list-conn",
+            ike_filter(args),
+        ),
+        "list-certs" => {
+            let mut req = Message::new();
+            if let Some(ty) = &args.cert_type {
+                req = req.kv_str("type", ty);
+            }
+            if let Some(subject) = &args.subject {
+                req = req.kv_str("subject", subject);
+            }
+            streamed(&mut client, &out, "list-certs", "list-cert", req)
+        }
+        "initiate" => {
+            let mut req = InitiateRequest::new();
+            if let Some(child) = &args.child {
+                req = req.child(child.as_str());
+            }
+            if let Some(ike) = &args.ike {
+                req = req.ike(ike.as_str());
+            }
+            if let Some(secs) = args.timeout {
+                req = req.timeout(Duration::from_secs(secs));
+            }
+            streamed(
+                &mut client,
+                &out,
+                "initiate",
+                "control-log",
+                req.to_message(),
+            )
+        }
+        "terminate" => {
+            let mut req = TerminateRequest::new().force(args.force);
+            if let Some(child) = &args.child {
+                req = req.child(child.as_str());
+            }
+            if let Some(ike) = &args.ike {
+                req = req.ike(ike.as_str());
+            }
+            if let Some(id) = args.child_id {
+                req = req.child_id(id);
+            }
+            if let Some(id) = args.ike_id {
+                req = req.ike_id(id);
+            }
+            if let Some(secs) = args.timeout {
+                req = req.timeout(Duration::from_secs(secs));
+            }
+            streamed(
+                &mut client,
+                &out,
+                "terminate",
+                "control-log",
+                req.to_message(),
+            )
+        }
+        "stats" => simple(&mut client, &out, "stats", Message::new()),
+        "counters" => {
+            let req = match (&args.name, args.all) {
+                (Some(name), _) => Message::new().kv_str("name", name),
+                (None, true) => Message::new().kv_str("all", "yes"),
+                (None, false) => Message::new(),
+            };
+            simple(&mut client, &out, "get-counters", req)
+        }
+        "log" => {
+            client.register_event("log")?;
+            loop {
+                let (name, msg) = client.next_event()?;
+                out.message("event", &name, &msg);
+            }
+        }
+        "load-all" => load_all(&mut client, &out, args),
+        _ => unreachable!("unhandled command {command}"),
+    }
+}
+
+fn ike_filter(args: &Args) -> Message {
+    match &args.ike {
+        Some(ike) => Message::new().kv_str("ike", ike),
+        None => Message::new(),
+    }
+}
+
+/// Whether a response signals success (responses without `success` do).
+fn succeeded(resp: &Message) -> bool {
+    resp.tree()
+        .map(|t| t.flag("success").unwrap_or(true))
+        .unwrap_or(false)
+}
+
+fn simple(client: &mut Client, out: &Output, command: &str, req: Message) -> Result<bool> {
+    let resp = client.call(command, &req)?;
+    out.message("reply", command, &resp);
+    Ok(succeeded(&resp))
+}
+
+fn streamed(
+    client: &mut Client,
+    out: &Output,
+    command: &str,
+    event: &str,
+    req: Message,
+) -> Result<bool> {
+    client.register_event(event)?;
+    let resp = client.call_streaming(command, &req, |name, msg| {
+        if name == event {
+            out.message("event", name, msg);
+        }
+    })?;
+    client.unregister_event(event)?;
+    out.message("reply", command, &resp);
+    Ok(succeeded(&resp))
+}
+
+fn load_all(client: &mut Client, out: &Output, args: &Args) -> Result<bool> {
+    let conf = SwanctlConf::load(args.file.as_deref().unwrap_or(DEFAULT_CONF))?;
+    let summary = client.load_all(&conf)?;
+    let counts = [
+        ("certs", &summary.certs),
+        ("keys", &summary.keys),
+        ("shared", &summary.shared),
+        ("pools", &summary.pools),
+        ("conns", &summary.conns),
+    ];
+    let mut msg = Message::new();
+    for (kind, count) in counts {
+        msg = msg
+            .section_start(kind)
+            .kv_str("loaded", count.loaded.to_string())
+            .kv_str("unloaded", count.unloaded.to_string())
+            .section_start("failed");
+        for (name, reason) in &count.failed {
+            msg = msg.kv_str(name.as_str(), reason);
+        }
+        msg = msg.section_end().section_end();
+    }
+    out.message("reply", "load-all", &msg);
+    Ok(summary.is_ok())
+}
@@ -0,0 +1,212 @@
+//! Configurable construction of [`Client`]s.
+
+use std::io;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::client::{Client, Transport, DEFAULT_SOCKET};
+use crate::error::{Error, Result};
+use crate::packet::MAX_FRAME_LEN;
+use crate::reconnect::{Backoff, ReconnectingClient};
+
+/// Where to connect to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Endpoint {
+    Unix(PathBuf),
+    Tcp(String),
+}
+
+/// Builder for [`Client`] and [`ReconnectingClient`].
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use rustici::Client;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = Client::builder()
+///     .uri("unix:///var/run/charon.vici")?
+///     .connect_timeout(Duration::from_secs(2))
+///     .read_timeout(Some(Duration::from_secs(10)))
+///     .max_frame_size(4 * 1024 * 1024)
+///     .event_buffer(256)
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    endpoint: Endpoint,
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+    max_frame: usize,
+    event_buffer: usize,
+    reconnect: Option<Backoff>,
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        Self {
+            endpoint: Endpoint::Unix(PathBuf::from(DEFAULT_SOCKET)),
+            connect_timeout: None,
+            read_timeout: None,
+            write_timeout: None,
+            max_frame: MAX_FRAME_LEN,
+            event_buffer: 0,
+            reconnect: None,
+        }
+    }
+}
+
+impl ClientBuilder {
+    /// Defaults: [`DEFAULT_SOCKET`], no timeouts, 512 KiB frames, no event
+    /// buffer, no reconnect policy.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Connect to the UNIX socket at `path`.
+    pub fn path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.endpoint = Endpoint::Unix(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Connect to a URI as used by charon's `charon.plugins.vici.socket`:
+    /// `unix:///path/to/socket` or `tcp://host:port`.
+    pub fn uri(mut self, uri: &str) -> Result<Self> {
+        self.endpoint = if let Some(path) = uri.strip_prefix("unix://") {
+            Endpoint::Unix(PathBuf::from(path))
+        } else if let Some(addr) = uri.strip_prefix("tcp://") {
+            Endpoint::Tcp(addr.to_string())
+        } else {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unsupported VICI URI: {uri}"),
+            )));
+        };
+        Ok(self)
+    }
+
+    /// Fail with [`Error::Timeout`] if connecting over TCP takes longer than
+    /// `timeout`.
+    ///
+    /// A UNIX socket connect only blocks while the daemon's listen backlog is
+    /// full, and std cannot bound it, so the timeout does not apply there.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Socket read timeout, see [`Client::set_read_timeout`].
+    pub fn read_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.read_timeout = timeout;
+        self
+    }
+
+    /// Socket write timeout, see [`Client::set_write_timeout`].
+    pub fn write_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.write_timeout = timeout;
+        self
+    }
+
+    /// Largest packet accepted or sent, in bytes (default 512 KiB).
+    pub fn max_frame_size(mut self, bytes: usize) -> Self {
+        self.max_frame = bytes;
+        self
+    }
+
+    /// Keep up to `events` unsolicited events that arrive while awaiting a
+    /// command reply, for [`Client::next_event`] to return later.
+    ///
+    /// Without a buffer (the default) such events are discarded; when the
+    /// buffer is full the oldest event is. Both are counted in
+    /// [`Client::dropped_events`].
+    ///
+    /// This includes unrelated events arriving during streaming helpers such
+    /// as [`Client::list_sas`] or [`Client::initiate_with_log`]. A raw
+    /// [`Client::call_streaming`] instead hands every event to its callback.
+    pub fn event_buffer(mut self, events: usize) -> Self {
+        self.event_buffer = events;
+        self
+    }
+
+    /// Reconnect policy used by [`build_reconnecting`](Self::build_reconnecting).
+    pub fn reconnect(mut self, backoff: Backoff) -> Self {
+        self.reconnect = Some(backoff);
+        self
+    }
+
+    pub(crate) fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.read_timeout = timeout;
+    }
+
+    pub(crate) fn set_write_timeout(&mut self, timeout: Option<Duration>) {
+        self.write_timeout = timeout;
+    }
+
+    /// Connect and configure a [`Client`].
+    pub fn build(&self) -> Result<Client> {
+        let stream = match &self.endpoint {
+            Endpoint::Unix(path) => Transport::Unix(self.connect_unix(path)?),
+            Endpoint::Tcp(addr) => Transport::Tcp(self.connect_tcp(addr)?),
+        };
+        let client = Client::from_transport(stream, self.max_frame, self.event_buffer);
+        client.set_read_timeout(self.read_timeout)?;
+        client.set_write_timeout(self.write_timeout)?;
+        Ok(client)
+    }
+
+    /// Connect a [`ReconnectingClient`] that uses these settings (and the
+    /// [`reconnect`](Self::reconnect) policy, or the default backoff) for
+    /// every reconnection.
+    pub fn build_reconnecting(self) -> Result<ReconnectingClient> {
+        ReconnectingClient::from_builder(self)
+    }
+
+    pub(crate) fn backoff(&self) -> Backoff {
+        self.reconnect.clone().unwrap_or_default()
+    }
+
+    fn connect_unix(&self, path: &Path) -> Result<UnixStream> {
+        Ok(UnixStream::connect(path)?)
+    }
+
+    fn connect_tcp(&self, addr: &str) -> Result<TcpStream> {
+        let timeout = match self.connect_timeout {
+            None => return Ok(TcpStream::connect(addr)?),
+            Some(timeout) => timeout,
+        };
+        let mut last = None;
+        for addr in addr.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, timeout) {
+                Ok(stream) => return Ok(stream),
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => last = Some(Error::Timeout),
+                Err(e) => last = Some(Error::Io(e)),
+            }
+        }
+        Err(last.unwrap_or_else(|| {
+            Error::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "address resolved to nothing",
+            ))
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uri_parsing() {
+        let b = ClientBuilder::new().uri("unix:///run/charon.vici").unwrap();
+        assert_eq!(b.endpoint, Endpoint::Unix("/run/charon.vici".into()));
+        let b = ClientBuilder::new().uri("tcp://127.0.0.1:4502").unwrap();
+        assert_eq!(b.endpoint, Endpoint::Tcp("127.0.0.1:4502".into()));
+        assert!(ClientBuilder::new().uri("http://example.com").is_err());
+    }
+}
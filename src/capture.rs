@@ -0,0 +1,386 @@
+//! Recording and replaying VICI sessions.
+//!
+//! A [`Recorder`] attached to a [`Client`] with [`Client::record`] writes
+//! every packet sent and received, with a timestamp, to a capture file. A
+//! [`Replayer`] serves such a capture as a fake daemon: it expects the client
+//! to send exactly the recorded requests and answers each with the recorded
+//! replies and events.
+//!
+//! The capture format is binary: the magic `VICICAP\x01`, then one record per
+//! packet consisting of the microseconds since recording started (u64 BE), the
+//! direction (u8: 1 sent, 2 received), the packet length (u32 BE) and the
+//! packet as on the wire, without the transport length header.
+//!
+//! ```no_run
+//! use rustici::capture::{Recorder, Replayer};
+//! use rustici::{Client, Message};
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut client = Client::connect("/var/run/charon.vici")?;
+//! client.record(Recorder::create("incident.vicicap")?);
+//! client.call("version", &Message::new())?;
+//! client.stop_recording().unwrap().finish()?;
+//!
+//! let replay = Replayer::open("incident.vicicap")?.spawn()?;
+//! let mut client = replay.connect()?;
+//! client.call("version", &Message::new())?;
+//! drop(client);
+//! replay.finish()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::packet::{FrameReader, Packet, MAX_FRAME_LEN};
+use crate::server::SocketFile;
+
+const MAGIC: &[u8; 8] = b"VICICAP\x01";
+
+/// Which way a recorded packet went, seen from the client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Sent by the client to the daemon.
+    Sent,
+    /// Received by the client from the daemon.
+    Received,
+}
+
+impl Direction {
+    fn to_u8(self) -> u8 {
+        match self {
+            Direction::Sent => 1,
+            Direction::Received => 2,
+        }
+    }
+
+    fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            1 => Some(Direction::Sent),
+            2 => Some(Direction::Received),
+            _ => None,
+        }
+    }
+}
+
+/// One recorded packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    /// Time since recording started.
+    pub at: Duration,
+    /// Which way the packet went.
+    pub direction: Direction,
+    /// The encoded packet, without the transport length header.
+    pub data: Vec<u8>,
+}
+
+impl Record {
+    /// Decode the recorded packet.
+    pub fn packet(&self) -> Result<Packet> {
+        Packet::decode(&self.data)
+    }
+}
+
+/// Writes packets to a capture.
+///
+/// Recording is best effort: the first write error stops it and is reported
+/// by [`finish`](Self::finish), but never fails the client's calls.
+pub struct Recorder {
+    out: Box<dyn Write + Send>,
+    start: Instant,
+    error: Option<io::Error>,
+}
+
+impl Recorder {
+    /// Start a capture on `out`.
+    pub fn new<W: Write + Send + 'static>(out: W) -> io::Result<Self> {
+        let mut out: Box<dyn Write + Send> = Box::new(out);
+        out.write_all(MAGIC)?;
+        Ok(Self {
+            out,
+            start: Instant::now(),
+            error: None,
+        })
+    }
+
+    /// Start a capture in a new file at `path`, truncating an existing one.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+
+    /// Record the encoded packet `data` (without length header).
+    pub fn record(&mut self, direction: Direction, data: &[u8]) {
+        if self.error.is_some() {
+            return;
+        }
+        let at = self.start.elapsed().as_micros() as u64;
+        let mut head = [0u8; 13];
+        head[..8].copy_from_slice(&at.to_be_bytes());
+        head[8] = direction.to_u8();
+        head[9..].copy_from_slice(&(data.len() as u32).to_be_bytes());
+        if let Err(e) = self
+            .out
+            .write_all(&head)
+            .and_then(|()| self.out.write_all(data))
+        {
+            self.error = Some(e);
+        }
+    }
+
+    /// Flush the capture; fails with the first error that stopped recording.
+    pub fn finish(mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => self.out.flush(),
+        }
+    }
+}
+
+impl std::fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Recorder")
+            .field("start", &self.start)
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Reads the [`Record`]s of a capture.
+#[derive(Debug)]
+pub struct CaptureReader<R> {
+    input: R,
+}
+
+impl<R: Read> CaptureReader<R> {
+    /// Read a capture from `input`, checking its header.
+    pub fn new(mut input: R) -> Result<Self> {
+        let mut magic = [0u8; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(Error::Protocol("not a VICI capture"));
+        }
+        Ok(Self { input })
+    }
+
+    /// The next record, `None` at the end of the capture.
+    pub fn next_record(&mut self) -> Result<Option<Record>> {
+        let mut head = [0u8; 13];
+        match self.input.read(&mut head[..1])? {
+            0 => return Ok(None),
+            _ => self.input.read_exact(&mut head[1..])?,
+        }
+        let at = u64::from_be_bytes(head[..8].try_into().unwrap());
+        let direction =
+            Direction::from_u8(head[8]).ok_or(Error::Protocol("bad capture direction"))?;
+        let len = u32::from_be_bytes(head[9..].try_into().unwrap()) as usize;
+        if len > MAX_FRAME_LEN {
+            return Err(Error::TooLong("capture record"));
+        }
+        let mut data = vec![0u8; len];
+        self.input.read_exact(&mut data)?;
+        Ok(Some(Record {
+            at: Duration::from_micros(at),
+            direction,
+            data,
+        }))
+    }
+}
+
+impl CaptureReader<BufReader<File>> {
+    /// Open the capture file at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+/// Serves a capture as a fake daemon to one client connection.
+#[derive(Debug, Clone)]
+pub struct Replayer {
+    records: Vec<Record>,
+    realtime: bool,
+}
+
+impl Replayer {
+    /// Replay `records`.
+    pub fn new(records: Vec<Record>) -> Self {
+        Self {
+            records,
+            realtime: false,
+        }
+    }
+
+    /// Replay the capture file at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::new(
+            CaptureReader::open(path)?.collect::<Result<_>>()?,
+        ))
+    }
+
+    /// Reproduce the recorded delays between received packets (default: send
+    /// them as fast as possible).
+    pub fn realtime(mut self, realtime: bool) -> Self {
+        self.realtime = realtime;
+        self
+    }
+
+    /// The records being replayed.
+    pub fn records(&self) -> &[Record] {
+        &self.records
+    }
+
+    /// Play the daemon side of the capture on `stream`.
+    ///
+    /// Fails with [`Error::Protocol`] as soon as the client sends something
+    /// other than the next recorded packet, or disconnects before the end.
+    pub fn replay<S: Read + Write>(&self, mut stream: S) -> Result<()> {
+        let mut reader = FrameReader::new(MAX_FRAME_LEN);
+        let mut chunk = [0u8; 8192];
+        let mut last = None;
+        for record in &self.records {
+            match record.direction {
+                Direction::Sent => loop {
+                    if let Some(frame) = reader.next_frame()? {
+                        if frame != record.data {
+                            return Err(Error::Protocol("request does not match capture"));
+                        }
+                        break;
+                    }
+                    match stream.read(&mut chunk)? {
+                        0 => return Err(Error::Protocol("client left before end of capture")),
+                        n => reader.push(&chunk[..n]),
+                    }
+                },
+                Direction::Received => {
+                    if self.realtime {
+                        if let Some(last) = last {
+                            thread::sleep(record.at.saturating_sub(last));
+                        }
+                    }
+                    let mut frame = (record.data.len() as u32).to_be_bytes().to_vec();
+                    frame.extend_from_slice(&record.data);
+                    stream.write_all(&frame)?;
+                }
+            }
+            last = Some(record.at);
+        }
+        Ok(())
+    }
+
+    /// Listen on `path` and replay to the first client that connects.
+    ///
+    /// A stale socket file at `path` is replaced; one something still
+    /// listens on is not.
+    pub fn serve<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let (listener, _socket) = SocketFile::bind(path.as_ref())?;
+        listener
+            .accept()
+            .map_err(Error::from)
+            .and_then(|(stream, _)| self.replay(stream))
+    }
+
+    /// Replay on a fresh socket in the temp directory, in the background.
+    pub fn spawn(self) -> Result<Replay> {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "rustici-replay-{}-{}.sock",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let (listener, socket) = SocketFile::bind(&path)?;
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept()?;
+            self.replay(stream)
+        });
+        Ok(Replay {
+            socket,
+            handle: Some(handle),
+        })
+    }
+}
+
+/// A capture being replayed in the background, see [`Replayer::spawn`].
+pub struct Replay {
+    socket: SocketFile,
+    handle: Option<JoinHandle<Result<()>>>,
+}
+
+impl Replay {
+    /// Path of the socket the capture is served on.
+    pub fn path(&self) -> &Path {
+        self.socket.path()
+    }
+
+    /// Connect a [`Client`] to the replayed daemon.
+    pub fn connect(&self) -> Result<Client> {
+        Client::connect(self.socket.path())
+    }
+
+    /// Wait for the replay to end and return its outcome.
+    pub fn finish(mut self) -> Result<()> {
+        let handle = self.handle.take().expect("replay already finished");
+        handle
+            .join()
+            .map_err(|_| Error::Protocol("replay thread panicked"))?
+    }
+}
+
+impl Drop for Replay {
+    fn drop(&mut self) {
+        if self.handle.take().is_some() {
+            // Release a replay thread still waiting for its client.
+            let _ = UnixStream::connect(self.socket.path());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::PacketType;
+    use crate::wire::Message;
+
+    #[test]
+    fn capture_roundtrip() {
+        let pkt = Packet::new(
+            PacketType::CmdRequest,
+            Some("list-sas".into()),
+            Some(Message::new().kv_str("ike", "gw")),
+        );
+        let data = pkt.encode().unwrap()[4..].to_vec();
+
+        let path = std::env::temp_dir().join(format!("rustici-rt-{}.vicicap", std::process::id()));
+        let mut rec = Recorder::create(&path).unwrap();
+        rec.record(Direction::Sent, &data);
+        rec.record(Direction::Received, &[PacketType::CmdUnknown as u8]);
+        rec.finish().unwrap();
+
+        let records: Vec<_> = CaptureReader::open(&path)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].direction, Direction::Sent);
+        assert_eq!(records[0].packet().unwrap(), pkt);
+        assert_eq!(records[1].direction, Direction::Received);
+        assert!(records[0].at <= records[1].at);
+
+        assert!(CaptureReader::new(&b"garbage!"[..]).is_err());
+    }
+}
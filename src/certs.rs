@@ -0,0 +1,413 @@
+//! Certificate and certification authority listing.
+//!
+//! `list-certs` streams one `list-cert` event per certificate with the raw
+//! DER encoding; parsing the certificate itself is left to the caller.
+//! Authorities are managed with `load-authority`/`unload-authority` and
+//! inspected with `list-authorities`/`get-authorities`.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::client::{expect_success, Client};
+use crate::creds::CertFlag;
+use crate::error::{Error, Result};
+use crate::wire::{Message, Section, Value};
+
+/// Certificate type as reported by `list-certs`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CertKind {
+    /// X.509 certificate.
+    X509,
+    /// X.509 attribute certificate.
+    X509Ac,
+    /// X.509 certificate revocation list.
+    X509Crl,
+    /// OCSP response.
+    OcspResponse,
+    /// Raw public key.
+    Pubkey,
+}
+
+impl CertKind {
+    /// The name charon uses for this type.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CertKind::X509 => "X509",
+            CertKind::X509Ac => "X509_AC",
+            CertKind::X509Crl => "X509_CRL",
+            CertKind::OcspResponse => "OCSP_RESPONSE",
+            CertKind::Pubkey => "PUBKEY",
+        }
+    }
+}
+
+impl FromStr for CertKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_uppercase().as_str() {
+            "X509" => Ok(CertKind::X509),
+            "X509_AC" => Ok(CertKind::X509Ac),
+            "X509_CRL" => Ok(CertKind::X509Crl),
+            "OCSP_RESPONSE" => Ok(CertKind::OcspResponse),
+            "PUBKEY" => Ok(CertKind::Pubkey),
+            _ => Err(Error::Protocol("unknown certificate kind")),
+        }
+    }
+}
+
+impl fmt::Display for CertKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Filter for `list-certs`; an empty filter lists everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CertFilter {
+    kind: Option<CertKind>,
+    flag: Option<CertFlag>,
+    subject: Option<String>,
+}
+
+impl CertFilter {
+    /// Create an empty filter.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only list certificates of this type.
+    pub fn kind(mut self, kind: CertKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    /// Only list X.509 certificates with this flag.
+    pub fn flag(mut self, flag: CertFlag) -> Self {
+        self.flag = Some(flag);
+        self
+    }
+
+    /// Only list certificates with this subject.
+    pub fn subject(mut self, subject: impl Into<String>) -> Self {
+        self.subject = Some(subject.into());
+        self
+    }
+
+    /// Encode the `list-certs` request message.
+    pub fn to_message(&self) -> Message {
+        let mut msg = Message::new();
+        if let Some(kind) = self.kind {
+            msg = msg.kv_str("type", kind.as_str());
+        }
+        if let Some(flag) = self.flag {
+            msg = msg.kv_str("flag", flag.as_str());
+        }
+        if let Some(subject) = &self.subject {
+            msg = msg.kv_str("subject", subject);
+        }
+        msg
+    }
+}
+
+/// A certificate as reported by `list-certs`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertInfo {
+    /// Certificate type as named by charon, e.g. `X509`.
+    pub cert_type: String,
+    /// X.509 flag, if any.
+    pub flag: Option<CertFlag>,
+    /// Whether the matching private key is available.
+    pub has_privkey: bool,
+    /// Raw certificate encoding (DER).
+    pub data: Vec<u8>,
+    /// Subject, reported for raw public keys.
+    pub subject: Option<String>,
+    /// Start of validity, reported for raw public keys.
+    pub not_before: Option<String>,
+    /// End of validity, reported for raw public keys.
+    pub not_after: Option<String>,
+}
+
+impl CertInfo {
+    /// Parse a `list-cert` event message.
+    pub fn from_message(msg: &Message) -> Result<Self> {
+        let tree = msg.tree()?;
+        Ok(Self {
+            cert_type: tree
+                .str("type")
+                .ok_or(Error::Protocol("certificate without type"))?
+                .to_string(),
+            flag: tree.parse("flag"),
+            has_privkey: tree.flag("has_privkey").unwrap_or(false),
+            data: tree
+                .bytes("data")
+                .ok_or(Error::Protocol("certificate without data"))?
+                .to_vec(),
+            subject: tree.str("subject").map(str::to_string),
+            not_before: tree.str("not-before").map(str::to_string),
+            not_after: tree.str("not-after").map(str::to_string),
+        })
+    }
+
+    /// The certificate type, if it is one of the known kinds.
+    pub fn kind(&self) -> Option<CertKind> {
+        self.cert_type.parse().ok()
+    }
+}
+
+/// A certification authority definition for `load-authority`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthorityConfig {
+    name: String,
+    cacert: Option<Vec<u8>>,
+    file: Option<String>,
+    handle: Option<String>,
+    slot: Option<u32>,
+    module: Option<String>,
+    crl_uris: Vec<String>,
+    ocsp_uris: Vec<String>,
+    cert_uri_base: Option<String>,
+}
+
+impl AuthorityConfig {
+    /// Create an authority definition named `name`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            cacert: None,
+            file: None,
+            handle: None,
+            slot: None,
+            module: None,
+            crl_uris: Vec::new(),
+            ocsp_uris: Vec::new(),
+            cert_uri_base: None,
+        }
+    }
+
+    /// Name of this authority.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// CA certificate data (PEM or DER).
+    pub fn cacert(mut self, data: impl Into<Vec<u8>>) -> Self {
+        self.cacert = Some(data.into());
+        self
+    }
+
+    /// Path of the CA certificate as seen by the daemon.
+    pub fn file(mut self, path: impl Into<String>) -> Self {
+        self.file = Some(path.into());
+        self
+    }
+
+    /// Hex-encoded CKA_ID of a CA certificate on a token.
+    pub fn handle(mut self, handle: impl Into<String>) -> Self {
+        self.handle = Some(handle.into());
+        self
+    }
+
+    /// Token slot of the CA certificate.
+    pub fn slot(mut self, slot: u32) -> Self {
+        self.slot = Some(slot);
+        self
+    }
+
+    /// PKCS#11 module of the CA certificate.
+    pub fn module(mut self, module: impl Into<String>) -> Self {
+        self.module = Some(module.into());
+        self
+    }
+
+    /// Add a CRL distribution point.
+    pub fn crl_uri(mut self, uri: impl Into<String>) -> Self {
+        self.crl_uris.push(uri.into());
+        self
+    }
+
+    /// Add an OCSP responder URI.
+    pub fn ocsp_uri(mut self, uri: impl Into<String>) -> Self {
+        self.ocsp_uris.push(uri.into());
+        self
+    }
+
+    /// Base URI for hash-and-URL certificate lookups.
+    pub fn cert_uri_base(mut self, uri: impl Into<String>) -> Self {
+        self.cert_uri_base = Some(uri.into());
+        self
+    }
+
+    /// Encode the `load-authority` request message.
+    pub fn to_message(&self) -> Message {
+        let mut sec = Section::new();
+        if let Some(data) = &self.cacert {
+            sec.insert("cacert", Value::Bytes(data.clone()));
+        }
+        if let Some(file) = &self.file {
+            sec.insert_str("file", file);
+        }
+        if let Some(handle) = &self.handle {
+            sec.insert_str("handle", handle);
+        }
+        if let Some(slot) = self.slot {
+            sec.insert_str("slot", slot.to_string());
+        }
+        if let Some(module) = &self.module {
+            sec.insert_str("module", module);
+        }
+        if !self.crl_uris.is_empty() {
+            sec.insert_str_list("crl_uris", &self.crl_uris);
+        }
+        if !self.ocsp_uris.is_empty() {
+            sec.insert_str_list("ocsp_uris", &self.ocsp_uris);
+        }
+        if let Some(base) = &self.cert_uri_base {
+            sec.insert_str("cert_uri_base", base);
+        }
+        let mut root = Section::new();
+        root.insert(self.name.clone(), Value::Section(sec));
+        root.to_message()
+    }
+}
+
+/// An authority as reported by `list-authorities`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthorityInfo {
+    /// Authority name.
+    pub name: String,
+    /// Subject of the CA certificate.
+    pub cacert: Option<String>,
+    /// CRL distribution points.
+    pub crl_uris: Vec<String>,
+    /// OCSP responder URIs.
+    pub ocsp_uris: Vec<String>,
+    /// Base URI for hash-and-URL lookups.
+    pub cert_uri_base: Option<String>,
+}
+
+impl AuthorityInfo {
+    /// Parse all authorities contained in a `list-authority` event message.
+    pub fn from_event(msg: &Message) -> Result<Vec<Self>> {
+        let tree = msg.tree()?;
+        Ok(tree
+            .sections()
+            .map(|(name, sec)| Self {
+                name: name.to_string(),
+                cacert: sec.str("cacert").map(str::to_string),
+                crl_uris: sec.str_list("crl_uris"),
+                ocsp_uris: sec.str_list("ocsp_uris"),
+                cert_uri_base: sec.str("cert_uri_base").map(str::to_string),
+            })
+            .collect())
+    }
+}
+
+impl Client {
+    /// List certificates matching `filter`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rustici::Client;
+    /// use rustici::certs::{CertFilter, CertKind};
+    /// use rustici::creds::CertFlag;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = Client::connect("/var/run/charon.vici")?;
+    /// let filter = CertFilter::new().kind(CertKind::X509).flag(CertFlag::Ca);
+    /// for cert in client.list_certs(&filter)? {
+    ///     println!("{} bytes, private key: {}", cert.data.len(), cert.has_privkey);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn list_certs(&mut self, filter: &CertFilter) -> Result<Vec<CertInfo>> {
+        self.collect_streamed(
+            "list-certs",
+            &filter.to_message(),
+            "list-cert",
+            |msg, out| {
+                out.push(CertInfo::from_message(msg)?);
+                Ok(())
+            },
+        )
+    }
+
+    /// List loaded authorities, optionally only the one named `name`.
+    pub fn list_authorities(&mut self, name: Option<&str>) -> Result<Vec<AuthorityInfo>> {
+        let mut req = Message::new();
+        if let Some(name) = name {
+            req = req.kv_str("name", name);
+        }
+        self.collect_streamed("list-authorities", &req, "list-authority", |msg, out| {
+            out.append(&mut AuthorityInfo::from_event(msg)?);
+            Ok(())
+        })
+    }
+
+    /// Load (or replace) a certification authority.
+    pub fn load_authority(&mut self, authority: &AuthorityConfig) -> Result<()> {
+        let resp = self.call("load-authority", &authority.to_message())?;
+        expect_success(&resp).map(|_| ())
+    }
+
+    /// Unload the authority named `name`.
+    pub fn unload_authority(&mut self, name: &str) -> Result<()> {
+        let resp = self.call("unload-authority", &Message::new().kv_str("name", name))?;
+        expect_success(&resp).map(|_| ())
+    }
+
+    /// Names of all authorities loaded over VICI.
+    pub fn get_authorities(&mut self) -> Result<Vec<String>> {
+        let resp = self.call("get-authorities", &Message::new())?;
+        Ok(resp.tree()?.str_list("names"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_cert_parsing() {
+        let msg = Message::new()
+            .kv_str("type", "X509")
+            .kv_str("flag", "CA")
+            .kv_str("has_privkey", "yes")
+            .kv_bytes("data", [0x30, 0x82, 0x01, 0x00]);
+        let cert = CertInfo::from_message(&msg).unwrap();
+        assert_eq!(cert.kind(), Some(CertKind::X509));
+        assert_eq!(cert.flag, Some(CertFlag::Ca));
+        assert!(cert.has_privkey);
+        assert_eq!(cert.data, [0x30, 0x82, 0x01, 0x00]);
+    }
+
+    #[test]
+    fn authority_roundtrip() {
+        let cfg = AuthorityConfig::new("strongswan")
+            .cacert(b"-----BEGIN CERTIFICATE-----".to_vec())
+            .crl_uri("http://crl.example.org/ca.crl")
+            .ocsp_uri("http://ocsp.example.org");
+        let tree = cfg.to_message().tree().unwrap();
+        let sec = tree.section("strongswan").unwrap();
+        assert_eq!(sec.str_list("crl_uris"), ["http://crl.example.org/ca.crl"]);
+
+        let listed = Message::new()
+            .section_start("strongswan")
+            .kv_str("cacert", "C=CH, O=strongSwan, CN=Root CA")
+            .list_start("ocsp_uris")
+            .list_item_str("http://ocsp.example.org")
+            .list_end()
+            .section_end();
+        let info = AuthorityInfo::from_event(&listed).unwrap();
+        assert_eq!(info[0].name, "strongswan");
+        assert_eq!(
+            info[0].cacert.as_deref(),
+            Some("C=CH, O=strongSwan, CN=Root CA")
+        );
+        assert_eq!(info[0].ocsp_uris, ["http://ocsp.example.org"]);
+        assert!(info[0].crl_uris.is_empty());
+    }
+}
@@ -4,22 +4,215 @@
 //! the underlying file descriptor so advanced users can integrate it into
 //! their own event loop if desired.
 
-use std::io::{Read, Write};
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpStream};
 use std::os::fd::AsRawFd;
 use std::os::unix::net::UnixStream;
 use std::path::Path;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use crate::builder::ClientBuilder;
+use crate::capture::{Direction, Recorder};
 use crate::error::{Error, Result};
-use crate::packet::{Packet, PacketType};
-use crate::wire::Message;
+use crate::packet::{FrameReader, Packet, PacketType};
+use crate::wire::{Message, Section};
 
 /// The default charon VICI socket path.
 pub const DEFAULT_SOCKET: &str = "/var/run/charon.vici";
 
+/// Callback receiving events streamed during a request.
+type EventCallback<'a> = dyn FnMut(&str, &Message) + 'a;
+
+/// How often a cancellable call checks its [`CancelToken`].
+const CANCEL_POLL: Duration = Duration::from_millis(50);
+
+/// The socket a [`Client`] talks over.
+#[derive(Debug)]
+pub(crate) enum Transport {
+    Unix(UnixStream),
+    Tcp(TcpStream),
+}
+
+impl Transport {
+    fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        match self {
+            Transport::Unix(s) => s.read_timeout(),
+            Transport::Tcp(s) => s.read_timeout(),
+        }
+    }
+
+    fn set_read_timeout(&self, to: Option<Duration>) -> io::Result<()> {
+        match self {
+            Transport::Unix(s) => s.set_read_timeout(to),
+            Transport::Tcp(s) => s.set_read_timeout(to),
+        }
+    }
+
+    fn set_write_timeout(&self, to: Option<Duration>) -> io::Result<()> {
+        match self {
+            Transport::Unix(s) => s.set_write_timeout(to),
+            Transport::Tcp(s) => s.set_write_timeout(to),
+        }
+    }
+
+    fn as_raw_fd(&self) -> i32 {
+        match self {
+            Transport::Unix(s) => s.as_raw_fd(),
+            Transport::Tcp(s) => s.as_raw_fd(),
+        }
+    }
+
+    pub(crate) fn try_clone(&self) -> io::Result<Self> {
+        Ok(match self {
+            Transport::Unix(s) => Transport::Unix(s.try_clone()?),
+            Transport::Tcp(s) => Transport::Tcp(s.try_clone()?),
+        })
+    }
+
+    pub(crate) fn shutdown(&self) -> io::Result<()> {
+        match self {
+            Transport::Unix(s) => s.shutdown(Shutdown::Both),
+            Transport::Tcp(s) => s.shutdown(Shutdown::Both),
+        }
+    }
+}
+
+impl Read for Transport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Transport::Unix(s) => s.read(buf),
+            Transport::Tcp(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for Transport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Transport::Unix(s) => s.write(buf),
+            Transport::Tcp(s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Transport::Unix(s) => s.flush(),
+            Transport::Tcp(s) => s.flush(),
+        }
+    }
+}
+
 /// A simple synchronous client.
 pub struct Client {
-    stream: UnixStream,
+    stream: Transport,
+    events: Vec<String>,
+    reader: FrameReader,
+    max_frame: usize,
+    /// Unsolicited events received while awaiting a reply.
+    queued: VecDeque<(String, Message)>,
+    queue_cap: usize,
+    dropped: u64,
+    /// Replies still owed to requests that were aborted.
+    stale: usize,
+    /// Events registered only for a stream that was aborted; unregistered
+    /// before the next request, and dropped when they arrive until then.
+    aborted: Vec<String>,
+    poisoned: bool,
+    recorder: Option<Recorder>,
+}
+
+/// A handle to abort a pending call from another thread.
+///
+/// Clones share the same state. A cancelled token stays cancelled; use a
+/// fresh one for the next call.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// A token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Abort the call(s) using this token with [`Error::Cancelled`].
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether [`cancel`](Self::cancel) was called.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Deadline and cancellation for a single call.
+///
+/// Both cover the whole exchange, including any events streamed before the
+/// response. Without either, the socket timeouts set on the [`Client`] apply
+/// to each read as before.
+#[derive(Debug, Clone, Default)]
+pub struct CallOptions {
+    deadline: Option<Instant>,
+    cancel: Option<CancelToken>,
+}
+
+impl CallOptions {
+    /// No deadline, not cancellable.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail with [`Error::Timeout`] if the call has not completed by `deadline`.
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Shorthand for a deadline `timeout` from now.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        self.deadline(Instant::now() + timeout)
+    }
+
+    /// Fail with [`Error::Cancelled`] once `token` is cancelled.
+    pub fn cancel(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    fn is_limited(&self) -> bool {
+        self.deadline.is_some() || self.cancel.is_some()
+    }
+
+    /// Fail if the call must stop now.
+    fn check(&self) -> Result<()> {
+        if self
+            .cancel
+            .as_ref()
+            .map_or(false, CancelToken::is_cancelled)
+        {
+            return Err(Error::Cancelled);
+        }
+        if self.deadline.map_or(false, |d| Instant::now() >= d) {
+            return Err(Error::Timeout);
+        }
+        Ok(())
+    }
+
+    /// How long the next read may block, `None` for the socket default.
+    fn next_wait(&self) -> Result<Option<Duration>> {
+        self.check()?;
+        let mut wait = self
+            .deadline
+            .map(|d| d.saturating_duration_since(Instant::now()));
+        if self.cancel.is_some() {
+            wait = Some(wait.map_or(CANCEL_POLL, |w| w.min(CANCEL_POLL)));
+        }
+        // A zero read timeout is rejected by the socket.
+        Ok(wait.map(|w| w.max(Duration::from_millis(1))))
+    }
 }
 
 impl Client {
@@ -44,8 +237,28 @@ impl Client {
     /// # }
     /// ```
     pub fn connect<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let stream = UnixStream::connect(path)?;
-        Ok(Self { stream })
+        ClientBuilder::new().path(path).build()
+    }
+
+    /// Start configuring a client; see [`ClientBuilder`].
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+
+    pub(crate) fn from_transport(stream: Transport, max_frame: usize, queue_cap: usize) -> Self {
+        Self {
+            stream,
+            events: Vec::new(),
+            reader: FrameReader::new(max_frame),
+            max_frame,
+            queued: VecDeque::new(),
+            queue_cap,
+            dropped: 0,
+            stale: 0,
+            aborted: Vec::new(),
+            poisoned: false,
+            recorder: None,
+        }
     }
 
     /// Returns the raw file descriptor for integration with `select`/`poll`.
@@ -134,8 +347,11 @@ impl Client {
     /// Send a simple RPC-style command and await its response.
     ///
     /// This method sends a command request and waits for the corresponding
-    /// response. Any unsolicited events received while waiting are silently
-    /// ignored.
+    /// response. Unsolicited events received while waiting are queued for
+    /// [`next_event`](Self::next_event), up to
+    /// [`ClientBuilder::event_buffer`](crate::builder::ClientBuilder::event_buffer);
+    /// the rest are dropped and counted in
+    /// [`dropped_events`](Self::dropped_events).
     ///
     /// # Arguments
     ///
@@ -161,30 +377,79 @@ impl Client {
     /// # }
     /// ```
     pub fn call(&mut self, command: &str, request: &Message) -> Result<Message> {
-        let pkt = Packet {
-            ty: PacketType::CmdRequest,
-            name: Some(command.to_string()),
-            message: Some(request.clone()),
-        };
-        self.send_packet(&pkt)?;
+        self.call_with(command, request, &CallOptions::new())
+    }
 
-        loop {
-            let pkt = self.recv_packet()?;
-            match pkt.ty {
-                PacketType::CmdResponse => {
-                    // Some VICI commands legitimately return an empty response body.
-                    // Treat a missing message as an empty Message instead of a protocol error.
-                    return Ok(pkt.message.unwrap_or_default());
-                }
-                PacketType::CmdUnknown => {
-                    return Err(Error::UnknownCommand(command.to_string()));
-                }
-                PacketType::Event => {
-                    // Ignore unsolicited events here.
-                    continue;
-                }
-                _ => return Err(Error::Protocol("unexpected packet while awaiting response")),
-            }
+    /// Like [`call`](Self::call), but fail with [`Error::Timeout`] if no
+    /// response arrived by `deadline`.
+    ///
+    /// The connection stays usable: the late response is discarded when it
+    /// eventually arrives.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::time::{Duration, Instant};
+    /// use rustici::{Client, wire::Message};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = Client::connect("/var/run/charon.vici")?;
+    /// let deadline = Instant::now() + Duration::from_secs(2);
+    /// let response = client.call_with_deadline("version", &Message::new(), deadline)?;
+    /// println!("Response: {}", response);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn call_with_deadline(
+        &mut self,
+        command: &str,
+        request: &Message,
+        deadline: Instant,
+    ) -> Result<Message> {
+        self.call_with(command, request, &CallOptions::new().deadline(deadline))
+    }
+
+    /// Like [`call`](Self::call), with a deadline and/or cancellation.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::thread;
+    /// use std::time::Duration;
+    /// use rustici::client::{CallOptions, CancelToken};
+    /// use rustici::{Client, wire::Message};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = Client::connect("/var/run/charon.vici")?;
+    /// let token = CancelToken::new();
+    /// let remote = token.clone();
+    /// thread::spawn(move || {
+    ///     thread::sleep(Duration::from_secs(1));
+    ///     remote.cancel();
+    /// });
+    /// let req = Message::new().kv_str("child", "net");
+    /// let result = client.call_with("initiate", &req, &CallOptions::new().cancel(token));
+    /// println!("{:?}", result);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn call_with(
+        &mut self,
+        command: &str,
+        request: &Message,
+        opts: &CallOptions,
+    ) -> Result<Message> {
+        let pkt = Packet::new(
+            PacketType::CmdRequest,
+            Some(command.to_string()),
+            Some(request.clone()),
+        );
+        let resp = self.request(&pkt, opts, None)?;
+        match resp.ty {
+            // Some VICI commands legitimately return an empty response body.
+            PacketType::CmdResponse => Ok(resp.message.unwrap_or_default()),
+            PacketType::CmdUnknown => Err(Error::UnknownCommand(command.to_string())),
+            _ => Err(Error::Protocol("unexpected packet while awaiting response")),
         }
     }
 
@@ -215,15 +480,15 @@ impl Client {
     /// # }
     /// ```
     pub fn register_event(&mut self, name: &str) -> Result<()> {
-        let pkt = Packet {
-            ty: PacketType::EventRegister,
-            name: Some(name.to_string()),
-            message: None,
-        };
-        self.send_packet(&pkt)?;
-        let resp = self.recv_packet()?;
+        let pkt = Packet::new(PacketType::EventRegister, Some(name.to_string()), None);
+        let resp = self.request(&pkt, &CallOptions::new(), None)?;
         match resp.ty {
-            PacketType::EventConfirm => Ok(()),
+            PacketType::EventConfirm => {
+                if !self.is_registered(name) {
+                    self.events.push(name.to_string());
+                }
+                Ok(())
+            }
             PacketType::EventUnknown => Err(Error::Protocol("event registration failed")),
             _ => Err(Error::Protocol("unexpected packet after event register")),
         }
@@ -257,20 +522,38 @@ impl Client {
     /// # }
     /// ```
     pub fn unregister_event(&mut self, name: &str) -> Result<()> {
-        let pkt = Packet {
-            ty: PacketType::EventUnregister,
-            name: Some(name.to_string()),
-            message: None,
-        };
-        self.send_packet(&pkt)?;
-        let resp = self.recv_packet()?;
+        self.drain_aborted()?;
+        self.unregister(name)
+    }
+
+    fn unregister(&mut self, name: &str) -> Result<()> {
+        let pkt = Packet::new(PacketType::EventUnregister, Some(name.to_string()), None);
+        let resp = self.exchange(&pkt, &CallOptions::new(), None)?;
         match resp.ty {
-            PacketType::EventConfirm => Ok(()),
+            PacketType::EventConfirm => {
+                self.events.retain(|e| e != name);
+                Ok(())
+            }
             PacketType::EventUnknown => Err(Error::Protocol("event deregistration failed")),
             _ => Err(Error::Protocol("unexpected packet after event unregister")),
         }
     }
 
+    /// Names of the events this client is currently registered for.
+    pub fn registered_events(&self) -> &[String] {
+        &self.events
+    }
+
+    /// Events still registered only for a stream that was aborted.
+    pub(crate) fn aborted_events(&self) -> &[String] {
+        &self.aborted
+    }
+
+    /// Whether this client is currently registered for the event `name`.
+    pub fn is_registered(&self, name: &str) -> bool {
+        self.events.iter().any(|e| e == name)
+    }
+
     /// Execute a streaming command that yields multiple events before completing.
     ///
     /// Some VICI commands (like "list-sas", "list-conns") stream multiple event
@@ -305,39 +588,136 @@ impl Client {
         &mut self,
         command: &str,
         request: &Message,
+        on_event: F,
+    ) -> Result<Message>
+    where
+        F: FnMut(&str, &Message),
+    {
+        self.call_streaming_with(command, request, &CallOptions::new(), on_event)
+    }
+
+    /// Like [`call_streaming`](Self::call_streaming), with a deadline and/or
+    /// cancellation covering the whole stream.
+    pub fn call_streaming_with<F>(
+        &mut self,
+        command: &str,
+        request: &Message,
+        opts: &CallOptions,
         mut on_event: F,
     ) -> Result<Message>
     where
         F: FnMut(&str, &Message),
     {
-        let pkt = Packet {
-            ty: PacketType::CmdRequest,
-            name: Some(command.to_string()),
-            message: Some(request.clone()),
-        };
-        self.send_packet(&pkt)?;
+        let pkt = Packet::new(
+            PacketType::CmdRequest,
+            Some(command.to_string()),
+            Some(request.clone()),
+        );
+        let resp = self.request(&pkt, opts, Some(&mut on_event))?;
+        match resp.ty {
+            // Final response may be empty; surface it as an empty Message.
+            PacketType::CmdResponse => Ok(resp.message.unwrap_or_default()),
+            PacketType::CmdUnknown => Err(Error::UnknownCommand(command.to_string())),
+            _ => Err(Error::Protocol(
+                "unexpected packet while awaiting streamed response",
+            )),
+        }
+    }
 
-        loop {
-            let pkt = self.recv_packet()?;
-            match pkt.ty {
-                PacketType::Event => {
-                    if let (Some(name), Some(msg)) = (pkt.name.as_ref(), pkt.message.as_ref()) {
-                        on_event(name, msg);
-                    } else {
-                        return Err(Error::Protocol("event without name or message"));
-                    }
-                }
-                PacketType::CmdResponse => {
-                    // Final response may be empty; surface it as an empty Message.
-                    return Ok(pkt.message.unwrap_or_default());
+    /// Run `call` registered for `event`, as charon only streams to
+    /// registered clients.
+    ///
+    /// An existing registration is left in place; one made here is undone
+    /// afterwards, or before the next request if `call` timed out or was
+    /// cancelled while the stream was still coming in.
+    pub(crate) fn with_event<T, F>(&mut self, event: &str, call: F) -> Result<T>
+    where
+        F: FnOnce(&mut Self) -> Result<T>,
+    {
+        self.drain_aborted()?;
+        if self.is_registered(event) {
+            return call(self);
+        }
+        self.register_event(event)?;
+        let res = call(self);
+        match &res {
+            Err(Error::Timeout | Error::Cancelled) => self.aborted.push(event.to_string()),
+            Err(_) => {
+                // Keep the original error; a dead connection fails this too.
+                let _ = self.unregister(event);
+            }
+            Ok(_) => self.unregister(event)?,
+        }
+        res
+    }
+
+    /// Run streaming `command` registered for `event` (see
+    /// [`with_event`](Self::with_event)), passing each `event` to `on_event`.
+    ///
+    /// Other events arriving meanwhile are queued for
+    /// [`next_event`](Self::next_event), as during [`call`](Self::call).
+    pub(crate) fn stream_event<F>(
+        &mut self,
+        command: &str,
+        request: &Message,
+        event: &str,
+        mut on_event: F,
+    ) -> Result<Message>
+    where
+        F: FnMut(&Message),
+    {
+        let mut others = Vec::new();
+        let resp = self.with_event(event, |client| {
+            client.call_streaming(command, request, |name, msg| {
+                if name == event {
+                    on_event(msg);
+                } else {
+                    others.push((name.to_string(), msg.clone()));
                 }
-                PacketType::CmdUnknown => return Err(Error::UnknownCommand(command.to_string())),
-                _ => {
-                    return Err(Error::Protocol(
-                        "unexpected packet while awaiting streamed response",
-                    ))
+            })
+        });
+        for (name, msg) in others {
+            self.queue_event(name, msg);
+        }
+        resp
+    }
+
+    /// Unregister the events left over from aborted streams.
+    fn drain_aborted(&mut self) -> Result<()> {
+        while let Some(event) = self.aborted.first().cloned() {
+            // Streamed events still arriving are dropped until confirmed.
+            self.unregister(&event)?;
+            self.aborted.remove(0);
+        }
+        Ok(())
+    }
+
+    /// Run a streaming command and parse every `event` it emits into `out`.
+    ///
+    /// Parsing stops at the first error, which is returned once the final
+    /// response has been read so the connection stays in sync.
+    pub(crate) fn collect_streamed<T, F>(
+        &mut self,
+        command: &str,
+        request: &Message,
+        event: &str,
+        mut parse: F,
+    ) -> Result<Vec<T>>
+    where
+        F: FnMut(&Message, &mut Vec<T>) -> Result<()>,
+    {
+        let mut out = Vec::new();
+        let mut failure = None;
+        self.stream_event(command, request, event, |msg| {
+            if failure.is_none() {
+                if let Err(e) = parse(msg, &mut out) {
+                    failure = Some(e);
                 }
             }
+        })?;
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(out),
         }
     }
 
@@ -380,36 +760,44 @@ impl Client {
     /// # }
     /// ```
     pub fn next_event(&mut self) -> Result<(String, Message)> {
-        loop {
-            let pkt = match self.recv_packet() {
+        self.next_event_with(&CallOptions::new())
+    }
+
+    /// Like [`next_event`](Self::next_event), with a deadline and/or
+    /// cancellation for the wait.
+    pub fn next_event_with(&mut self, opts: &CallOptions) -> Result<(String, Message)> {
+        if let Some(event) = self.queued.pop_front() {
+            return Ok(event);
+        }
+        self.check_poisoned()?;
+        let prev = self.limit_reads(opts)?;
+        let res = loop {
+            let pkt = match self.recv_packet(opts) {
                 Ok(pkt) => pkt,
-                Err(e) => {
-                    // Convert I/O timeout errors to our Timeout error
-                    if let Error::Io(ref io_err) = e {
-                        if io_err.kind() == std::io::ErrorKind::TimedOut
-                            || io_err.kind() == std::io::ErrorKind::WouldBlock
-                        {
-                            return Err(Error::Timeout);
-                        }
-                    }
-                    return Err(e);
-                }
+                Err(e) => break Err(e),
             };
-
-            if let PacketType::Event = pkt.ty {
-                let name = pkt.name.ok_or(Error::Protocol("event without name"))?;
-                let msg = pkt
-                    .message
-                    .ok_or(Error::Protocol("event without message"))?;
-                return Ok((name, msg));
+            match pkt.ty {
+                PacketType::Event if self.is_aborted(pkt.name.as_deref()) => {}
+                PacketType::Event => {
+                    break match (pkt.name, pkt.message) {
+                        (Some(name), Some(msg)) => Ok((name, msg)),
+                        (None, _) => Err(Error::Protocol("event without name")),
+                        (_, None) => Err(Error::Protocol("event without message")),
+                    };
+                }
+                // A late reply to an aborted request.
+                _ if self.stale > 0 => self.stale -= 1,
+                _ => {}
             }
-        }
+        };
+        self.restore_reads(prev)?;
+        res
     }
 
     /// Try to receive the next event with a specific timeout.
     ///
-    /// This is a convenience method that temporarily sets the read timeout,
-    /// attempts to receive an event, and then restores the previous timeout.
+    /// The timeout covers the whole wait; the read timeout configured with
+    /// [`set_read_timeout`](Self::set_read_timeout) is left untouched.
     ///
     /// # Arguments
     ///
@@ -439,115 +827,229 @@ impl Client {
     /// # }
     /// ```
     pub fn try_next_event(&mut self, timeout: Duration) -> Result<(String, Message)> {
-        // Save current timeout
-        let previous_timeout = self.stream.read_timeout().ok().flatten();
+        self.next_event_with(&CallOptions::new().timeout(timeout))
+    }
+
+    /// Whether a framing error or partial write left the connection unusable.
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
+
+    /// Whether received data is buffered that has not been handed out yet.
+    ///
+    /// When multiplexing on [`as_raw_fd`](Self::as_raw_fd), check this before
+    /// waiting for the descriptor to become readable.
+    pub fn has_buffered_data(&self) -> bool {
+        self.reader.has_buffered()
+    }
 
-        // Set new timeout
-        self.set_read_timeout(Some(timeout))?;
+    fn check_poisoned(&self) -> Result<()> {
+        if self.poisoned {
+            Err(Error::Poisoned)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Send `pkt` and return its reply, passing events that arrive meanwhile
+    /// to `on_event`, or queueing them for [`next_event`](Self::next_event)
+    /// without a callback. Replies owed to earlier, aborted requests are
+    /// skipped.
+    ///
+    /// If the call is aborted after the request went out, its reply is
+    /// remembered as stale so the connection stays in sync.
+    fn request(
+        &mut self,
+        pkt: &Packet,
+        opts: &CallOptions,
+        on_event: Option<&mut EventCallback<'_>>,
+    ) -> Result<Packet> {
+        self.check_poisoned()?;
+        self.drain_aborted()?;
+        self.exchange(pkt, opts, on_event)
+    }
+
+    /// [`request`](Self::request) without unregistering aborted streams first.
+    fn exchange(
+        &mut self,
+        pkt: &Packet,
+        opts: &CallOptions,
+        mut on_event: Option<&mut EventCallback<'_>>,
+    ) -> Result<Packet> {
+        self.check_poisoned()?;
+        opts.check()?;
+        self.send_packet(pkt)?;
+
+        let prev = self.limit_reads(opts)?;
+        // A malformed event fails the call, but only once the reply is in so
+        // the connection stays in sync.
+        let mut failure = None;
+        let res = loop {
+            let resp = match self.recv_packet(opts) {
+                Ok(resp) => resp,
+                Err(e) => break Err(e),
+            };
+            if resp.ty == PacketType::Event {
+                if self.is_aborted(resp.name.as_deref()) {
+                    continue;
+                }
+                match (resp.name, resp.message, on_event.as_mut()) {
+                    (Some(name), Some(msg), Some(f)) => f(&name, &msg),
+                    (Some(name), Some(msg), None) => self.queue_event(name, msg),
+                    (_, _, Some(_)) if failure.is_none() => {
+                        failure = Some(Error::Protocol("event without name or message"));
+                    }
+                    _ => {}
+                }
+            } else if self.stale > 0 {
+                self.stale -= 1;
+            } else {
+                break failure.map_or(Ok(resp), Err);
+            }
+        };
+        if let Err(Error::Timeout | Error::Cancelled) = res {
+            self.stale += 1;
+        }
+        self.restore_reads(prev)?;
+        res
+    }
+
+    fn is_aborted(&self, event: Option<&str>) -> bool {
+        event.map_or(false, |e| self.aborted.iter().any(|a| a == e))
+    }
+
+    fn queue_event(&mut self, name: String, msg: Message) {
+        if self.queue_cap == 0 {
+            self.dropped += 1;
+            return;
+        }
+        if self.queued.len() == self.queue_cap {
+            self.queued.pop_front();
+            self.dropped += 1;
+        }
+        self.queued.push_back((name, msg));
+    }
 
-        // Try to get next event
-        let result = self.next_event();
+    /// Number of unsolicited events discarded while awaiting replies, either
+    /// because the event buffer was full or none was configured (see
+    /// [`ClientBuilder::event_buffer`]).
+    pub fn dropped_events(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Record every packet sent and received from now on to `recorder`,
+    /// replacing an earlier one.
+    pub fn record(&mut self, recorder: Recorder) {
+        self.recorder = Some(recorder);
+    }
+
+    /// Stop recording; returns the recorder to [`finish`](Recorder::finish).
+    pub fn stop_recording(&mut self) -> Option<Recorder> {
+        self.recorder.take()
+    }
 
-        // Restore previous timeout
-        self.set_read_timeout(previous_timeout)?;
+    /// Remember the socket read timeout if `opts` will override it.
+    fn limit_reads(&self, opts: &CallOptions) -> Result<Option<Option<Duration>>> {
+        if opts.is_limited() {
+            Ok(Some(self.stream.read_timeout()?))
+        } else {
+            Ok(None)
+        }
+    }
 
-        result
+    fn restore_reads(&self, prev: Option<Option<Duration>>) -> Result<()> {
+        if let Some(to) = prev {
+            self.stream.set_read_timeout(to)?;
+        }
+        Ok(())
     }
 
     /// Send a packet (encodes transport frame).
+    ///
+    /// A write that fails after part of the frame went out poisons the
+    /// connection, as the daemon would misread everything that follows.
     fn send_packet(&mut self, pkt: &Packet) -> Result<()> {
-        let mut data = Vec::new();
-        data.push(pkt.ty as u8);
-        if pkt.ty.is_named() {
-            let name = pkt
-                .name
-                .as_ref()
-                .ok_or(Error::Protocol("named packet missing name"))?;
-            encode_name(&mut data, name)?;
-        }
-        if let Some(msg) = &pkt.message {
-            let bytes = msg.encode()?;
-            data.extend_from_slice(&bytes);
-        }
-        let len = data.len();
-        if len > (512 * 1024) {
+        let frame = pkt.encode()?;
+        if frame.len() - 4 > self.max_frame {
             return Err(Error::TooLong("packet"));
         }
-        let mut frame = Vec::with_capacity(4 + len);
-        frame.extend_from_slice(&(len as u32).to_be_bytes());
-        frame.extend_from_slice(&data);
-        self.stream.write_all(&frame)?;
+        let mut written = 0;
+        while written < frame.len() {
+            match self.stream.write(&frame[written..]) {
+                Ok(0) => {
+                    self.poisoned = written > 0;
+                    return Err(Error::Io(io::ErrorKind::WriteZero.into()));
+                }
+                Ok(n) => written += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.poisoned = written > 0;
+                    return Err(if is_timeout(&e) && written == 0 {
+                        Error::Timeout
+                    } else {
+                        Error::Io(e)
+                    });
+                }
+            }
+        }
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(Direction::Sent, &frame[4..]);
+        }
         Ok(())
     }
 
     /// Receive the *next* packet from the stream (decodes one transport frame).
-    fn recv_packet(&mut self) -> Result<Packet> {
-        // Read 4-byte length header (big endian)
-        let mut len_hdr = [0u8; 4];
-        self.stream.read_exact(&mut len_hdr)?;
-        let len = u32::from_be_bytes(len_hdr) as usize;
-        if len > (512 * 1024) {
-            return Err(Error::TooLong("frame"));
-        }
-        let mut buf = vec![0u8; len];
-        self.stream.read_exact(&mut buf)?;
-        // Parse packet
-        let (ty_u8, mut rest) = decode_u8(&buf)?;
-        let ty = match ty_u8 {
-            0 => PacketType::CmdRequest,
-            1 => PacketType::CmdResponse,
-            2 => PacketType::CmdUnknown,
-            3 => PacketType::EventRegister,
-            4 => PacketType::EventUnregister,
-            5 => PacketType::EventConfirm,
-            6 => PacketType::EventUnknown,
-            7 => PacketType::Event,
-            _ => return Err(Error::Protocol("unknown packet type")),
-        };
-        let name = if ty.is_named() {
-            let (nm, r) = decode_name(rest)?;
-            rest = r;
-            Some(nm)
-        } else {
-            None
-        };
-        let message = if !rest.is_empty() {
-            Some(Message::decode(rest)?)
-        } else {
-            None
-        };
-        Ok(Packet { ty, name, message })
-    }
-}
-
-// --- Small local helpers (mirror what's in wire.rs but private here) ---
-
-fn decode_u8(input: &[u8]) -> Result<(u8, &[u8])> {
-    if input.is_empty() {
-        return Err(Error::Protocol("unexpected EOF reading u8"));
+    ///
+    /// Partial frames survive timeouts in the frame reader, so an interrupted
+    /// read can simply be retried.
+    fn recv_packet(&mut self, opts: &CallOptions) -> Result<Packet> {
+        loop {
+            match self.reader.next_frame() {
+                Ok(Some(frame)) => {
+                    if let Some(recorder) = &mut self.recorder {
+                        recorder.record(Direction::Received, &frame);
+                    }
+                    return Packet::decode(&frame);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    self.poisoned = true;
+                    return Err(e);
+                }
+            }
+            if let Some(wait) = opts.next_wait()? {
+                self.stream.set_read_timeout(Some(wait))?;
+            }
+            let mut chunk = [0u8; 8192];
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Err(Error::Io(io::ErrorKind::UnexpectedEof.into())),
+                Ok(n) => self.reader.push(&chunk[..n]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                // With limits, loop around so next_wait() decides what's next.
+                Err(e) if is_timeout(&e) && opts.is_limited() => {}
+                Err(e) if is_timeout(&e) => return Err(Error::Timeout),
+                Err(e) => return Err(Error::Io(e)),
+            }
+        }
     }
-    Ok((input[0], &input[1..]))
 }
 
-fn decode_name(input: &[u8]) -> Result<(String, &[u8])> {
-    if input.is_empty() {
-        return Err(Error::Protocol("unexpected EOF reading name length"));
-    }
-    let len = input[0] as usize;
-    let input = &input[1..];
-    if input.len() < len {
-        return Err(Error::Protocol("unexpected EOF reading name bytes"));
-    }
-    let name = String::from_utf8(input[..len].to_vec())?;
-    Ok((name, &input[len..]))
+fn is_timeout(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+    )
 }
 
-fn encode_name(out: &mut Vec<u8>, name: &str) -> Result<()> {
-    let bytes = name.as_bytes();
-    if bytes.len() > u8::MAX as usize {
-        return Err(Error::TooLong("packet name"));
+/// Interpret the common `success`/`errmsg` reply of a command.
+///
+/// Returns the reply tree on success and `Error::CommandFailed` otherwise.
+pub(crate) fn expect_success(resp: &Message) -> Result<Section> {
+    let tree = resp.tree()?;
+    match tree.flag("success") {
+        Some(true) => Ok(tree),
+        _ => Err(Error::CommandFailed(
+            tree.str("errmsg").unwrap_or("unknown error").to_string(),
+        )),
     }
-    out.push(bytes.len() as u8);
-    out.extend_from_slice(bytes);
-    Ok(())
 }
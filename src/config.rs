@@ -0,0 +1,816 @@
+//! `swanctl.conf` parsing and `swanctl --load-all`.
+//!
+//! [`SwanctlConf`] reads the strongSwan settings syntax (`name { key = value }`
+//! with `#` comments and `include` statements) and converts its
+//! `connections`, `pools` and `secrets` sections into the messages swanctl
+//! would send. Certificates and private keys are taken from the usual
+//! directories next to the file (`x509/`, `x509ca/`, `private/`, ...).
+//!
+//! Section references (`name : base { }`) are not supported.
+//!
+//! ```no_run
+//! use rustici::config::SwanctlConf;
+//! use rustici::Client;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let conf = SwanctlConf::load("/etc/swanctl/swanctl.conf")?;
+//! let mut client = Client::connect(rustici::client::DEFAULT_SOCKET)?;
+//! let summary = client.load_all(&conf)?;
+//! println!("loaded {} connections", summary.conns.loaded);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::client::{expect_success, Client};
+use crate::creds::{CertFlag, CertType, KeyType, SharedSecret, SharedType};
+use crate::error::{Error, Result};
+use crate::wire::{Message, Section, Value};
+
+/// Default location of `swanctl.conf`.
+pub const DEFAULT_CONF: &str = "/etc/swanctl/swanctl.conf";
+
+/// Connection keys whose comma-separated values swanctl sends as lists.
+const LIST_KEYS: &[&str] = &[
+    "local_addrs",
+    "remote_addrs",
+    "proposals",
+    "esp_proposals",
+    "ah_proposals",
+    "local_ts",
+    "remote_ts",
+    "vips",
+    "pools",
+    "groups",
+    "cert_policy",
+];
+
+/// Authentication keys naming files, with the directory relative paths are
+/// resolved against.
+const FILE_KEYS: &[(&str, &str)] = &[
+    ("certs", "x509"),
+    ("cacerts", "x509ca"),
+    ("pubkeys", "pubkey"),
+];
+
+/// Certificate directories and what their files are loaded as.
+const CERT_DIRS: &[(&str, CertType, Option<CertFlag>)] = &[
+    ("x509", CertType::X509, None),
+    ("x509ca", CertType::X509, Some(CertFlag::Ca)),
+    ("x509aa", CertType::X509, Some(CertFlag::Aa)),
+    ("x509ocsp", CertType::X509, Some(CertFlag::Ocsp)),
+    ("x509crl", CertType::X509Crl, None),
+    ("x509ac", CertType::X509Ac, None),
+    ("pubkey", CertType::Pubkey, None),
+];
+
+/// Private key directories and the key type of their files.
+const KEY_DIRS: &[(&str, KeyType)] = &[
+    ("private", KeyType::Any),
+    ("rsa", KeyType::Rsa),
+    ("ecdsa", KeyType::Ecdsa),
+    ("pkcs8", KeyType::Any),
+];
+
+/// A parsed setting: a value or a nested section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Value(String),
+    Section(Settings),
+}
+
+/// Ordered settings of one section; later definitions override earlier ones
+/// and sections of the same name are merged, as in strongSwan.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct Settings {
+    entries: Vec<(String, Node)>,
+}
+
+impl Settings {
+    fn get(&self, key: &str) -> Option<&Node> {
+        self.entries.iter().find(|(k, _)| k == key).map(|(_, n)| n)
+    }
+
+    fn value(&self, key: &str) -> Option<&str> {
+        match self.get(key)? {
+            Node::Value(v) => Some(v),
+            Node::Section(_) => None,
+        }
+    }
+
+    fn section(&self, key: &str) -> Option<&Settings> {
+        match self.get(key)? {
+            Node::Section(s) => Some(s),
+            Node::Value(_) => None,
+        }
+    }
+
+    fn sections(&self) -> impl Iterator<Item = (&str, &Settings)> {
+        self.entries.iter().filter_map(|(k, n)| match n {
+            Node::Section(s) => Some((k.as_str(), s)),
+            Node::Value(_) => None,
+        })
+    }
+
+    fn set(&mut self, key: &str, value: String) {
+        match self.entries.iter_mut().find(|(k, _)| k == key) {
+            Some((_, node)) => *node = Node::Value(value),
+            None => self.entries.push((key.to_string(), Node::Value(value))),
+        }
+    }
+
+    fn section_mut(&mut self, key: &str) -> &mut Settings {
+        let pos = match self.entries.iter().position(|(k, _)| k == key) {
+            Some(pos) => {
+                if let Node::Value(_) = self.entries[pos].1 {
+                    self.entries[pos].1 = Node::Section(Settings::default());
+                }
+                pos
+            }
+            None => {
+                self.entries
+                    .push((key.to_string(), Node::Section(Settings::default())));
+                self.entries.len() - 1
+            }
+        };
+        match &mut self.entries[pos].1 {
+            Node::Section(s) => s,
+            Node::Value(_) => unreachable!(),
+        }
+    }
+}
+
+/// A parsed `swanctl.conf`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwanctlConf {
+    root: Settings,
+    dir: PathBuf,
+}
+
+impl SwanctlConf {
+    /// Parse configuration text; relative paths (includes, credential files)
+    /// are resolved against `dir`.
+    pub fn parse<P: AsRef<Path>>(text: &str, dir: P) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        let mut root = Settings::default();
+        Parser::new(text, &dir, 0).parse_into(&mut root)?;
+        Ok(Self { root, dir })
+    }
+
+    /// Read and parse the file at `path`, following includes.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let dir = path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .to_path_buf();
+        Self::parse(&fs::read_to_string(path)?, dir).map_err(|e| in_file(e, path))
+    }
+
+    /// Directory credential files are looked up in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Names of the configured connections.
+    pub fn conn_names(&self) -> Vec<String> {
+        self.names("connections")
+    }
+
+    /// `load-conn` messages for all connections, with their names.
+    ///
+    /// Files named by `certs`, `cacerts` and `pubkeys` are read and embedded.
+    pub fn connections(&self) -> Result<Vec<(String, Message)>> {
+        let conns = match self.root.section("connections") {
+            Some(conns) => conns,
+            None => return Ok(Vec::new()),
+        };
+        conns
+            .sections()
+            .map(|(name, conn)| {
+                let mut root = Section::new();
+                root.insert(name, Value::Section(self.conn_section(conn)?));
+                Ok((name.to_string(), root.to_message()))
+            })
+            .collect()
+    }
+
+    /// Names of the configured pools.
+    pub fn pool_names(&self) -> Vec<String> {
+        self.names("pools")
+    }
+
+    /// `load-pool` messages for all pools, with their names.
+    pub fn pools(&self) -> Vec<(String, Message)> {
+        let pools = match self.root.section("pools") {
+            Some(pools) => pools,
+            None => return Vec::new(),
+        };
+        pools
+            .sections()
+            .map(|(name, pool)| {
+                let mut sec = Section::new();
+                for (key, node) in &pool.entries {
+                    if let Node::Value(v) = node {
+                        if key == "addrs" {
+                            sec.insert_str(key.clone(), v);
+                        } else {
+                            sec.insert_str_list(key.clone(), split_list(v));
+                        }
+                    }
+                }
+                let mut root = Section::new();
+                root.insert(name, Value::Section(sec));
+                (name.to_string(), root.to_message())
+            })
+            .collect()
+    }
+
+    /// Shared secrets from `secrets` (`ike*`, `eap*`, `xauth*`, `ntlm*` and
+    /// `ppk*` sections), with their section name, which is also their ID.
+    ///
+    /// Other sections, e.g. passphrases for encrypted private keys, are
+    /// skipped.
+    pub fn shared_secrets(&self) -> Result<Vec<(String, SharedSecret)>> {
+        let secrets = match self.root.section("secrets") {
+            Some(secrets) => secrets,
+            None => return Ok(Vec::new()),
+        };
+        let mut out = Vec::new();
+        for (name, sec) in secrets.sections() {
+            let ty = match shared_type(name) {
+                Some(ty) => ty,
+                None => continue,
+            };
+            let data = decode_secret(
+                sec.value("secret")
+                    .ok_or(Error::Config("shared secret without `secret`".into()))?,
+            )?;
+            let mut secret = SharedSecret::new(ty, data).id(name);
+            for (key, node) in &sec.entries {
+                if let (true, Node::Value(id)) = (key.starts_with("id"), node) {
+                    secret = secret.owner(id.clone());
+                }
+            }
+            out.push((name.to_string(), secret));
+        }
+        Ok(out)
+    }
+
+    /// Certificate files in the credential directories, with how to load
+    /// them, sorted by path.
+    pub fn cert_files(&self) -> Vec<(PathBuf, CertType, Option<CertFlag>)> {
+        CERT_DIRS
+            .iter()
+            .flat_map(|&(dir, ty, flag)| {
+                files_in(&self.dir.join(dir))
+                    .into_iter()
+                    .map(move |path| (path, ty, flag))
+            })
+            .collect()
+    }
+
+    /// Private key files in the credential directories, with their key type,
+    /// sorted by path.
+    pub fn key_files(&self) -> Vec<(PathBuf, KeyType)> {
+        KEY_DIRS
+            .iter()
+            .flat_map(|&(dir, ty)| {
+                files_in(&self.dir.join(dir))
+                    .into_iter()
+                    .map(move |path| (path, ty))
+            })
+            .collect()
+    }
+
+    fn names(&self, section: &str) -> Vec<String> {
+        self.root
+            .section(section)
+            .map(|s| s.sections().map(|(name, _)| name.to_string()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Convert a connection (or nested) section as swanctl does.
+    fn conn_section(&self, settings: &Settings) -> Result<Section> {
+        let mut sec = Section::new();
+        for (key, node) in &settings.entries {
+            match node {
+                Node::Section(s) => sec.insert(key.clone(), Value::Section(self.conn_section(s)?)),
+                Node::Value(v) => {
+                    if let Some((_, dir)) = FILE_KEYS.iter().find(|(k, _)| k == key) {
+                        let files = split_list(v)
+                            .into_iter()
+                            .map(|file| Ok(fs::read(self.dir.join(dir).join(file))?))
+                            .collect::<Result<_>>()?;
+                        sec.insert(key.clone(), Value::List(files));
+                    } else if LIST_KEYS.contains(&key.as_str()) {
+                        sec.insert_str_list(key.clone(), split_list(v));
+                    } else {
+                        sec.insert_str(key.clone(), v);
+                    }
+                }
+            }
+        }
+        Ok(sec)
+    }
+}
+
+fn split_list(v: &str) -> Vec<&str> {
+    v.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+fn shared_type(section: &str) -> Option<SharedType> {
+    const PREFIXES: &[(&str, SharedType)] = &[
+        ("ike", SharedType::Ike),
+        ("eap", SharedType::Eap),
+        ("xauth", SharedType::Xauth),
+        ("ntlm", SharedType::Ntlm),
+        ("ppk", SharedType::Ppk),
+    ];
+    PREFIXES
+        .iter()
+        .find(|(prefix, _)| section.starts_with(prefix))
+        .map(|&(_, ty)| ty)
+}
+
+/// Decode a secret: `0x` prefixes hex, `0s` base64, anything else is taken
+/// literally.
+fn decode_secret(v: &str) -> Result<Vec<u8>> {
+    if let Some(hex) = v.strip_prefix("0x") {
+        if hex.len() % 2 != 0 {
+            return Err(Error::Config("odd-length hex secret".into()));
+        }
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| {
+                u8::from_str_radix(hex.get(i..i + 2).unwrap_or("x"), 16)
+                    .map_err(|_| Error::Config("invalid hex secret".into()))
+            })
+            .collect()
+    } else if let Some(b64) = v.strip_prefix("0s") {
+        decode_base64(b64)
+    } else {
+        Ok(v.as_bytes().to_vec())
+    }
+}
+
+pub(crate) fn decode_base64(s: &str) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len() / 4 * 3);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for c in s.bytes().filter(|c| !c.is_ascii_whitespace() && *c != b'=') {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return Err(Error::Config("invalid base64 secret".into())),
+        };
+        acc = (acc << 6) | u32::from(v);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    Ok(out)
+}
+
+/// Regular files in `dir`, sorted; a missing directory yields none.
+fn files_in(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<_> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.is_file())
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+/// Match `name` against a shell pattern with `*` and `?`.
+fn glob_match(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => {
+            glob_match(&pattern[1..], name) || (!name.is_empty() && glob_match(pattern, &name[1..]))
+        }
+        (Some(b'?'), Some(_)) => glob_match(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) if p == n => glob_match(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
+
+/// Prefix a configuration error with the file it occurred in.
+fn in_file(e: Error, path: &Path) -> Error {
+    match e {
+        Error::Config(msg) => Error::Config(format!("{}: {msg}", path.display())),
+        e => e,
+    }
+}
+
+/// Nested includes beyond this depth are assumed to be a loop.
+const MAX_INCLUDE_DEPTH: usize = 10;
+
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+    dir: &'a Path,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str, dir: &'a Path, depth: usize) -> Self {
+        Self {
+            input: text.as_bytes(),
+            pos: 0,
+            dir,
+            depth,
+        }
+    }
+
+    /// A [`Error::Config`] for the current line.
+    fn error(&self, what: &str) -> Error {
+        let line = self.input[..self.pos]
+            .iter()
+            .filter(|&&c| c == b'\n')
+            .count()
+            + 1;
+        Error::Config(format!("line {line}: {what}"))
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    /// Skip whitespace and comments, including newlines if `newlines`.
+    fn skip(&mut self, newlines: bool) {
+        while let Some(c) = self.peek() {
+            match c {
+                b'#' => {
+                    while !matches!(self.peek(), None | Some(b'\n')) {
+                        self.pos += 1;
+                    }
+                }
+                b'\n' if !newlines => return,
+                c if c.is_ascii_whitespace() => self.pos += 1,
+                _ => return,
+            }
+        }
+    }
+
+    fn word(&mut self) -> &'a str {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_whitespace() || b"{}=:#\"".contains(&c) {
+                break;
+            }
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.input[start..self.pos]).unwrap_or_default()
+    }
+
+    /// Parse entries into `into` until the end of input.
+    fn parse_into(&mut self, into: &mut Settings) -> Result<()> {
+        if self.parse_section(into)? {
+            return Err(self.error("unbalanced `}` in configuration"));
+        }
+        Ok(())
+    }
+
+    /// Parse entries until `}` (returns true) or the end of input (false).
+    fn parse_section(&mut self, into: &mut Settings) -> Result<bool> {
+        loop {
+            self.skip(true);
+            match self.peek() {
+                None => return Ok(false),
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(true);
+                }
+                _ => {}
+            }
+            let name = self.word();
+            if name.is_empty() {
+                return Err(self.error("expected a setting name"));
+            }
+            self.skip(false);
+            match self.peek() {
+                Some(b'{') => {
+                    self.pos += 1;
+                    if !self.parse_section(into.section_mut(name))? {
+                        return Err(self.error("unterminated section in configuration"));
+                    }
+                }
+                Some(b'=') => {
+                    self.pos += 1;
+                    self.skip(false);
+                    let value = self.value()?;
+                    into.set(name, value);
+                }
+                Some(b':') => return Err(self.error("section references are not supported")),
+                _ if name == "include" => {
+                    let pattern = self.value()?;
+                    self.include(&pattern, into)?;
+                }
+                _ => return Err(self.error("expected `{` or `=` in configuration")),
+            }
+        }
+    }
+
+    /// A quoted string or the rest of the line (up to a comment or `}`).
+    fn value(&mut self) -> Result<String> {
+        if self.peek() == Some(b'"') {
+            self.pos += 1;
+            let mut out = Vec::new();
+            loop {
+                match self.peek() {
+                    None => return Err(self.error("unterminated string in configuration")),
+                    Some(b'"') => {
+                        self.pos += 1;
+                        break;
+                    }
+                    Some(b'\\') => {
+                        self.pos += 1;
+                        let c = self
+                            .peek()
+                            .ok_or_else(|| self.error("unterminated string"))?;
+                        out.push(match c {
+                            b'n' => b'\n',
+                            b't' => b'\t',
+                            b'r' => b'\r',
+                            c => c,
+                        });
+                        self.pos += 1;
+                    }
+                    Some(c) => {
+                        out.push(c);
+                        self.pos += 1;
+                    }
+                }
+            }
+            return Ok(String::from_utf8(out)?);
+        }
+        let start = self.pos;
+        while !matches!(self.peek(), None | Some(b'\n' | b'#' | b'}')) {
+            self.pos += 1;
+        }
+        Ok(std::str::from_utf8(&self.input[start..self.pos])
+            .map_err(|_| self.error("configuration is not UTF-8"))?
+            .trim()
+            .to_string())
+    }
+
+    /// Parse the files matching `pattern` (`*`/`?` in the file name only)
+    /// into `into`, in sorted order.
+    fn include(&mut self, pattern: &str, into: &mut Settings) -> Result<()> {
+        if self.depth >= MAX_INCLUDE_DEPTH {
+            return Err(self.error("includes nested too deeply"));
+        }
+        let pattern = self.dir.join(pattern);
+        let dir = pattern.parent().unwrap_or_else(|| Path::new("."));
+        let file = pattern
+            .file_name()
+            .and_then(|f| f.to_str())
+            .ok_or_else(|| self.error("invalid include pattern"))?;
+        let paths = if file.contains(['*', '?']) {
+            files_in(dir)
+                .into_iter()
+                .filter(|p| {
+                    p.file_name()
+                        .and_then(|f| f.to_str())
+                        .map_or(false, |f| glob_match(file.as_bytes(), f.as_bytes()))
+                })
+                .collect()
+        } else {
+            vec![pattern.clone()]
+        };
+        for path in paths {
+            let text = fs::read_to_string(&path)?;
+            let dir = path.parent().unwrap_or_else(|| Path::new("."));
+            Parser::new(&text, dir, self.depth + 1)
+                .parse_into(into)
+                .map_err(|e| in_file(e, &path))?;
+        }
+        Ok(())
+    }
+}
+
+/// Loaded, unloaded and failed counts for one kind of object.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadCount {
+    /// Successfully loaded.
+    pub loaded: usize,
+    /// Unloaded because they are no longer configured.
+    pub unloaded: usize,
+    /// Failed to load or unload, with the reason.
+    pub failed: Vec<(String, String)>,
+}
+
+/// Outcome of [`Client::load_all`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadSummary {
+    /// Certificates.
+    pub certs: LoadCount,
+    /// Private keys.
+    pub keys: LoadCount,
+    /// Shared secrets.
+    pub shared: LoadCount,
+    /// Pools.
+    pub pools: LoadCount,
+    /// Connections.
+    pub conns: LoadCount,
+}
+
+impl LoadSummary {
+    /// Whether everything loaded and unloaded cleanly.
+    pub fn is_ok(&self) -> bool {
+        [
+            &self.certs,
+            &self.keys,
+            &self.shared,
+            &self.pools,
+            &self.conns,
+        ]
+        .iter()
+        .all(|c| c.failed.is_empty())
+    }
+}
+
+impl LoadCount {
+    /// Count `res`; command failures are recorded, other errors abort.
+    pub(crate) fn track(&mut self, name: &str, res: Result<()>, unload: bool) -> Result<()> {
+        match res {
+            Ok(()) if unload => self.unloaded += 1,
+            Ok(()) => self.loaded += 1,
+            Err(Error::CommandFailed(msg)) => self.failed.push((name.to_string(), msg)),
+            Err(e) => return Err(e),
+        }
+        Ok(())
+    }
+}
+
+impl Client {
+    /// Load everything in `conf` like `swanctl --load-all`: credentials,
+    /// shared secrets, pools and connections, unloading shared secrets,
+    /// pools and connections that are no longer configured.
+    ///
+    /// Commands charon rejects are reported in the summary; I/O and protocol
+    /// errors abort.
+    pub fn load_all(&mut self, conf: &SwanctlConf) -> Result<LoadSummary> {
+        let mut summary = LoadSummary::default();
+
+        for (path, ty, flag) in conf.cert_files() {
+            let res = self.load_cert_file(ty, flag, &path);
+            summary
+                .certs
+                .track(&path.display().to_string(), res, false)?;
+        }
+        for (path, ty) in conf.key_files() {
+            let res = self.load_key_file(ty, &path).map(|_| ());
+            summary
+                .keys
+                .track(&path.display().to_string(), res, false)?;
+        }
+
+        let secrets = conf.shared_secrets()?;
+        let mut wanted = BTreeSet::new();
+        for (id, secret) in &secrets {
+            let res = self.load_shared(secret);
+            summary.shared.track(id, res, false)?;
+            wanted.insert(id.as_str());
+        }
+        for id in self.get_shared()? {
+            if !wanted.contains(id.as_str()) {
+                let res = self.unload_shared(&id);
+                summary.shared.track(&id, res, true)?;
+            }
+        }
+
+        let pools = conf.pools();
+        for (name, msg) in &pools {
+            let res = self
+                .call("load-pool", msg)
+                .and_then(|r| expect_success(&r).map(|_| ()));
+            summary.pools.track(name, res, false)?;
+        }
+        for pool in self.get_pools(None, false)? {
+            if !pools.iter().any(|(name, _)| *name == pool.name) {
+                let res = self.unload_pool(&pool.name);
+                summary.pools.track(&pool.name, res, true)?;
+            }
+        }
+
+        let conns = conf.connections()?;
+        for (name, msg) in &conns {
+            let res = self
+                .call("load-conn", msg)
+                .and_then(|r| expect_success(&r).map(|_| ()));
+            summary.conns.track(name, res, false)?;
+        }
+        for name in self.get_conns()? {
+            if !conns.iter().any(|(n, _)| *n == name) {
+                let res = self.unload_conn(&name);
+                summary.conns.track(&name, res, true)?;
+            }
+        }
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONF: &str = r#"
+# gateway
+connections {
+    gw {
+        version = 2
+        local_addrs = 192.168.0.1, 192.168.0.2   # two uplinks
+        proposals = aes256-sha256-modp2048
+        local { auth = psk
+        }
+        children {
+            net {
+                local_ts = 10.1.0.0/16
+                updown = "/usr/local/bin/updown --tag \"gw\""
+            }
+        }
+    }
+}
+pools { rw { addrs = 10.3.0.0/24
+    dns = 10.1.0.53, 10.1.0.54 } }
+secrets {
+    ike-gw {
+        id = moon
+        id-2 = sun
+        secret = 0x736563726574
+    }
+    eap-carol { secret = 0sc2VjcmV0 }
+    private-moon { file = moon.pem }
+}
+connections { gw { version = 1 } }
+"#;
+
+    #[test]
+    fn parses_and_converts_like_swanctl() {
+        let conf = SwanctlConf::parse(CONF, "/nonexistent").unwrap();
+        assert_eq!(conf.conn_names(), ["gw"]);
+
+        let conns = conf.connections().unwrap();
+        let tree = conns[0].1.tree().unwrap();
+        let gw = tree.section("gw").unwrap();
+        // The later definition overrides the earlier one.
+        assert_eq!(gw.str("version"), Some("1"));
+        assert_eq!(gw.str_list("local_addrs"), ["192.168.0.1", "192.168.0.2"]);
+        assert_eq!(gw.str_list("proposals"), ["aes256-sha256-modp2048"]);
+        assert_eq!(gw.section("local").unwrap().str("auth"), Some("psk"));
+        let net = gw.section("children").unwrap().section("net").unwrap();
+        assert_eq!(net.str_list("local_ts"), ["10.1.0.0/16"]);
+        assert_eq!(
+            net.str("updown"),
+            Some("/usr/local/bin/updown --tag \"gw\"")
+        );
+
+        let pools = conf.pools();
+        let rw = pools[0].1.tree().unwrap();
+        let rw = rw.section("rw").unwrap();
+        assert_eq!(rw.str("addrs"), Some("10.3.0.0/24"));
+        assert_eq!(rw.str_list("dns"), ["10.1.0.53", "10.1.0.54"]);
+
+        let secrets = conf.shared_secrets().unwrap();
+        assert_eq!(secrets.len(), 2);
+        assert_eq!(secrets[0].0, "ike-gw");
+        let ike = secrets[0].1.to_message().tree().unwrap();
+        assert_eq!(ike.str("id"), Some("ike-gw"));
+        assert_eq!(ike.str("type"), Some("IKE"));
+        assert_eq!(ike.str("data"), Some("secret"));
+        assert_eq!(ike.str_list("owners"), ["moon", "sun"]);
+        let eap = secrets[1].1.to_message().tree().unwrap();
+        assert_eq!(eap.str("data"), Some("secret"));
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert!(SwanctlConf::parse("connections {", ".").is_err());
+        assert!(SwanctlConf::parse("}", ".").is_err());
+        assert!(SwanctlConf::parse("a : b { }", ".").is_err());
+        let err = SwanctlConf::parse("connections {\n  gw {\n    version\n", ".").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid configuration: line 3: expected `{` or `=` in configuration"
+        );
+        assert!(glob_match(b"*.conf", b"gw.conf"));
+        assert!(!glob_match(b"*.conf", b"gw.conf.bak"));
+    }
+}
@@ -0,0 +1,1187 @@
+//! Typed connection configuration: `load-conn` and `list-conns`.
+//!
+//! [`ConnectionConfig`] covers the `load-conn` schema (the same one
+//! `swanctl.conf` uses for its `connections` section), so key names are
+//! checked by the compiler instead of by charon at runtime. The
+//! [`ConnDescription`] family is the read side, parsed from the `list-conn`
+//! events streamed by `list-conns`.
+//!
+//! ```no_run
+//! use rustici::Client;
+//! use rustici::conn::{AuthConfig, ChildConfig, ConnectionConfig, StartAction};
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let conn = ConnectionConfig::new("gw")
+//!     .version(2)
+//!     .local_addr("192.0.2.1")
+//!     .remote_addr("198.51.100.1")
+//!     .proposal("aes256-sha256-x25519")
+//!     .local_auth(AuthConfig::new().auth("psk").id("gw.example.org"))
+//!     .remote_auth(AuthConfig::new().auth("psk").id("peer.example.org"))
+//!     .child(
+//!         ChildConfig::new("net")
+//!             .esp_proposal("aes256gcm16")
+//!             .local_ts("10.1.0.0/16")
+//!             .remote_ts("10.2.0.0/16")
+//!             .start_action(StartAction::Trap),
+//!     );
+//!
+//! let mut client = Client::connect("/var/run/charon.vici")?;
+//! client.load_conn(&conn)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::client::{expect_success, Client};
+use crate::error::{Error, Result};
+use crate::wire::{Message, Section, Value};
+
+/// What to do with a CHILD_SA once its configuration is loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartAction {
+    /// Do nothing.
+    None,
+    /// Install a trap policy that triggers the tunnel on demand.
+    Trap,
+    /// Initiate the CHILD_SA immediately.
+    Start,
+    /// Install a trap policy and initiate immediately.
+    TrapStart,
+}
+
+/// What to do when the peer closes a CHILD_SA (also used for DPD timeouts).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseAction {
+    /// Do nothing / clear the SA.
+    None,
+    /// Install a trap policy to re-establish on demand.
+    Trap,
+    /// Re-initiate immediately.
+    Start,
+}
+
+/// IPsec mode of a CHILD_SA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChildMode {
+    /// Tunnel mode.
+    Tunnel,
+    /// Transport mode.
+    Transport,
+    /// Bound End-to-End Tunnel mode.
+    Beet,
+    /// Bypass policy.
+    Pass,
+    /// Drop policy.
+    Drop,
+}
+
+impl StartAction {
+    /// The keyword charon uses for this action.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StartAction::None => "none",
+            StartAction::Trap => "trap",
+            StartAction::Start => "start",
+            StartAction::TrapStart => "trap|start",
+        }
+    }
+}
+
+impl CloseAction {
+    /// The keyword charon uses for this action.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CloseAction::None => "none",
+            CloseAction::Trap => "trap",
+            CloseAction::Start => "start",
+        }
+    }
+}
+
+impl ChildMode {
+    /// The keyword charon uses for this mode.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChildMode::Tunnel => "tunnel",
+            ChildMode::Transport => "transport",
+            ChildMode::Beet => "beet",
+            ChildMode::Pass => "pass",
+            ChildMode::Drop => "drop",
+        }
+    }
+}
+
+impl FromStr for StartAction {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" | "add" => Ok(StartAction::None),
+            "trap" | "route" => Ok(StartAction::Trap),
+            "start" => Ok(StartAction::Start),
+            "trap|start" | "start|trap" => Ok(StartAction::TrapStart),
+            _ => Err(Error::Protocol("unknown start action")),
+        }
+    }
+}
+
+impl FromStr for CloseAction {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" | "clear" => Ok(CloseAction::None),
+            "trap" | "hold" | "route" => Ok(CloseAction::Trap),
+            "start" | "restart" => Ok(CloseAction::Start),
+            _ => Err(Error::Protocol("unknown close action")),
+        }
+    }
+}
+
+impl FromStr for ChildMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "tunnel" => Ok(ChildMode::Tunnel),
+            "transport" => Ok(ChildMode::Transport),
+            "beet" => Ok(ChildMode::Beet),
+            "pass" => Ok(ChildMode::Pass),
+            "drop" => Ok(ChildMode::Drop),
+            _ => Err(Error::Protocol("unknown child mode")),
+        }
+    }
+}
+
+impl fmt::Display for StartAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for CloseAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for ChildMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One local or remote authentication round.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuthConfig {
+    round: Option<u32>,
+    auth: Option<String>,
+    id: Option<String>,
+    eap_id: Option<String>,
+    aaa_id: Option<String>,
+    xauth_id: Option<String>,
+    ca_id: Option<String>,
+    revocation: Option<String>,
+    certs: Vec<Vec<u8>>,
+    cacerts: Vec<Vec<u8>>,
+    pubkeys: Vec<Vec<u8>>,
+    groups: Vec<String>,
+    cert_policy: Vec<String>,
+}
+
+impl AuthConfig {
+    /// Create an empty authentication round.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Explicit round number (defaults to the position in the connection).
+    pub fn round(mut self, round: u32) -> Self {
+        self.round = Some(round);
+        self
+    }
+
+    /// Authentication method, e.g. `pubkey`, `psk`, `eap-tls`.
+    pub fn auth(mut self, method: impl Into<String>) -> Self {
+        self.auth = Some(method.into());
+        self
+    }
+
+    /// IKE identity.
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// EAP identity.
+    pub fn eap_id(mut self, id: impl Into<String>) -> Self {
+        self.eap_id = Some(id.into());
+        self
+    }
+
+    /// AAA server identity (remote rounds).
+    pub fn aaa_id(mut self, id: impl Into<String>) -> Self {
+        self.aaa_id = Some(id.into());
+        self
+    }
+
+    /// XAuth identity.
+    pub fn xauth_id(mut self, id: impl Into<String>) -> Self {
+        self.xauth_id = Some(id.into());
+        self
+    }
+
+    /// Identity of the CA that must be part of the trust chain (remote rounds).
+    pub fn ca_id(mut self, id: impl Into<String>) -> Self {
+        self.ca_id = Some(id.into());
+        self
+    }
+
+    /// Revocation policy: `strict`, `ifuri` or `relaxed` (remote rounds).
+    pub fn revocation(mut self, policy: impl Into<String>) -> Self {
+        self.revocation = Some(policy.into());
+        self
+    }
+
+    /// Certificate (PEM or DER) to authenticate with or to expect.
+    pub fn cert(mut self, data: impl Into<Vec<u8>>) -> Self {
+        self.certs.push(data.into());
+        self
+    }
+
+    /// CA certificate (PEM or DER) constraining the trust chain.
+    pub fn cacert(mut self, data: impl Into<Vec<u8>>) -> Self {
+        self.cacerts.push(data.into());
+        self
+    }
+
+    /// Raw public key (PEM or DER).
+    pub fn pubkey(mut self, data: impl Into<Vec<u8>>) -> Self {
+        self.pubkeys.push(data.into());
+        self
+    }
+
+    /// Group membership required of the peer (remote rounds).
+    pub fn group(mut self, group: impl Into<String>) -> Self {
+        self.groups.push(group.into());
+        self
+    }
+
+    /// Certificate policy OID required of the peer (remote rounds).
+    pub fn cert_policy(mut self, oid: impl Into<String>) -> Self {
+        self.cert_policy.push(oid.into());
+        self
+    }
+
+    fn to_section(&self, default_round: u32) -> Section {
+        let mut sec = Section::new();
+        opt(&mut sec, "round", Some(self.round.unwrap_or(default_round)));
+        opt(&mut sec, "auth", self.auth.as_ref());
+        opt(&mut sec, "id", self.id.as_ref());
+        opt(&mut sec, "eap_id", self.eap_id.as_ref());
+        opt(&mut sec, "aaa_id", self.aaa_id.as_ref());
+        opt(&mut sec, "xauth_id", self.xauth_id.as_ref());
+        opt(&mut sec, "ca_id", self.ca_id.as_ref());
+        opt(&mut sec, "revocation", self.revocation.as_ref());
+        blobs(&mut sec, "certs", &self.certs);
+        blobs(&mut sec, "cacerts", &self.cacerts);
+        blobs(&mut sec, "pubkeys", &self.pubkeys);
+        list(&mut sec, "groups", &self.groups);
+        list(&mut sec, "cert_policy", &self.cert_policy);
+        sec
+    }
+}
+
+/// A CHILD_SA configuration within a connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChildConfig {
+    name: String,
+    mode: Option<ChildMode>,
+    esp_proposals: Vec<String>,
+    ah_proposals: Vec<String>,
+    local_ts: Vec<String>,
+    remote_ts: Vec<String>,
+    updown: Option<String>,
+    hostaccess: Option<bool>,
+    policies: Option<bool>,
+    start_action: Option<StartAction>,
+    close_action: Option<CloseAction>,
+    dpd_action: Option<CloseAction>,
+    rekey_time: Option<Duration>,
+    life_time: Option<Duration>,
+    rand_time: Option<Duration>,
+    rekey_bytes: Option<u64>,
+    life_bytes: Option<u64>,
+    rekey_packets: Option<u64>,
+    life_packets: Option<u64>,
+    inactivity: Option<Duration>,
+    reqid: Option<u32>,
+    priority: Option<u32>,
+    interface: Option<String>,
+    mark_in: Option<String>,
+    mark_out: Option<String>,
+    if_id_in: Option<String>,
+    if_id_out: Option<String>,
+}
+
+impl ChildConfig {
+    /// Create a CHILD_SA configuration named `name`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            mode: None,
+            esp_proposals: Vec::new(),
+            ah_proposals: Vec::new(),
+            local_ts: Vec::new(),
+            remote_ts: Vec::new(),
+            updown: None,
+            hostaccess: None,
+            policies: None,
+            start_action: None,
+            close_action: None,
+            dpd_action: None,
+            rekey_time: None,
+            life_time: None,
+            rand_time: None,
+            rekey_bytes: None,
+            life_bytes: None,
+            rekey_packets: None,
+            life_packets: None,
+            inactivity: None,
+            reqid: None,
+            priority: None,
+            interface: None,
+            mark_in: None,
+            mark_out: None,
+            if_id_in: None,
+            if_id_out: None,
+        }
+    }
+
+    /// Name of this CHILD_SA configuration.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// IPsec mode.
+    pub fn mode(mut self, mode: ChildMode) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Add an ESP proposal, e.g. `aes256gcm16-x25519`.
+    pub fn esp_proposal(mut self, proposal: impl Into<String>) -> Self {
+        self.esp_proposals.push(proposal.into());
+        self
+    }
+
+    /// Add an AH proposal.
+    pub fn ah_proposal(mut self, proposal: impl Into<String>) -> Self {
+        self.ah_proposals.push(proposal.into());
+        self
+    }
+
+    /// Add a local traffic selector, e.g. `10.1.0.0/16` or `dynamic`.
+    pub fn local_ts(mut self, ts: impl Into<String>) -> Self {
+        self.local_ts.push(ts.into());
+        self
+    }
+
+    /// Add a remote traffic selector.
+    pub fn remote_ts(mut self, ts: impl Into<String>) -> Self {
+        self.remote_ts.push(ts.into());
+        self
+    }
+
+    /// Updown script to invoke on CHILD_SA state changes.
+    pub fn updown(mut self, script: impl Into<String>) -> Self {
+        self.updown = Some(script.into());
+        self
+    }
+
+    /// Whether the updown script should allow access to the gateway itself.
+    pub fn hostaccess(mut self, enabled: bool) -> Self {
+        self.hostaccess = Some(enabled);
+        self
+    }
+
+    /// Whether to install IPsec policies.
+    pub fn policies(mut self, enabled: bool) -> Self {
+        self.policies = Some(enabled);
+        self
+    }
+
+    /// Action after loading the configuration.
+    pub fn start_action(mut self, action: StartAction) -> Self {
+        self.start_action = Some(action);
+        self
+    }
+
+    /// Action when the peer closes the CHILD_SA.
+    pub fn close_action(mut self, action: CloseAction) -> Self {
+        self.close_action = Some(action);
+        self
+    }
+
+    /// Action on DPD timeout.
+    pub fn dpd_action(mut self, action: CloseAction) -> Self {
+        self.dpd_action = Some(action);
+        self
+    }
+
+    /// Time after which the CHILD_SA is rekeyed.
+    pub fn rekey_time(mut self, time: Duration) -> Self {
+        self.rekey_time = Some(time);
+        self
+    }
+
+    /// Hard lifetime of the CHILD_SA.
+    pub fn life_time(mut self, time: Duration) -> Self {
+        self.life_time = Some(time);
+        self
+    }
+
+    /// Random jitter subtracted from the rekey time.
+    pub fn rand_time(mut self, time: Duration) -> Self {
+        self.rand_time = Some(time);
+        self
+    }
+
+    /// Volume after which the CHILD_SA is rekeyed.
+    pub fn rekey_bytes(mut self, bytes: u64) -> Self {
+        self.rekey_bytes = Some(bytes);
+        self
+    }
+
+    /// Hard volume limit of the CHILD_SA.
+    pub fn life_bytes(mut self, bytes: u64) -> Self {
+        self.life_bytes = Some(bytes);
+        self
+    }
+
+    /// Packet count after which the CHILD_SA is rekeyed.
+    pub fn rekey_packets(mut self, packets: u64) -> Self {
+        self.rekey_packets = Some(packets);
+        self
+    }
+
+    /// Hard packet limit of the CHILD_SA.
+    pub fn life_packets(mut self, packets: u64) -> Self {
+        self.life_packets = Some(packets);
+        self
+    }
+
+    /// Close the CHILD_SA after this much inactivity.
+    pub fn inactivity(mut self, time: Duration) -> Self {
+        self.inactivity = Some(time);
+        self
+    }
+
+    /// Fixed reqid for the CHILD_SA.
+    pub fn reqid(mut self, reqid: u32) -> Self {
+        self.reqid = Some(reqid);
+        self
+    }
+
+    /// Policy priority.
+    pub fn priority(mut self, priority: u32) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Restrict policies to this network interface.
+    pub fn interface(mut self, name: impl Into<String>) -> Self {
+        self.interface = Some(name.into());
+        self
+    }
+
+    /// Inbound mark, e.g. `0x10/0xff` or `%unique`.
+    pub fn mark_in(mut self, mark: impl Into<String>) -> Self {
+        self.mark_in = Some(mark.into());
+        self
+    }
+
+    /// Outbound mark.
+    pub fn mark_out(mut self, mark: impl Into<String>) -> Self {
+        self.mark_out = Some(mark.into());
+        self
+    }
+
+    /// Inbound XFRM interface id.
+    pub fn if_id_in(mut self, id: impl Into<String>) -> Self {
+        self.if_id_in = Some(id.into());
+        self
+    }
+
+    /// Outbound XFRM interface id.
+    pub fn if_id_out(mut self, id: impl Into<String>) -> Self {
+        self.if_id_out = Some(id.into());
+        self
+    }
+
+    fn to_section(&self) -> Section {
+        let mut sec = Section::new();
+        opt(&mut sec, "mode", self.mode);
+        list(&mut sec, "esp_proposals", &self.esp_proposals);
+        list(&mut sec, "ah_proposals", &self.ah_proposals);
+        list(&mut sec, "local_ts", &self.local_ts);
+        list(&mut sec, "remote_ts", &self.remote_ts);
+        opt(&mut sec, "updown", self.updown.as_ref());
+        opt(&mut sec, "hostaccess", self.hostaccess.map(yes_no));
+        opt(&mut sec, "policies", self.policies.map(yes_no));
+        opt(&mut sec, "start_action", self.start_action);
+        opt(&mut sec, "close_action", self.close_action);
+        opt(&mut sec, "dpd_action", self.dpd_action);
+        opt(&mut sec, "rekey_time", self.rekey_time.map(secs));
+        opt(&mut sec, "life_time", self.life_time.map(secs));
+        opt(&mut sec, "rand_time", self.rand_time.map(secs));
+        opt(&mut sec, "rekey_bytes", self.rekey_bytes);
+        opt(&mut sec, "life_bytes", self.life_bytes);
+        opt(&mut sec, "rekey_packets", self.rekey_packets);
+        opt(&mut sec, "life_packets", self.life_packets);
+        opt(&mut sec, "inactivity", self.inactivity.map(secs));
+        opt(&mut sec, "reqid", self.reqid);
+        opt(&mut sec, "priority", self.priority);
+        opt(&mut sec, "interface", self.interface.as_ref());
+        opt(&mut sec, "mark_in", self.mark_in.as_ref());
+        opt(&mut sec, "mark_out", self.mark_out.as_ref());
+        opt(&mut sec, "if_id_in", self.if_id_in.as_ref());
+        opt(&mut sec, "if_id_out", self.if_id_out.as_ref());
+        sec
+    }
+}
+
+/// An IKE connection configuration as accepted by `load-conn`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionConfig {
+    name: String,
+    version: Option<u8>,
+    local_addrs: Vec<String>,
+    remote_addrs: Vec<String>,
+    local_port: Option<u16>,
+    remote_port: Option<u16>,
+    proposals: Vec<String>,
+    vips: Vec<String>,
+    pools: Vec<String>,
+    encap: Option<bool>,
+    mobike: Option<bool>,
+    unique: Option<String>,
+    keyingtries: Option<u32>,
+    dpd_delay: Option<Duration>,
+    dpd_timeout: Option<Duration>,
+    rekey_time: Option<Duration>,
+    reauth_time: Option<Duration>,
+    over_time: Option<Duration>,
+    rand_time: Option<Duration>,
+    if_id_in: Option<String>,
+    if_id_out: Option<String>,
+    local: Vec<AuthConfig>,
+    remote: Vec<AuthConfig>,
+    children: Vec<ChildConfig>,
+}
+
+impl ConnectionConfig {
+    /// Create a connection configuration named `name`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: None,
+            local_addrs: Vec::new(),
+            remote_addrs: Vec::new(),
+            local_port: None,
+            remote_port: None,
+            proposals: Vec::new(),
+            vips: Vec::new(),
+            pools: Vec::new(),
+            encap: None,
+            mobike: None,
+            unique: None,
+            keyingtries: None,
+            dpd_delay: None,
+            dpd_timeout: None,
+            rekey_time: None,
+            reauth_time: None,
+            over_time: None,
+            rand_time: None,
+            if_id_in: None,
+            if_id_out: None,
+            local: Vec::new(),
+            remote: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Name of this connection.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// CHILD_SA configurations of this connection.
+    pub fn children(&self) -> &[ChildConfig] {
+        &self.children
+    }
+
+    /// IKE major version: 0 (any), 1 or 2.
+    pub fn version(mut self, version: u8) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// Add a local address, range, subnet or DNS name.
+    pub fn local_addr(mut self, addr: impl Into<String>) -> Self {
+        self.local_addrs.push(addr.into());
+        self
+    }
+
+    /// Add a remote address, range, subnet or DNS name.
+    pub fn remote_addr(mut self, addr: impl Into<String>) -> Self {
+        self.remote_addrs.push(addr.into());
+        self
+    }
+
+    /// Local IKE port.
+    pub fn local_port(mut self, port: u16) -> Self {
+        self.local_port = Some(port);
+        self
+    }
+
+    /// Remote IKE port.
+    pub fn remote_port(mut self, port: u16) -> Self {
+        self.remote_port = Some(port);
+        self
+    }
+
+    /// Add an IKE proposal, e.g. `aes256-sha256-x25519`.
+    pub fn proposal(mut self, proposal: impl Into<String>) -> Self {
+        self.proposals.push(proposal.into());
+        self
+    }
+
+    /// Request a virtual IP (`0.0.0.0`/`::` for any) from the peer.
+    pub fn vip(mut self, addr: impl Into<String>) -> Self {
+        self.vips.push(addr.into());
+        self
+    }
+
+    /// Assign virtual IPs to peers from this pool.
+    pub fn pool(mut self, name: impl Into<String>) -> Self {
+        self.pools.push(name.into());
+        self
+    }
+
+    /// Force UDP encapsulation of ESP packets.
+    pub fn encap(mut self, enabled: bool) -> Self {
+        self.encap = Some(enabled);
+        self
+    }
+
+    /// Enable or disable MOBIKE.
+    pub fn mobike(mut self, enabled: bool) -> Self {
+        self.mobike = Some(enabled);
+        self
+    }
+
+    /// Uniqueness policy: `no`, `never`, `keep` or `replace`.
+    pub fn unique(mut self, policy: impl Into<String>) -> Self {
+        self.unique = Some(policy.into());
+        self
+    }
+
+    /// Number of retransmission sequences before giving up (0 for forever).
+    pub fn keyingtries(mut self, tries: u32) -> Self {
+        self.keyingtries = Some(tries);
+        self
+    }
+
+    /// Interval of liveness checks.
+    pub fn dpd_delay(mut self, delay: Duration) -> Self {
+        self.dpd_delay = Some(delay);
+        self
+    }
+
+    /// Timeout for IKEv1 DPD.
+    pub fn dpd_timeout(mut self, timeout: Duration) -> Self {
+        self.dpd_timeout = Some(timeout);
+        self
+    }
+
+    /// Time after which the IKE_SA is rekeyed.
+    pub fn rekey_time(mut self, time: Duration) -> Self {
+        self.rekey_time = Some(time);
+        self
+    }
+
+    /// Time after which the IKE_SA is reauthenticated.
+    pub fn reauth_time(mut self, time: Duration) -> Self {
+        self.reauth_time = Some(time);
+        self
+    }
+
+    /// Hard IKE_SA lifetime beyond the rekey/reauth time.
+    pub fn over_time(mut self, time: Duration) -> Self {
+        self.over_time = Some(time);
+        self
+    }
+
+    /// Random jitter subtracted from the rekey/reauth time.
+    pub fn rand_time(mut self, time: Duration) -> Self {
+        self.rand_time = Some(time);
+        self
+    }
+
+    /// Default inbound XFRM interface id for CHILD_SAs.
+    pub fn if_id_in(mut self, id: impl Into<String>) -> Self {
+        self.if_id_in = Some(id.into());
+        self
+    }
+
+    /// Default outbound XFRM interface id for CHILD_SAs.
+    pub fn if_id_out(mut self, id: impl Into<String>) -> Self {
+        self.if_id_out = Some(id.into());
+        self
+    }
+
+    /// Add a local authentication round.
+    pub fn local_auth(mut self, auth: AuthConfig) -> Self {
+        self.local.push(auth);
+        self
+    }
+
+    /// Add a remote authentication round.
+    pub fn remote_auth(mut self, auth: AuthConfig) -> Self {
+        self.remote.push(auth);
+        self
+    }
+
+    /// Add a CHILD_SA configuration.
+    pub fn child(mut self, child: ChildConfig) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    /// Encode the `load-conn` request message.
+    pub fn to_message(&self) -> Message {
+        let mut sec = Section::new();
+        opt(&mut sec, "version", self.version);
+        list(&mut sec, "local_addrs", &self.local_addrs);
+        list(&mut sec, "remote_addrs", &self.remote_addrs);
+        opt(&mut sec, "local_port", self.local_port);
+        opt(&mut sec, "remote_port", self.remote_port);
+        list(&mut sec, "proposals", &self.proposals);
+        list(&mut sec, "vips", &self.vips);
+        list(&mut sec, "pools", &self.pools);
+        opt(&mut sec, "encap", self.encap.map(yes_no));
+        opt(&mut sec, "mobike", self.mobike.map(yes_no));
+        opt(&mut sec, "unique", self.unique.as_ref());
+        opt(&mut sec, "keyingtries", self.keyingtries);
+        opt(&mut sec, "dpd_delay", self.dpd_delay.map(secs));
+        opt(&mut sec, "dpd_timeout", self.dpd_timeout.map(secs));
+        opt(&mut sec, "rekey_time", self.rekey_time.map(secs));
+        opt(&mut sec, "reauth_time", self.reauth_time.map(secs));
+        opt(&mut sec, "over_time", self.over_time.map(secs));
+        opt(&mut sec, "rand_time", self.rand_time.map(secs));
+        opt(&mut sec, "if_id_in", self.if_id_in.as_ref());
+        opt(&mut sec, "if_id_out", self.if_id_out.as_ref());
+        for (i, auth) in self.local.iter().enumerate() {
+            let round = i as u32 + 1;
+            sec.insert(
+                format!("local-{round}"),
+                Value::Section(auth.to_section(round)),
+            );
+        }
+        for (i, auth) in self.remote.iter().enumerate() {
+            let round = i as u32 + 1;
+            sec.insert(
+                format!("remote-{round}"),
+                Value::Section(auth.to_section(round)),
+            );
+        }
+        if !self.children.is_empty() {
+            let mut children = Section::new();
+            for child in &self.children {
+                children.insert(child.name.clone(), Value::Section(child.to_section()));
+            }
+            sec.insert("children", Value::Section(children));
+        }
+
+        let mut root = Section::new();
+        root.insert(self.name.clone(), Value::Section(sec));
+        root.to_message()
+    }
+}
+
+/// An authentication round as reported by `list-conns`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuthDescription {
+    /// Authentication class, e.g. `public key`, `pre-shared key`, `EAP`.
+    pub class: Option<String>,
+    /// EAP method, if any.
+    pub eap_type: Option<String>,
+    /// IKE identity.
+    pub id: Option<String>,
+    /// EAP identity.
+    pub eap_id: Option<String>,
+    /// AAA identity.
+    pub aaa_id: Option<String>,
+    /// XAuth identity.
+    pub xauth_id: Option<String>,
+    /// Required CA identity.
+    pub ca_id: Option<String>,
+    /// Revocation policy.
+    pub revocation: Option<String>,
+    /// Required group memberships.
+    pub groups: Vec<String>,
+    /// Required certificate policies.
+    pub cert_policy: Vec<String>,
+    /// Subjects of the configured certificates.
+    pub certs: Vec<String>,
+    /// Subjects of the configured CA certificates.
+    pub cacerts: Vec<String>,
+}
+
+impl AuthDescription {
+    fn from_section(sec: &Section) -> Self {
+        Self {
+            class: sec.str("class").map(str::to_string),
+            eap_type: sec.str("eap-type").map(str::to_string),
+            id: sec.str("id").map(str::to_string),
+            eap_id: sec.str("eap_id").map(str::to_string),
+            aaa_id: sec.str("aaa_id").map(str::to_string),
+            xauth_id: sec.str("xauth_id").map(str::to_string),
+            ca_id: sec.str("ca_id").map(str::to_string),
+            revocation: sec.str("revocation").map(str::to_string),
+            groups: sec.str_list("groups"),
+            cert_policy: sec.str_list("cert_policy"),
+            certs: sec.str_list("certs"),
+            cacerts: sec.str_list("cacerts"),
+        }
+    }
+}
+
+/// A CHILD_SA configuration as reported by `list-conns`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChildDescription {
+    /// CHILD_SA configuration name.
+    pub name: String,
+    /// IPsec mode.
+    pub mode: Option<ChildMode>,
+    /// Security label, if any.
+    pub label: Option<String>,
+    /// Rekey time in seconds.
+    pub rekey_time: Option<u64>,
+    /// Rekey volume in bytes.
+    pub rekey_bytes: Option<u64>,
+    /// Rekey packet count.
+    pub rekey_packets: Option<u64>,
+    /// Action after loading, if reported by the daemon.
+    pub start_action: Option<StartAction>,
+    /// Action on DPD timeout.
+    pub dpd_action: Option<CloseAction>,
+    /// Action when the peer closes the CHILD_SA.
+    pub close_action: Option<CloseAction>,
+    /// Local traffic selectors.
+    pub local_ts: Vec<String>,
+    /// Remote traffic selectors.
+    pub remote_ts: Vec<String>,
+    /// Interface policies are restricted to.
+    pub interface: Option<String>,
+    /// Policy priority.
+    pub priority: Option<u32>,
+}
+
+impl ChildDescription {
+    fn from_section(name: &str, sec: &Section) -> Self {
+        Self {
+            name: name.to_string(),
+            mode: sec.parse("mode"),
+            label: sec.str("label").map(str::to_string),
+            rekey_time: sec.parse("rekey_time"),
+            rekey_bytes: sec.parse("rekey_bytes"),
+            rekey_packets: sec.parse("rekey_packets"),
+            start_action: sec.parse("start_action"),
+            dpd_action: sec.parse("dpd_action"),
+            close_action: sec.parse("close_action"),
+            local_ts: sec.str_list("local-ts"),
+            remote_ts: sec.str_list("remote-ts"),
+            interface: sec.str("interface").map(str::to_string),
+            priority: sec.parse("priority"),
+        }
+    }
+}
+
+/// A loaded connection as reported by `list-conns`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnDescription {
+    /// Connection name.
+    pub name: String,
+    /// Local addresses.
+    pub local_addrs: Vec<String>,
+    /// Remote addresses.
+    pub remote_addrs: Vec<String>,
+    /// Local IKE port, if reported.
+    pub local_port: Option<u16>,
+    /// Remote IKE port, if reported.
+    pub remote_port: Option<u16>,
+    /// IKE version, e.g. `IKEv2` or `IKEv1/2`.
+    pub version: Option<String>,
+    /// Reauthentication time in seconds.
+    pub reauth_time: Option<u64>,
+    /// Rekey time in seconds.
+    pub rekey_time: Option<u64>,
+    /// Uniqueness policy.
+    pub unique: Option<String>,
+    /// DPD delay in seconds.
+    pub dpd_delay: Option<u64>,
+    /// Local authentication rounds, in order.
+    pub local_auth: Vec<AuthDescription>,
+    /// Remote authentication rounds, in order.
+    pub remote_auth: Vec<AuthDescription>,
+    /// CHILD_SA configurations.
+    pub children: Vec<ChildDescription>,
+}
+
+impl ConnDescription {
+    /// Parse all connections contained in a `list-conn` event message.
+    pub fn from_event(msg: &Message) -> Result<Vec<Self>> {
+        let tree = msg.tree()?;
+        Ok(tree
+            .sections()
+            .map(|(name, sec)| Self::from_section(name, sec))
+            .collect())
+    }
+
+    fn from_section(name: &str, sec: &Section) -> Self {
+        let mut local_auth = Vec::new();
+        let mut remote_auth = Vec::new();
+        for (key, auth) in sec.sections() {
+            if key.starts_with("local") {
+                local_auth.push(AuthDescription::from_section(auth));
+            } else if key.starts_with("remote") {
+                remote_auth.push(AuthDescription::from_section(auth));
+            }
+        }
+        let children = sec
+            .section("children")
+            .map(|c| {
+                c.sections()
+                    .map(|(n, s)| ChildDescription::from_section(n, s))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            name: name.to_string(),
+            local_addrs: sec.str_list("local_addrs"),
+            remote_addrs: sec.str_list("remote_addrs"),
+            local_port: sec.parse("local_port"),
+            remote_port: sec.parse("remote_port"),
+            version: sec.str("version").map(str::to_string),
+            reauth_time: sec.parse("reauth_time"),
+            rekey_time: sec.parse("rekey_time"),
+            unique: sec.str("unique").map(str::to_string),
+            dpd_delay: sec.parse("dpd_delay"),
+            local_auth,
+            remote_auth,
+            children,
+        }
+    }
+
+    /// Look up a CHILD_SA configuration by name.
+    pub fn child(&self, name: &str) -> Option<&ChildDescription> {
+        self.children.iter().find(|c| c.name == name)
+    }
+}
+
+impl Client {
+    /// Load (or replace) a connection configuration.
+    ///
+    /// Returns `Error::CommandFailed` with charon's `errmsg` if the
+    /// configuration is rejected.
+    pub fn load_conn(&mut self, conn: &ConnectionConfig) -> Result<()> {
+        let resp = self.call("load-conn", &conn.to_message())?;
+        expect_success(&resp).map(|_| ())
+    }
+
+    /// Unload a previously loaded connection by name.
+    pub fn unload_conn(&mut self, name: &str) -> Result<()> {
+        let req = Message::new().kv_str("name", name);
+        let resp = self.call("unload-conn", &req)?;
+        expect_success(&resp).map(|_| ())
+    }
+
+    /// Names of all connections loaded over VICI.
+    pub fn get_conns(&mut self) -> Result<Vec<String>> {
+        let resp = self.call("get-conns", &Message::new())?;
+        Ok(resp.tree()?.str_list("conns"))
+    }
+
+    /// List loaded connections, optionally only the one named `ike`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rustici::Client;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = Client::connect("/var/run/charon.vici")?;
+    /// for conn in client.list_conns(None)? {
+    ///     println!("{}: {:?} -> {:?}", conn.name, conn.local_addrs, conn.remote_addrs);
+    ///     for child in &conn.children {
+    ///         println!("  {} {:?} === {:?}", child.name, child.local_ts, child.remote_ts);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn list_conns(&mut self, ike: Option<&str>) -> Result<Vec<ConnDescription>> {
+        let mut req = Message::new();
+        if let Some(ike) = ike {
+            req = req.kv_str("ike", ike);
+        }
+        self.collect_streamed("list-conns", &req, "list-conn", |msg, out| {
+            out.append(&mut ConnDescription::from_event(msg)?);
+            Ok(())
+        })
+    }
+}
+
+fn yes_no(v: bool) -> &'static str {
+    if v {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+fn secs(d: Duration) -> String {
+    format!("{}s", d.as_secs())
+}
+
+fn opt<T: ToString>(sec: &mut Section, key: &str, value: Option<T>) {
+    if let Some(v) = value {
+        sec.insert_str(key, v.to_string());
+    }
+}
+
+fn list(sec: &mut Section, key: &str, items: &[String]) {
+    if !items.is_empty() {
+        sec.insert_str_list(key, items);
+    }
+}
+
+fn blobs(sec: &mut Section, key: &str, items: &[Vec<u8>]) {
+    if !items.is_empty() {
+        sec.insert(key, Value::List(items.to_vec()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connection_encoding() {
+        let conn = ConnectionConfig::new("gw")
+            .version(2)
+            .local_addr("192.0.2.1")
+            .remote_addr("198.51.100.1")
+            .proposal("aes256-sha256-x25519")
+            .rekey_time(Duration::from_secs(3600))
+            .local_auth(AuthConfig::new().auth("pubkey").cert(vec![0x30, 0x82]))
+            .remote_auth(AuthConfig::new().auth("pubkey").id("peer"))
+            .child(
+                ChildConfig::new("net")
+                    .mode(ChildMode::Tunnel)
+                    .esp_proposal("aes256gcm16")
+                    .local_ts("10.1.0.0/16")
+                    .start_action(StartAction::TrapStart)
+                    .dpd_action(CloseAction::Start),
+            );
+
+        let tree = conn.to_message().tree().unwrap();
+        let gw = tree.section("gw").unwrap();
+        assert_eq!(gw.str("version"), Some("2"));
+        assert_eq!(gw.str_list("local_addrs"), ["192.0.2.1"]);
+        assert_eq!(gw.str("rekey_time"), Some("3600s"));
+
+        let local = gw.section("local-1").unwrap();
+        assert_eq!(local.str("round"), Some("1"));
+        assert_eq!(local.list("certs").unwrap(), [vec![0x30, 0x82]]);
+        assert_eq!(gw.section("remote-1").unwrap().str("id"), Some("peer"));
+
+        let net = gw.section("children").unwrap().section("net").unwrap();
+        assert_eq!(net.str("mode"), Some("tunnel"));
+        assert_eq!(net.str("start_action"), Some("trap|start"));
+        assert_eq!(net.str("dpd_action"), Some("start"));
+        assert_eq!(net.get("remote_ts"), None);
+    }
+
+    #[test]
+    fn list_conn_parsing() {
+        let msg = Message::new()
+            .section_start("gw")
+            .list_start("local_addrs")
+            .list_item_str("192.0.2.1")
+            .list_end()
+            .list_start("remote_addrs")
+            .list_item_str("%any")
+            .list_end()
+            .kv_str("version", "IKEv2")
+            .kv_str("reauth_time", "0")
+            .kv_str("rekey_time", "14400")
+            .section_start("local-1")
+            .kv_str("class", "public key")
+            .kv_str("id", "gw.example.org")
+            .section_end()
+            .section_start("remote-1")
+            .kv_str("class", "EAP")
+            .kv_str("eap-type", "MSCHAPV2")
+            .section_end()
+            .section_start("children")
+            .section_start("net")
+            .kv_str("mode", "TUNNEL")
+            .kv_str("rekey_time", "3600")
+            .kv_str("dpd_action", "restart")
+            .kv_str("close_action", "clear")
+            .list_start("local-ts")
+            .list_item_str("10.1.0.0/16")
+            .list_end()
+            .list_start("remote-ts")
+            .list_item_str("dynamic")
+            .list_end()
+            .section_end()
+            .section_end()
+            .section_end();
+
+        let conns = ConnDescription::from_event(&msg).unwrap();
+        assert_eq!(conns.len(), 1);
+        let gw = &conns[0];
+        assert_eq!(gw.name, "gw");
+        assert_eq!(gw.remote_addrs, ["%any"]);
+        assert_eq!(gw.version.as_deref(), Some("IKEv2"));
+        assert_eq!(gw.rekey_time, Some(14400));
+        assert_eq!(gw.local_auth[0].id.as_deref(), Some("gw.example.org"));
+        assert_eq!(gw.remote_auth[0].eap_type.as_deref(), Some("MSCHAPV2"));
+
+        let net = gw.child("net").unwrap();
+        assert_eq!(net.mode, Some(ChildMode::Tunnel));
+        assert_eq!(net.dpd_action, Some(CloseAction::Start));
+        assert_eq!(net.close_action, Some(CloseAction::None));
+        assert_eq!(net.local_ts, ["10.1.0.0/16"]);
+        assert_eq!(net.remote_ts, ["dynamic"]);
+    }
+
+    #[test]
+    fn actions_parse_case_insensitively() {
+        assert_eq!("TUNNEL".parse::<ChildMode>().unwrap(), ChildMode::Tunnel);
+        assert_eq!("route".parse::<StartAction>().unwrap(), StartAction::Trap);
+        assert_eq!(
+            "restart".parse::<CloseAction>().unwrap(),
+            CloseAction::Start
+        );
+        assert!("bogus".parse::<StartAction>().is_err());
+    }
+}
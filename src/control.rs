@@ -0,0 +1,564 @@
+//! Typed `initiate`, `terminate`, `rekey` and `redirect` commands.
+//!
+//! Each command has a small request builder mirroring the options charon
+//! accepts, and all of them report a [`ControlOutcome`]. The `*_with_log`
+//! variants register for `control-log` for the duration of the call and
+//! hand every streamed line to a [`LogSink`], which is usually the only way
+//! to learn *why* a tunnel failed to come up.
+
+use std::time::Duration;
+
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::logging::{LogRecord, LogSink};
+use crate::wire::Message;
+
+/// The outcome of a control command.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ControlOutcome {
+    /// Whether the daemon reported success.
+    pub success: bool,
+    /// Number of matching SAs (`terminate`, `rekey`).
+    pub matches: Option<u32>,
+    /// Number of SAs actually terminated (`terminate`).
+    pub terminated: Option<u32>,
+    /// Failure reason reported by the daemon.
+    pub errmsg: Option<String>,
+}
+
+impl ControlOutcome {
+    /// Parse a control command response.
+    pub fn from_message(msg: &Message) -> Result<Self> {
+        let tree = msg.tree()?;
+        Ok(Self {
+            success: tree
+                .flag("success")
+                .ok_or(Error::Protocol("control response without success"))?,
+            matches: tree.parse("matches"),
+            terminated: tree.parse("terminated"),
+            errmsg: tree.str("errmsg").map(str::to_string),
+        })
+    }
+
+    /// Convert an unsuccessful outcome into `Error::CommandFailed`.
+    pub fn into_result(self) -> Result<Self> {
+        if self.success {
+            Ok(self)
+        } else {
+            Err(Error::CommandFailed(
+                self.errmsg.unwrap_or_else(|| "unknown error".to_string()),
+            ))
+        }
+    }
+}
+
+/// How long the daemon should wait for a control command to complete.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Wait {
+    Timeout(Duration),
+    NoWait,
+}
+
+impl Wait {
+    fn encode(self) -> String {
+        match self {
+            Wait::Timeout(d) => d.as_millis().to_string(),
+            Wait::NoWait => "-1".to_string(),
+        }
+    }
+}
+
+fn yes_no(v: bool) -> &'static str {
+    if v {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+/// Request builder for `initiate`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InitiateRequest {
+    child: Option<String>,
+    ike: Option<String>,
+    wait: Option<Wait>,
+    init_limits: Option<bool>,
+    loglevel: Option<i32>,
+}
+
+impl InitiateRequest {
+    /// Create an empty request.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// CHILD_SA configuration to initiate.
+    pub fn child(mut self, name: impl Into<String>) -> Self {
+        self.child = Some(name.into());
+        self
+    }
+
+    /// IKE_SA configuration to initiate or to look the CHILD_SA up in.
+    pub fn ike(mut self, name: impl Into<String>) -> Self {
+        self.ike = Some(name.into());
+        self
+    }
+
+    /// Wait at most `timeout` for the SA to establish (0 waits forever).
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.wait = Some(Wait::Timeout(timeout));
+        self
+    }
+
+    /// Return immediately instead of waiting for the SA to establish.
+    pub fn no_wait(mut self) -> Self {
+        self.wait = Some(Wait::NoWait);
+        self
+    }
+
+    /// Whether to apply the daemon's half-open/job-load init limits.
+    pub fn init_limits(mut self, enabled: bool) -> Self {
+        self.init_limits = Some(enabled);
+        self
+    }
+
+    /// Verbosity of streamed `control-log` lines.
+    pub fn loglevel(mut self, level: i32) -> Self {
+        self.loglevel = Some(level);
+        self
+    }
+
+    /// Encode the request message.
+    pub fn to_message(&self) -> Message {
+        let mut msg = Message::new();
+        if let Some(v) = &self.child {
+            msg = msg.kv_str("child", v);
+        }
+        if let Some(v) = &self.ike {
+            msg = msg.kv_str("ike", v);
+        }
+        if let Some(v) = self.wait {
+            msg = msg.kv_str("timeout", v.encode());
+        }
+        if let Some(v) = self.init_limits {
+            msg = msg.kv_str("init-limits", yes_no(v));
+        }
+        if let Some(v) = self.loglevel {
+            msg = msg.kv_str("loglevel", v.to_string());
+        }
+        msg
+    }
+}
+
+/// Request builder for `terminate`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TerminateRequest {
+    child: Option<String>,
+    ike: Option<String>,
+    child_id: Option<u32>,
+    ike_id: Option<u32>,
+    force: Option<bool>,
+    wait: Option<Wait>,
+    loglevel: Option<i32>,
+}
+
+impl TerminateRequest {
+    /// Create an empty request.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Terminate CHILD_SAs of this configuration.
+    pub fn child(mut self, name: impl Into<String>) -> Self {
+        self.child = Some(name.into());
+        self
+    }
+
+    /// Terminate IKE_SAs of this configuration.
+    pub fn ike(mut self, name: impl Into<String>) -> Self {
+        self.ike = Some(name.into());
+        self
+    }
+
+    /// Terminate the CHILD_SA with this unique id.
+    pub fn child_id(mut self, id: u32) -> Self {
+        self.child_id = Some(id);
+        self
+    }
+
+    /// Terminate the IKE_SA with this unique id.
+    pub fn ike_id(mut self, id: u32) -> Self {
+        self.ike_id = Some(id);
+        self
+    }
+
+    /// Delete the IKE_SA without waiting for the peer's DELETE response.
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = Some(force);
+        self
+    }
+
+    /// Wait at most `timeout` for the SA to go down (0 waits forever).
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.wait = Some(Wait::Timeout(timeout));
+        self
+    }
+
+    /// Return immediately instead of waiting for the SA to go down.
+    pub fn no_wait(mut self) -> Self {
+        self.wait = Some(Wait::NoWait);
+        self
+    }
+
+    /// Verbosity of streamed `control-log` lines.
+    pub fn loglevel(mut self, level: i32) -> Self {
+        self.loglevel = Some(level);
+        self
+    }
+
+    /// Encode the request message.
+    pub fn to_message(&self) -> Message {
+        let mut msg = Message::new();
+        if let Some(v) = &self.child {
+            msg = msg.kv_str("child", v);
+        }
+        if let Some(v) = &self.ike {
+            msg = msg.kv_str("ike", v);
+        }
+        if let Some(v) = self.child_id {
+            msg = msg.kv_str("child-id", v.to_string());
+        }
+        if let Some(v) = self.ike_id {
+            msg = msg.kv_str("ike-id", v.to_string());
+        }
+        if let Some(v) = self.force {
+            msg = msg.kv_str("force", yes_no(v));
+        }
+        if let Some(v) = self.wait {
+            msg = msg.kv_str("timeout", v.encode());
+        }
+        if let Some(v) = self.loglevel {
+            msg = msg.kv_str("loglevel", v.to_string());
+        }
+        msg
+    }
+}
+
+/// Request builder for `rekey`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RekeyRequest {
+    child: Option<String>,
+    ike: Option<String>,
+    child_id: Option<u32>,
+    ike_id: Option<u32>,
+    reauth: Option<bool>,
+}
+
+impl RekeyRequest {
+    /// Create an empty request.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rekey CHILD_SAs of this configuration.
+    pub fn child(mut self, name: impl Into<String>) -> Self {
+        self.child = Some(name.into());
+        self
+    }
+
+    /// Rekey IKE_SAs of this configuration.
+    pub fn ike(mut self, name: impl Into<String>) -> Self {
+        self.ike = Some(name.into());
+        self
+    }
+
+    /// Rekey the CHILD_SA with this unique id.
+    pub fn child_id(mut self, id: u32) -> Self {
+        self.child_id = Some(id);
+        self
+    }
+
+    /// Rekey the IKE_SA with this unique id.
+    pub fn ike_id(mut self, id: u32) -> Self {
+        self.ike_id = Some(id);
+        self
+    }
+
+    /// Reauthenticate instead of rekeying (IKEv2 only).
+    pub fn reauth(mut self, reauth: bool) -> Self {
+        self.reauth = Some(reauth);
+        self
+    }
+
+    /// Encode the request message.
+    pub fn to_message(&self) -> Message {
+        let mut msg = Message::new();
+        if let Some(v) = &self.child {
+            msg = msg.kv_str("child", v);
+        }
+        if let Some(v) = &self.ike {
+            msg = msg.kv_str("ike", v);
+        }
+        if let Some(v) = self.child_id {
+            msg = msg.kv_str("child-id", v.to_string());
+        }
+        if let Some(v) = self.ike_id {
+            msg = msg.kv_str("ike-id", v.to_string());
+        }
+        if let Some(v) = self.reauth {
+            msg = msg.kv_str("reauth", yes_no(v));
+        }
+        msg
+    }
+}
+
+/// Request builder for `redirect`.
+///
+/// At least one selector should be set; charon redirects every matching
+/// IKE_SA to `gateway`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedirectRequest {
+    gateway: String,
+    ike: Option<String>,
+    ike_id: Option<u32>,
+    peer_ip: Option<String>,
+    peer_id: Option<String>,
+}
+
+impl RedirectRequest {
+    /// Redirect matching IKE_SAs to `gateway` (an IP address or FQDN).
+    pub fn new(gateway: impl Into<String>) -> Self {
+        Self {
+            gateway: gateway.into(),
+            ike: None,
+            ike_id: None,
+            peer_ip: None,
+            peer_id: None,
+        }
+    }
+
+    /// Select IKE_SAs of this configuration.
+    pub fn ike(mut self, name: impl Into<String>) -> Self {
+        self.ike = Some(name.into());
+        self
+    }
+
+    /// Select the IKE_SA with this unique id.
+    pub fn ike_id(mut self, id: u32) -> Self {
+        self.ike_id = Some(id);
+        self
+    }
+
+    /// Select IKE_SAs with this peer address, subnet or range.
+    pub fn peer_ip(mut self, addr: impl Into<String>) -> Self {
+        self.peer_ip = Some(addr.into());
+        self
+    }
+
+    /// Select IKE_SAs with this peer identity (wildcards allowed).
+    pub fn peer_id(mut self, id: impl Into<String>) -> Self {
+        self.peer_id = Some(id.into());
+        self
+    }
+
+    /// Encode the request message.
+    pub fn to_message(&self) -> Message {
+        let mut msg = Message::new();
+        if let Some(v) = &self.ike {
+            msg = msg.kv_str("ike", v);
+        }
+        if let Some(v) = self.ike_id {
+            msg = msg.kv_str("ike-id", v.to_string());
+        }
+        if let Some(v) = &self.peer_ip {
+            msg = msg.kv_str("peer-ip", v);
+        }
+        if let Some(v) = &self.peer_id {
+            msg = msg.kv_str("peer-id", v);
+        }
+        msg.kv_str("gateway", &self.gateway)
+    }
+}
+
+impl Client {
+    /// Initiate a connection and wait for the outcome.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use rustici::{Client, control::InitiateRequest};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = Client::connect("/var/run/charon.vici")?;
+    /// let req = InitiateRequest::new().child("net").timeout(Duration::from_secs(10));
+    /// let outcome = client.initiate(&req)?;
+    /// if !outcome.success {
+    ///     eprintln!("initiate failed: {:?}", outcome.errmsg);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn initiate(&mut self, req: &InitiateRequest) -> Result<ControlOutcome> {
+        self.control("initiate", &req.to_message(), None)
+    }
+
+    /// Initiate a connection, forwarding streamed `control-log` lines to `sink`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rustici::{Client, control::InitiateRequest, logging::LogRecord};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = Client::connect("/var/run/charon.vici")?;
+    /// let req = InitiateRequest::new().child("net").loglevel(2);
+    /// let outcome = client.initiate_with_log(&req, |rec: &LogRecord| {
+    ///     println!("[{}] {}", rec.group, rec.msg);
+    /// })?;
+    /// println!("success: {}", outcome.success);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn initiate_with_log<S: LogSink>(
+        &mut self,
+        req: &InitiateRequest,
+        mut sink: S,
+    ) -> Result<ControlOutcome> {
+        self.control("initiate", &req.to_message(), Some(&mut sink))
+    }
+
+    /// Terminate matching SAs and wait for the outcome.
+    pub fn terminate(&mut self, req: &TerminateRequest) -> Result<ControlOutcome> {
+        self.control("terminate", &req.to_message(), None)
+    }
+
+    /// Terminate matching SAs, forwarding streamed `control-log` lines to `sink`.
+    pub fn terminate_with_log<S: LogSink>(
+        &mut self,
+        req: &TerminateRequest,
+        mut sink: S,
+    ) -> Result<ControlOutcome> {
+        self.control("terminate", &req.to_message(), Some(&mut sink))
+    }
+
+    /// Rekey matching SAs.
+    pub fn rekey(&mut self, req: &RekeyRequest) -> Result<ControlOutcome> {
+        self.control("rekey", &req.to_message(), None)
+    }
+
+    /// Rekey matching SAs, forwarding any streamed `control-log` lines to `sink`.
+    pub fn rekey_with_log<S: LogSink>(
+        &mut self,
+        req: &RekeyRequest,
+        mut sink: S,
+    ) -> Result<ControlOutcome> {
+        self.control("rekey", &req.to_message(), Some(&mut sink))
+    }
+
+    /// Redirect matching IKE_SAs to another gateway (RFC 5685).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rustici::{Client, control::RedirectRequest};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = Client::connect("/var/run/charon.vici")?;
+    /// // drain this gateway
+    /// let req = RedirectRequest::new("gw2.example.org").peer_ip("0.0.0.0/0");
+    /// client.redirect(&req)?.into_result()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn redirect(&mut self, req: &RedirectRequest) -> Result<ControlOutcome> {
+        self.control("redirect", &req.to_message(), None)
+    }
+
+    fn control(
+        &mut self,
+        command: &str,
+        request: &Message,
+        sink: Option<&mut dyn LogSink>,
+    ) -> Result<ControlOutcome> {
+        let resp = match sink {
+            None => self.call(command, request)?,
+            Some(sink) => self.stream_event(command, request, "control-log", |msg| {
+                if let Ok(rec) = LogRecord::from_message(msg) {
+                    sink.log(&rec);
+                }
+            })?,
+        };
+        ControlOutcome::from_message(&resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn initiate_request_encoding() {
+        let msg = InitiateRequest::new()
+            .child("net")
+            .ike("gw")
+            .timeout(Duration::from_secs(3))
+            .init_limits(false)
+            .loglevel(2)
+            .to_message();
+        let tree = msg.tree().unwrap();
+        assert_eq!(tree.str("child"), Some("net"));
+        assert_eq!(tree.str("ike"), Some("gw"));
+        assert_eq!(tree.str("timeout"), Some("3000"));
+        assert_eq!(tree.str("init-limits"), Some("no"));
+        assert_eq!(tree.str("loglevel"), Some("2"));
+    }
+
+    #[test]
+    fn terminate_request_encoding() {
+        let msg = TerminateRequest::new()
+            .ike_id(42)
+            .force(true)
+            .no_wait()
+            .to_message();
+        let tree = msg.tree().unwrap();
+        assert_eq!(tree.str("ike-id"), Some("42"));
+        assert_eq!(tree.str("force"), Some("yes"));
+        assert_eq!(tree.str("timeout"), Some("-1"));
+        assert_eq!(tree.get("child"), None);
+    }
+
+    #[test]
+    fn redirect_request_encoding() {
+        let tree = RedirectRequest::new("192.0.2.2")
+            .peer_id("*@example.org")
+            .to_message()
+            .tree()
+            .unwrap();
+        assert_eq!(tree.str("gateway"), Some("192.0.2.2"));
+        assert_eq!(tree.str("peer-id"), Some("*@example.org"));
+        assert_eq!(tree.get("ike"), None);
+    }
+
+    #[test]
+    fn outcome_parsing() {
+        let ok = Message::new()
+            .kv_str("success", "yes")
+            .kv_str("matches", "2")
+            .kv_str("terminated", "2");
+        let ok = ControlOutcome::from_message(&ok).unwrap();
+        assert!(ok.success);
+        assert_eq!(ok.matches, Some(2));
+        assert_eq!(ok.terminated, Some(2));
+
+        let failed = Message::new()
+            .kv_str("success", "no")
+            .kv_str("errmsg", "CHILD_SA config 'x' not found");
+        let failed = ControlOutcome::from_message(&failed).unwrap();
+        assert!(!failed.success);
+        assert!(matches!(
+            failed.into_result(),
+            Err(Error::CommandFailed(m)) if m.contains("not found")
+        ));
+    }
+}
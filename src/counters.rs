@@ -0,0 +1,361 @@
+//! IKE message counters (`get-counters`/`reset-counters`).
+//!
+//! Counters are provided by charon's `counters` plugin, either globally or
+//! per connection. [`CounterSnapshot`] pairs a reading with the time it was
+//! taken so two readings can be turned into deltas and per-second rates.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Index;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::client::{expect_success, Client};
+use crate::error::{Error, Result};
+use crate::wire::{Message, Section};
+
+/// A single IKE counter reported by charon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Counter {
+    /// IKE_SA rekeyings initiated.
+    IkeRekeyInit,
+    /// IKE_SA rekeyings responded to.
+    IkeRekeyResp,
+    /// CHILD_SA rekeyings.
+    ChildRekey,
+    /// Invalid messages received.
+    Invalid,
+    /// Messages with an invalid IKE SPI.
+    InvalidSpi,
+    /// Inbound IKE_SA_INIT requests.
+    IkeInitInReq,
+    /// Inbound IKE_SA_INIT responses.
+    IkeInitInResp,
+    /// Outbound IKE_SA_INIT requests.
+    IkeInitOutReq,
+    /// Outbound IKE_SA_INIT responses.
+    IkeInitOutResp,
+    /// Inbound IKE_AUTH requests.
+    IkeAuthInReq,
+    /// Inbound IKE_AUTH responses.
+    IkeAuthInResp,
+    /// Outbound IKE_AUTH requests.
+    IkeAuthOutReq,
+    /// Outbound IKE_AUTH responses.
+    IkeAuthOutResp,
+    /// Inbound CREATE_CHILD_SA requests.
+    CreateChildInReq,
+    /// Inbound CREATE_CHILD_SA responses.
+    CreateChildInResp,
+    /// Outbound CREATE_CHILD_SA requests.
+    CreateChildOutReq,
+    /// Outbound CREATE_CHILD_SA responses.
+    CreateChildOutResp,
+    /// Inbound INFORMATIONAL requests.
+    InfoInReq,
+    /// Inbound INFORMATIONAL responses.
+    InfoInResp,
+    /// Outbound INFORMATIONAL requests.
+    InfoOutReq,
+    /// Outbound INFORMATIONAL responses.
+    InfoOutResp,
+}
+
+impl Counter {
+    /// Every counter, in charon's reporting order.
+    pub const ALL: [Counter; 21] = [
+        Counter::IkeRekeyInit,
+        Counter::IkeRekeyResp,
+        Counter::ChildRekey,
+        Counter::Invalid,
+        Counter::InvalidSpi,
+        Counter::IkeInitInReq,
+        Counter::IkeInitInResp,
+        Counter::IkeInitOutReq,
+        Counter::IkeInitOutResp,
+        Counter::IkeAuthInReq,
+        Counter::IkeAuthInResp,
+        Counter::IkeAuthOutReq,
+        Counter::IkeAuthOutResp,
+        Counter::CreateChildInReq,
+        Counter::CreateChildInResp,
+        Counter::CreateChildOutReq,
+        Counter::CreateChildOutResp,
+        Counter::InfoInReq,
+        Counter::InfoInResp,
+        Counter::InfoOutReq,
+        Counter::InfoOutResp,
+    ];
+
+    /// The name charon reports this counter under.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Counter::IkeRekeyInit => "ike-rekey-init",
+            Counter::IkeRekeyResp => "ike-rekey-resp",
+            Counter::ChildRekey => "child-rekey",
+            Counter::Invalid => "invalid",
+            Counter::InvalidSpi => "invalid-spi",
+            Counter::IkeInitInReq => "ike-init-in-req",
+            Counter::IkeInitInResp => "ike-init-in-resp",
+            Counter::IkeInitOutReq => "ike-init-out-req",
+            Counter::IkeInitOutResp => "ike-init-out-resp",
+            Counter::IkeAuthInReq => "ike-auth-in-req",
+            Counter::IkeAuthInResp => "ike-auth-in-resp",
+            Counter::IkeAuthOutReq => "ike-auth-out-req",
+            Counter::IkeAuthOutResp => "ike-auth-out-resp",
+            Counter::CreateChildInReq => "create-child-in-req",
+            Counter::CreateChildInResp => "create-child-in-resp",
+            Counter::CreateChildOutReq => "create-child-out-req",
+            Counter::CreateChildOutResp => "create-child-out-resp",
+            Counter::InfoInReq => "info-in-req",
+            Counter::InfoInResp => "info-in-resp",
+            Counter::InfoOutReq => "info-out-req",
+            Counter::InfoOutResp => "info-out-resp",
+        }
+    }
+}
+
+impl FromStr for Counter {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Counter::ALL
+            .iter()
+            .copied()
+            .find(|c| c.as_str() == s)
+            .ok_or(Error::Protocol("unknown counter"))
+    }
+}
+
+impl fmt::Display for Counter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Values of all IKE counters, globally or for one connection.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counters {
+    values: [u64; 21],
+}
+
+impl Counters {
+    /// Parse one counter section of a `get-counters` response.
+    ///
+    /// Counters this crate does not know about are ignored.
+    pub fn from_section(sec: &Section) -> Self {
+        let mut counters = Counters::default();
+        for c in Counter::ALL {
+            counters.values[c as usize] = sec.parse(c.as_str()).unwrap_or(0);
+        }
+        counters
+    }
+
+    /// Value of counter `c`.
+    pub fn get(&self, c: Counter) -> u64 {
+        self.values[c as usize]
+    }
+
+    /// Iterate over all counters and their values.
+    pub fn iter(&self) -> impl Iterator<Item = (Counter, u64)> + '_ {
+        Counter::ALL.iter().map(move |&c| (c, self.get(c)))
+    }
+
+    /// Counter increase since `earlier`.
+    ///
+    /// A counter that went backwards was reset in between; its current value
+    /// is then taken as the increase.
+    pub fn delta(&self, earlier: &Counters) -> Counters {
+        let mut delta = Counters::default();
+        for c in Counter::ALL {
+            let (now, then) = (self.get(c), earlier.get(c));
+            delta.values[c as usize] = if now >= then { now - then } else { now };
+        }
+        delta
+    }
+}
+
+impl Index<Counter> for Counters {
+    type Output = u64;
+
+    fn index(&self, c: Counter) -> &u64 {
+        &self.values[c as usize]
+    }
+}
+
+/// A counter reading together with the time it was taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CounterSnapshot {
+    /// When the reading was taken.
+    pub taken: Instant,
+    /// The counter values.
+    pub counters: Counters,
+}
+
+impl CounterSnapshot {
+    /// Wrap a reading taken just now.
+    pub fn now(counters: Counters) -> Self {
+        Self {
+            taken: Instant::now(),
+            counters,
+        }
+    }
+
+    /// Deltas and rates between `earlier` and this snapshot.
+    pub fn since(&self, earlier: &CounterSnapshot) -> CounterRates {
+        CounterRates {
+            elapsed: self.taken.saturating_duration_since(earlier.taken),
+            delta: self.counters.delta(&earlier.counters),
+        }
+    }
+}
+
+/// Counter changes over an interval.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CounterRates {
+    /// Length of the interval.
+    pub elapsed: Duration,
+    /// Counter increase over the interval.
+    pub delta: Counters,
+}
+
+impl CounterRates {
+    /// Average per-second rate of counter `c` (0 for an empty interval).
+    pub fn per_second(&self, c: Counter) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs == 0.0 {
+            0.0
+        } else {
+            self.delta.get(c) as f64 / secs
+        }
+    }
+
+    /// Iterate over all counters with their delta and per-second rate.
+    pub fn iter(&self) -> impl Iterator<Item = (Counter, u64, f64)> + '_ {
+        Counter::ALL
+            .iter()
+            .map(move |&c| (c, self.delta.get(c), self.per_second(c)))
+    }
+}
+
+impl Client {
+    /// Read the global counters, or those of connection `name`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::{thread, time::Duration};
+    /// use rustici::Client;
+    /// use rustici::counters::{Counter, CounterSnapshot};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = Client::connect("/var/run/charon.vici")?;
+    /// let before = CounterSnapshot::now(client.get_counters(None)?);
+    /// thread::sleep(Duration::from_secs(10));
+    /// let after = CounterSnapshot::now(client.get_counters(None)?);
+    /// let rates = after.since(&before);
+    /// println!("IKE_SA_INIT/s: {:.2}", rates.per_second(Counter::IkeInitInReq));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_counters(&mut self, name: Option<&str>) -> Result<Counters> {
+        let mut req = Message::new();
+        if let Some(name) = name {
+            req = req.kv_str("name", name);
+        }
+        let resp = self.call("get-counters", &req)?;
+        let tree = expect_success(&resp)?;
+        let section = tree
+            .section("counters")
+            .and_then(|c| c.section(name.unwrap_or("")))
+            .ok_or(Error::Protocol("counters missing from response"))?;
+        Ok(Counters::from_section(section))
+    }
+
+    /// Read the counters of all connections; the global counters use the key `""`.
+    pub fn get_all_counters(&mut self) -> Result<BTreeMap<String, Counters>> {
+        let resp = self.call("get-counters", &Message::new().kv_str("all", "yes"))?;
+        let tree = expect_success(&resp)?;
+        Ok(tree
+            .section("counters")
+            .map(|c| {
+                c.sections()
+                    .map(|(name, sec)| (name.to_string(), Counters::from_section(sec)))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Reset the global counters, or those of connection `name`.
+    pub fn reset_counters(&mut self, name: Option<&str>) -> Result<()> {
+        let mut req = Message::new();
+        if let Some(name) = name {
+            req = req.kv_str("name", name);
+        }
+        let resp = self.call("reset-counters", &req)?;
+        expect_success(&resp).map(|_| ())
+    }
+
+    /// Reset the counters of all connections as well as the global ones.
+    pub fn reset_all_counters(&mut self) -> Result<()> {
+        let resp = self.call("reset-counters", &Message::new().kv_str("all", "yes"))?;
+        expect_success(&resp).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counters(pairs: &[(Counter, u64)]) -> Counters {
+        let mut sec = Section::new();
+        for (c, v) in pairs {
+            sec.insert_str(c.as_str(), v.to_string());
+        }
+        Counters::from_section(&sec)
+    }
+
+    #[test]
+    fn parse_response_section() {
+        let msg = Message::new()
+            .section_start("counters")
+            .section_start("")
+            .kv_str("ike-init-in-req", "12")
+            .kv_str("child-rekey", "3")
+            .kv_str("some-future-counter", "9")
+            .section_end()
+            .section_end()
+            .kv_str("success", "yes");
+        let tree = msg.tree().unwrap();
+        let global = Counters::from_section(tree.section("counters").unwrap().section("").unwrap());
+        assert_eq!(global[Counter::IkeInitInReq], 12);
+        assert_eq!(global.get(Counter::ChildRekey), 3);
+        assert_eq!(global.get(Counter::InfoOutResp), 0);
+    }
+
+    #[test]
+    fn delta_and_rates() {
+        let t0 = Instant::now();
+        let before = CounterSnapshot {
+            taken: t0,
+            counters: counters(&[(Counter::IkeInitInReq, 10), (Counter::ChildRekey, 50)]),
+        };
+        let after = CounterSnapshot {
+            taken: t0 + Duration::from_secs(4),
+            counters: counters(&[(Counter::IkeInitInReq, 30), (Counter::ChildRekey, 2)]),
+        };
+        let rates = after.since(&before);
+        assert_eq!(rates.delta[Counter::IkeInitInReq], 20);
+        assert_eq!(rates.per_second(Counter::IkeInitInReq), 5.0);
+        // counter was reset in between
+        assert_eq!(rates.delta[Counter::ChildRekey], 2);
+        assert_eq!(rates.iter().count(), Counter::ALL.len());
+    }
+
+    #[test]
+    fn counter_names_roundtrip() {
+        for c in Counter::ALL {
+            assert_eq!(c.as_str().parse::<Counter>().unwrap(), c);
+        }
+    }
+}
@@ -0,0 +1,473 @@
+//! Credential management: certificates, private keys, tokens and shared secrets.
+//!
+//! Certificate and key blobs may be PEM or DER; charon detects the encoding
+//! itself, so the `*_file` helpers simply read the file and pass the bytes on
+//! unmodified as binary message values.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::client::{expect_success, Client};
+use crate::error::{Error, Result};
+use crate::wire::Message;
+
+/// Certificate type accepted by `load-cert` and `flush-certs`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CertType {
+    /// X.509 certificate.
+    X509,
+    /// X.509 CA certificate.
+    X509Ca,
+    /// X.509 attribute authority certificate.
+    X509Aa,
+    /// X.509 certificate revocation list.
+    X509Crl,
+    /// X.509 attribute certificate.
+    X509Ac,
+    /// Raw public key.
+    Pubkey,
+}
+
+impl CertType {
+    /// The keyword charon uses for this type.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CertType::X509 => "x509",
+            CertType::X509Ca => "x509ca",
+            CertType::X509Aa => "x509aa",
+            CertType::X509Crl => "x509crl",
+            CertType::X509Ac => "x509ac",
+            CertType::Pubkey => "pubkey",
+        }
+    }
+}
+
+impl FromStr for CertType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "x509" => Ok(CertType::X509),
+            "x509ca" => Ok(CertType::X509Ca),
+            "x509aa" => Ok(CertType::X509Aa),
+            "x509crl" => Ok(CertType::X509Crl),
+            "x509ac" => Ok(CertType::X509Ac),
+            "pubkey" => Ok(CertType::Pubkey),
+            _ => Err(Error::Protocol("unknown certificate type")),
+        }
+    }
+}
+
+impl fmt::Display for CertType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// X.509 certificate flag accepted by `load-cert`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CertFlag {
+    /// No flag.
+    None,
+    /// Trusted end-entity certificate.
+    Trusted,
+    /// CA certificate.
+    Ca,
+    /// Attribute authority certificate.
+    Aa,
+    /// OCSP signer certificate.
+    Ocsp,
+}
+
+impl CertFlag {
+    /// The keyword charon uses for this flag.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CertFlag::None => "none",
+            CertFlag::Trusted => "trusted",
+            CertFlag::Ca => "ca",
+            CertFlag::Aa => "aa",
+            CertFlag::Ocsp => "ocsp",
+        }
+    }
+}
+
+impl FromStr for CertFlag {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(CertFlag::None),
+            "trusted" => Ok(CertFlag::Trusted),
+            "ca" => Ok(CertFlag::Ca),
+            "aa" => Ok(CertFlag::Aa),
+            "ocsp" => Ok(CertFlag::Ocsp),
+            _ => Err(Error::Protocol("unknown certificate flag")),
+        }
+    }
+}
+
+impl fmt::Display for CertFlag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Private key type accepted by `load-key`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyType {
+    /// Let charon detect the key type.
+    Any,
+    /// RSA key.
+    Rsa,
+    /// ECDSA key.
+    Ecdsa,
+    /// Ed25519 key.
+    Ed25519,
+    /// Ed448 key.
+    Ed448,
+}
+
+impl KeyType {
+    /// The keyword charon uses for this type.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            KeyType::Any => "any",
+            KeyType::Rsa => "rsa",
+            KeyType::Ecdsa => "ecdsa",
+            KeyType::Ed25519 => "ed25519",
+            KeyType::Ed448 => "ed448",
+        }
+    }
+}
+
+impl fmt::Display for KeyType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Type of a shared secret.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SharedType {
+    /// IKE pre-shared key.
+    Ike,
+    /// EAP secret.
+    Eap,
+    /// XAuth secret.
+    Xauth,
+    /// NTLM hash.
+    Ntlm,
+    /// Postquantum preshared key.
+    Ppk,
+}
+
+impl SharedType {
+    /// The keyword charon uses for this type.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SharedType::Ike => "IKE",
+            SharedType::Eap => "EAP",
+            SharedType::Xauth => "XAUTH",
+            SharedType::Ntlm => "NTLM",
+            SharedType::Ppk => "PPK",
+        }
+    }
+}
+
+impl fmt::Display for SharedType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A shared secret for `load-shared`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SharedSecret {
+    id: Option<String>,
+    ty: SharedType,
+    data: Vec<u8>,
+    owners: Vec<String>,
+}
+
+impl SharedSecret {
+    /// Create a secret of type `ty` with the raw secret `data`.
+    pub fn new(ty: SharedType, data: impl Into<Vec<u8>>) -> Self {
+        Self {
+            id: None,
+            ty,
+            data: data.into(),
+            owners: Vec::new(),
+        }
+    }
+
+    /// Unique identifier, required to unload or replace the secret later.
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Add an identity the secret belongs to.
+    pub fn owner(mut self, identity: impl Into<String>) -> Self {
+        self.owners.push(identity.into());
+        self
+    }
+
+    /// Encode the `load-shared` request message.
+    pub fn to_message(&self) -> Message {
+        let mut msg = Message::new();
+        if let Some(id) = &self.id {
+            msg = msg.kv_str("id", id);
+        }
+        msg = msg
+            .kv_str("type", self.ty.as_str())
+            .kv_bytes("data", &self.data);
+        if !self.owners.is_empty() {
+            msg = msg.list_start("owners");
+            for owner in &self.owners {
+                msg = msg.list_item_str(owner);
+            }
+            msg = msg.list_end();
+        }
+        msg
+    }
+}
+
+// Keep secrets out of debug logs.
+impl fmt::Debug for SharedSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedSecret")
+            .field("id", &self.id)
+            .field("ty", &self.ty)
+            .field("data", &"<redacted>")
+            .field("owners", &self.owners)
+            .finish()
+    }
+}
+
+/// A private key on a PKCS#11 token for `load-token`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct TokenKey {
+    handle: String,
+    slot: Option<u32>,
+    module: Option<String>,
+    pin: Option<String>,
+}
+
+impl TokenKey {
+    /// Reference the key object with the hex-encoded `handle` (CKA_ID).
+    pub fn new(handle: impl Into<String>) -> Self {
+        Self {
+            handle: handle.into(),
+            slot: None,
+            module: None,
+            pin: None,
+        }
+    }
+
+    /// Slot the token is in.
+    pub fn slot(mut self, slot: u32) -> Self {
+        self.slot = Some(slot);
+        self
+    }
+
+    /// PKCS#11 module name as configured in strongswan.conf.
+    pub fn module(mut self, module: impl Into<String>) -> Self {
+        self.module = Some(module.into());
+        self
+    }
+
+    /// PIN to unlock the token.
+    pub fn pin(mut self, pin: impl Into<String>) -> Self {
+        self.pin = Some(pin.into());
+        self
+    }
+
+    /// Encode the `load-token` request message.
+    pub fn to_message(&self) -> Message {
+        let mut msg = Message::new().kv_str("handle", &self.handle);
+        if let Some(slot) = self.slot {
+            msg = msg.kv_str("slot", slot.to_string());
+        }
+        if let Some(module) = &self.module {
+            msg = msg.kv_str("module", module);
+        }
+        if let Some(pin) = &self.pin {
+            msg = msg.kv_str("pin", pin);
+        }
+        msg
+    }
+}
+
+impl fmt::Debug for TokenKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenKey")
+            .field("handle", &self.handle)
+            .field("slot", &self.slot)
+            .field("module", &self.module)
+            .field("pin", &self.pin.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+impl Client {
+    /// Load a certificate, CRL or public key (PEM or DER).
+    ///
+    /// `flag` only applies to `CertType::X509`; pass `None` to omit it.
+    pub fn load_cert(&mut self, ty: CertType, flag: Option<CertFlag>, data: &[u8]) -> Result<()> {
+        let mut req = Message::new().kv_str("type", ty.as_str());
+        if let Some(flag) = flag {
+            req = req.kv_str("flag", flag.as_str());
+        }
+        req = req.kv_bytes("data", data);
+        let resp = self.call("load-cert", &req)?;
+        expect_success(&resp).map(|_| ())
+    }
+
+    /// Read a PEM or DER file and load it with [`Client::load_cert`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rustici::Client;
+    /// use rustici::creds::{CertFlag, CertType};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = Client::connect("/var/run/charon.vici")?;
+    /// client.load_cert_file(CertType::X509, Some(CertFlag::Ca), "/etc/swanctl/x509ca/ca.pem")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_cert_file<P: AsRef<Path>>(
+        &mut self,
+        ty: CertType,
+        flag: Option<CertFlag>,
+        path: P,
+    ) -> Result<()> {
+        let data = fs::read(path)?;
+        self.load_cert(ty, flag, &data)
+    }
+
+    /// Load a private key (PEM or DER) and return its key identifier.
+    pub fn load_key(&mut self, ty: KeyType, data: &[u8]) -> Result<Option<String>> {
+        let req = Message::new()
+            .kv_str("type", ty.as_str())
+            .kv_bytes("data", data);
+        let resp = self.call("load-key", &req)?;
+        Ok(expect_success(&resp)?.str("id").map(str::to_string))
+    }
+
+    /// Read a PEM or DER file and load it with [`Client::load_key`].
+    pub fn load_key_file<P: AsRef<Path>>(
+        &mut self,
+        ty: KeyType,
+        path: P,
+    ) -> Result<Option<String>> {
+        let data = fs::read(path)?;
+        self.load_key(ty, &data)
+    }
+
+    /// Unload the private key with identifier `id`.
+    pub fn unload_key(&mut self, id: &str) -> Result<()> {
+        let resp = self.call("unload-key", &Message::new().kv_str("id", id))?;
+        expect_success(&resp).map(|_| ())
+    }
+
+    /// Identifiers of all private keys loaded over VICI.
+    pub fn get_keys(&mut self) -> Result<Vec<String>> {
+        let resp = self.call("get-keys", &Message::new())?;
+        Ok(resp.tree()?.str_list("keys"))
+    }
+
+    /// Load a private key located on a token and return its key identifier.
+    pub fn load_token(&mut self, token: &TokenKey) -> Result<Option<String>> {
+        let resp = self.call("load-token", &token.to_message())?;
+        Ok(expect_success(&resp)?.str("id").map(str::to_string))
+    }
+
+    /// Load (or replace) a shared secret.
+    pub fn load_shared(&mut self, secret: &SharedSecret) -> Result<()> {
+        let resp = self.call("load-shared", &secret.to_message())?;
+        expect_success(&resp).map(|_| ())
+    }
+
+    /// Unload the shared secret with identifier `id`.
+    pub fn unload_shared(&mut self, id: &str) -> Result<()> {
+        let resp = self.call("unload-shared", &Message::new().kv_str("id", id))?;
+        expect_success(&resp).map(|_| ())
+    }
+
+    /// Identifiers of all shared secrets loaded over VICI.
+    pub fn get_shared(&mut self) -> Result<Vec<String>> {
+        let resp = self.call("get-shared", &Message::new())?;
+        Ok(resp.tree()?.str_list("keys"))
+    }
+
+    /// Flush the volatile certificate cache, optionally only of type `ty`.
+    pub fn flush_certs(&mut self, ty: Option<CertType>) -> Result<()> {
+        let mut req = Message::new();
+        if let Some(ty) = ty {
+            req = req.kv_str("type", ty.as_str());
+        }
+        let resp = self.call("flush-certs", &req)?;
+        expect_success(&resp).map(|_| ())
+    }
+
+    /// Clear all credentials loaded over VICI.
+    pub fn clear_creds(&mut self) -> Result<()> {
+        let resp = self.call("clear-creds", &Message::new())?;
+        expect_success(&resp).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_secret_encoding_keeps_binary_data() {
+        let secret = SharedSecret::new(SharedType::Ike, vec![0x00, 0xff, 0x10])
+            .id("psk-1")
+            .owner("gw.example.org")
+            .owner("peer.example.org");
+        let tree = secret.to_message().tree().unwrap();
+        assert_eq!(tree.str("id"), Some("psk-1"));
+        assert_eq!(tree.str("type"), Some("IKE"));
+        assert_eq!(tree.bytes("data"), Some(&[0x00, 0xff, 0x10][..]));
+        assert_eq!(
+            tree.str_list("owners"),
+            ["gw.example.org", "peer.example.org"]
+        );
+        assert!(!format!("{secret:?}").contains("255"));
+    }
+
+    #[test]
+    fn token_encoding() {
+        let tree = TokenKey::new("0a1b")
+            .slot(1)
+            .pin("1234")
+            .to_message()
+            .tree()
+            .unwrap();
+        assert_eq!(tree.str("handle"), Some("0a1b"));
+        assert_eq!(tree.str("slot"), Some("1"));
+        assert_eq!(tree.str("pin"), Some("1234"));
+        assert_eq!(tree.get("module"), None);
+    }
+
+    #[test]
+    fn cert_type_roundtrip() {
+        for ty in [
+            CertType::X509,
+            CertType::X509Ca,
+            CertType::X509Aa,
+            CertType::X509Crl,
+            CertType::X509Ac,
+            CertType::Pubkey,
+        ] {
+            assert_eq!(ty.as_str().parse::<CertType>().unwrap(), ty);
+        }
+    }
+}
@@ -0,0 +1,179 @@
+//! Daemon-wide commands: `reload-settings` and `get-algorithms`.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::client::{expect_success, Client};
+use crate::error::Result;
+use crate::wire::{Message, Value};
+
+/// Class of a crypto algorithm as reported by `get-algorithms`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AlgorithmClass {
+    /// Symmetric encryption.
+    Encryption,
+    /// Integrity protection.
+    Integrity,
+    /// Authenticated encryption.
+    Aead,
+    /// Hash functions.
+    Hasher,
+    /// Pseudo-random functions.
+    Prf,
+    /// Extendable-output functions.
+    Xof,
+    /// Deterministic random bit generators.
+    Drbg,
+    /// Diffie-Hellman groups.
+    Dh,
+    /// Key exchange methods (reported instead of `dh` by newer daemons).
+    Ke,
+    /// Random number generators.
+    Rng,
+    /// Nonce generators.
+    NonceGen,
+    /// Signature schemes (signing).
+    Signer,
+    /// Signature schemes (verification).
+    Verifier,
+    /// A class this crate does not know about.
+    Other(String),
+}
+
+impl AlgorithmClass {
+    /// The section name charon uses for this class.
+    pub fn as_str(&self) -> &str {
+        match self {
+            AlgorithmClass::Encryption => "encryption",
+            AlgorithmClass::Integrity => "integrity",
+            AlgorithmClass::Aead => "aead",
+            AlgorithmClass::Hasher => "hasher",
+            AlgorithmClass::Prf => "prf",
+            AlgorithmClass::Xof => "xof",
+            AlgorithmClass::Drbg => "drbg",
+            AlgorithmClass::Dh => "dh",
+            AlgorithmClass::Ke => "ke",
+            AlgorithmClass::Rng => "rng",
+            AlgorithmClass::NonceGen => "nonce-gen",
+            AlgorithmClass::Signer => "signer",
+            AlgorithmClass::Verifier => "verifier",
+            AlgorithmClass::Other(s) => s,
+        }
+    }
+
+    /// Map a section name to its class; unknown names become `Other`.
+    pub fn from_name(s: &str) -> Self {
+        match s {
+            "encryption" => AlgorithmClass::Encryption,
+            "integrity" => AlgorithmClass::Integrity,
+            "aead" => AlgorithmClass::Aead,
+            "hasher" => AlgorithmClass::Hasher,
+            "prf" => AlgorithmClass::Prf,
+            "xof" => AlgorithmClass::Xof,
+            "drbg" => AlgorithmClass::Drbg,
+            "dh" => AlgorithmClass::Dh,
+            "ke" => AlgorithmClass::Ke,
+            "rng" => AlgorithmClass::Rng,
+            "nonce-gen" => AlgorithmClass::NonceGen,
+            "signer" => AlgorithmClass::Signer,
+            "verifier" => AlgorithmClass::Verifier,
+            other => AlgorithmClass::Other(other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for AlgorithmClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An available algorithm and the plugin implementing it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Algorithm {
+    /// Algorithm name, e.g. `AES_CBC` or `CURVE_25519`.
+    pub name: String,
+    /// Plugin providing the implementation, e.g. `openssl`.
+    pub plugin: String,
+}
+
+/// Algorithms grouped by class, as returned by [`Client::get_algorithms`].
+pub type Algorithms = BTreeMap<AlgorithmClass, Vec<Algorithm>>;
+
+/// Parse a `get-algorithms` response.
+pub fn parse_algorithms(msg: &Message) -> Result<Algorithms> {
+    let tree = msg.tree()?;
+    let mut out = Algorithms::new();
+    for (class, sec) in tree.sections() {
+        let algs = sec
+            .entries()
+            .iter()
+            .filter_map(|(name, value)| match value {
+                Value::Bytes(plugin) => Some(Algorithm {
+                    name: name.clone(),
+                    plugin: String::from_utf8_lossy(plugin).into_owned(),
+                }),
+                _ => None,
+            })
+            .collect();
+        out.insert(AlgorithmClass::from_name(class), algs);
+    }
+    Ok(out)
+}
+
+impl Client {
+    /// Reload `strongswan.conf` settings and all plugins supporting reload.
+    pub fn reload_settings(&mut self) -> Result<()> {
+        let resp = self.call("reload-settings", &Message::new())?;
+        expect_success(&resp).map(|_| ())
+    }
+
+    /// List the algorithms charon has loaded, grouped by class.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rustici::Client;
+    /// use rustici::daemon::AlgorithmClass;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = Client::connect("/var/run/charon.vici")?;
+    /// let algs = client.get_algorithms()?;
+    /// for alg in algs.get(&AlgorithmClass::Aead).into_iter().flatten() {
+    ///     println!("{} ({})", alg.name, alg.plugin);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_algorithms(&mut self) -> Result<Algorithms> {
+        let resp = self.call("get-algorithms", &Message::new())?;
+        parse_algorithms(&resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn algorithms_parsing() {
+        let msg = Message::new()
+            .section_start("encryption")
+            .kv_str("AES_CBC", "aes")
+            .kv_str("CAMELLIA_CBC", "openssl")
+            .section_end()
+            .section_start("nonce-gen")
+            .kv_str("NONCE_GEN", "nonce")
+            .section_end()
+            .section_start("quantum")
+            .kv_str("ML_KEM_768", "ml")
+            .section_end();
+        let algs = parse_algorithms(&msg).unwrap();
+        let enc = &algs[&AlgorithmClass::Encryption];
+        assert_eq!(enc.len(), 2);
+        assert_eq!(enc[1].name, "CAMELLIA_CBC");
+        assert_eq!(enc[1].plugin, "openssl");
+        assert_eq!(algs[&AlgorithmClass::NonceGen][0].plugin, "nonce");
+        assert!(algs.contains_key(&AlgorithmClass::Other("quantum".into())));
+    }
+}
@@ -5,7 +5,10 @@ use std::{fmt, io, string::FromUtf8Error};
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
 /// Errors that may occur while encoding/decoding or doing IO.
+///
+/// New variants may be added in minor releases.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// Underlying I/O error.
     Io(io::Error),
@@ -13,12 +16,46 @@ pub enum Error {
     Protocol(&'static str),
     /// The remote reported an unknown command.
     UnknownCommand(String),
+    /// The remote executed the command but reported a failure (its `errmsg`).
+    CommandFailed(String),
+    /// A configuration file is invalid; the message says where and why.
+    Config(String),
+    /// A message literal is invalid; the message says where and why.
+    Literal(String),
     /// Too large message/field.
     TooLong(&'static str),
     /// UTF-8 conversion failed (when interpreting bytes as a String).
     Utf8(FromUtf8Error),
     /// Operation timed out.
     Timeout,
+    /// The call was aborted through its [`CancelToken`](crate::client::CancelToken).
+    Cancelled,
+    /// The connection lost framing sync (e.g. a partially written request)
+    /// and can no longer be used; reconnect.
+    Poisoned,
+}
+
+impl Error {
+    /// Whether this error means the connection to charon is gone (EOF,
+    /// broken pipe, reset, or a refused/missing socket on connect) or
+    /// unusable.
+    pub fn is_disconnect(&self) -> bool {
+        use io::ErrorKind::*;
+        match self {
+            Error::Poisoned => true,
+            Error::Io(e) => matches!(
+                e.kind(),
+                UnexpectedEof
+                    | BrokenPipe
+                    | ConnectionReset
+                    | ConnectionAborted
+                    | ConnectionRefused
+                    | NotConnected
+                    | NotFound
+            ),
+            _ => false,
+        }
+    }
 }
 
 impl From<io::Error> for Error {
@@ -39,9 +76,14 @@ impl fmt::Display for Error {
             Error::Io(e) => write!(f, "io error: {e}"),
             Error::Protocol(s) => write!(f, "protocol error: {s}"),
             Error::UnknownCommand(cmd) => write!(f, "unknown command: {cmd}"),
+            Error::CommandFailed(msg) => write!(f, "command failed: {msg}"),
+            Error::Config(msg) => write!(f, "invalid configuration: {msg}"),
+            Error::Literal(msg) => write!(f, "invalid message literal: {msg}"),
             Error::TooLong(what) => write!(f, "value too long: {what}"),
             Error::Utf8(e) => write!(f, "utf-8 error: {e}"),
             Error::Timeout => write!(f, "operation timed out"),
+            Error::Cancelled => write!(f, "operation cancelled"),
+            Error::Poisoned => write!(f, "connection out of sync, reconnect required"),
         }
     }
 }
@@ -0,0 +1,363 @@
+//! Typed charon events.
+//!
+//! [`Client::next_event`] hands out the raw `(name, Message)` pair; the
+//! `*_typed_event` variants decode it into an [`Event`] instead. Events this
+//! crate does not know about are passed through as [`Event::Unknown`] so
+//! consumers keep working against newer daemons.
+
+use std::time::Duration;
+
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::logging::LogRecord;
+use crate::sa::{ChildSa, IkeSa};
+use crate::wire::{Message, Section};
+
+/// A decoded charon event.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// `ike-updown`: an IKE_SA went up or down.
+    IkeUpdown {
+        /// `true` when the IKE_SA was established, `false` when it went down.
+        up: bool,
+        /// The IKE_SA.
+        ike: IkeSa,
+    },
+    /// `child-updown`: a CHILD_SA went up or down.
+    ChildUpdown {
+        /// `true` when the CHILD_SA was installed, `false` when it went down.
+        up: bool,
+        /// The owning IKE_SA; its `child_sas` holds the affected CHILD_SA.
+        ike: IkeSa,
+    },
+    /// `ike-rekey`: an IKE_SA was rekeyed.
+    IkeRekey {
+        /// The IKE_SA being replaced.
+        old: IkeSa,
+        /// The replacing IKE_SA.
+        new: IkeSa,
+    },
+    /// `child-rekey`: a CHILD_SA was rekeyed.
+    ChildRekey {
+        /// The owning IKE_SA (without CHILD_SAs).
+        ike: IkeSa,
+        /// The CHILD_SA being replaced.
+        old: ChildSa,
+        /// The replacing CHILD_SA.
+        new: ChildSa,
+    },
+    /// `ike-update`: the endpoints of an IKE_SA changed (e.g. MOBIKE, NAT).
+    IkeUpdate {
+        /// New local address.
+        local_host: String,
+        /// New local port.
+        local_port: u16,
+        /// New remote address.
+        remote_host: String,
+        /// New remote port.
+        remote_port: u16,
+        /// The IKE_SA, still listing its previous endpoints.
+        ike: IkeSa,
+    },
+    /// `ike-reestablish`: an IKE_SA was reestablished.
+    IkeReestablish {
+        /// The IKE_SA being replaced, if reported.
+        old: Option<IkeSa>,
+        /// The reestablished IKE_SA.
+        new: IkeSa,
+    },
+    /// `log`: a daemon log line.
+    Log(LogRecord),
+    /// `control-log`: a log line related to a running control command.
+    ControlLog(LogRecord),
+    /// Any other event, passed through undecoded.
+    Unknown {
+        /// Event name.
+        name: String,
+        /// Event message.
+        message: Message,
+    },
+}
+
+impl Event {
+    /// Decode the event `name` carrying `msg`.
+    ///
+    /// Unknown event names are not an error; they become [`Event::Unknown`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rustici::{Message, event::Event};
+    ///
+    /// let msg = Message::new()
+    ///     .kv_str("group", "ike")
+    ///     .kv_str("level", "1")
+    ///     .kv_str("msg", "IKE_SA gw[1] established");
+    /// match Event::parse("log", &msg).unwrap() {
+    ///     Event::Log(rec) => assert_eq!(rec.group, "ike"),
+    ///     other => panic!("unexpected {other:?}"),
+    /// }
+    /// ```
+    pub fn parse(name: &str, msg: &Message) -> Result<Self> {
+        match name {
+            "ike-updown" => {
+                let tree = msg.tree()?;
+                let (name, sec) = single_ike(&tree)?;
+                Ok(Event::IkeUpdown {
+                    up: tree.flag("up").unwrap_or(false),
+                    ike: IkeSa::from_section(name, sec)?,
+                })
+            }
+            "child-updown" => {
+                let tree = msg.tree()?;
+                let (name, sec) = single_ike(&tree)?;
+                Ok(Event::ChildUpdown {
+                    up: tree.flag("up").unwrap_or(false),
+                    ike: IkeSa::from_section(name, sec)?,
+                })
+            }
+            "ike-rekey" => {
+                let tree = msg.tree()?;
+                let (name, sec) = single_ike(&tree)?;
+                let (old, new) = old_new(sec)?;
+                Ok(Event::IkeRekey {
+                    old: IkeSa::from_section(name, old)?,
+                    new: IkeSa::from_section(name, new)?,
+                })
+            }
+            "child-rekey" => parse_child_rekey(msg),
+            "ike-update" => {
+                let tree = msg.tree()?;
+                let (name, sec) = single_ike(&tree)?;
+                Ok(Event::IkeUpdate {
+                    local_host: tree.str("local-host").unwrap_or_default().to_string(),
+                    local_port: tree.parse("local-port").unwrap_or(0),
+                    remote_host: tree.str("remote-host").unwrap_or_default().to_string(),
+                    remote_port: tree.parse("remote-port").unwrap_or(0),
+                    ike: IkeSa::from_section(name, sec)?,
+                })
+            }
+            "ike-reestablish" => {
+                let tree = msg.tree()?;
+                let (name, sec) = single_ike(&tree)?;
+                match old_new(sec) {
+                    Ok((old, new)) => Ok(Event::IkeReestablish {
+                        old: Some(IkeSa::from_section(name, old)?),
+                        new: IkeSa::from_section(name, new)?,
+                    }),
+                    Err(_) => Ok(Event::IkeReestablish {
+                        old: None,
+                        new: IkeSa::from_section(name, sec)?,
+                    }),
+                }
+            }
+            "log" => Ok(Event::Log(LogRecord::from_message(msg)?)),
+            "control-log" => Ok(Event::ControlLog(LogRecord::from_message(msg)?)),
+            _ => Ok(Event::Unknown {
+                name: name.to_string(),
+                message: msg.clone(),
+            }),
+        }
+    }
+
+    /// The wire name of this event.
+    pub fn name(&self) -> &str {
+        match self {
+            Event::IkeUpdown { .. } => "ike-updown",
+            Event::ChildUpdown { .. } => "child-updown",
+            Event::IkeRekey { .. } => "ike-rekey",
+            Event::ChildRekey { .. } => "child-rekey",
+            Event::IkeUpdate { .. } => "ike-update",
+            Event::IkeReestablish { .. } => "ike-reestablish",
+            Event::Log(_) => "log",
+            Event::ControlLog(_) => "control-log",
+            Event::Unknown { name, .. } => name,
+        }
+    }
+}
+
+/// The one IKE_SA section an SA event is about.
+fn single_ike(tree: &Section) -> Result<(&str, &Section)> {
+    tree.sections()
+        .next()
+        .ok_or(Error::Protocol("event without IKE_SA section"))
+}
+
+fn old_new(sec: &Section) -> Result<(&Section, &Section)> {
+    match (sec.section("old"), sec.section("new")) {
+        (Some(old), Some(new)) => Ok((old, new)),
+        _ => Err(Error::Protocol("rekey event without old/new sections")),
+    }
+}
+
+fn parse_child_rekey(msg: &Message) -> Result<Event> {
+    let tree = msg.tree()?;
+    let (name, sec) = single_ike(&tree)?;
+    let (key, child) = sec
+        .section("child-sas")
+        .and_then(|c| c.sections().next())
+        .ok_or(Error::Protocol("child-rekey without CHILD_SA"))?;
+    let (old, new) = old_new(child)?;
+
+    // The child-sas section nests old/new, so parse the IKE_SA without it.
+    let mut ike = IkeSa::from_section(name, &without_children(sec))?;
+    ike.child_sas.clear();
+    Ok(Event::ChildRekey {
+        ike,
+        old: ChildSa::from_section(key, old)?,
+        new: ChildSa::from_section(key, new)?,
+    })
+}
+
+fn without_children(sec: &Section) -> Section {
+    let mut out = Section::new();
+    for (k, v) in sec.entries() {
+        if k != "child-sas" {
+            out.insert(k.clone(), v.clone());
+        }
+    }
+    out
+}
+
+impl Client {
+    /// Block until the next event arrives and decode it.
+    ///
+    /// Timeout behaviour is the same as [`Client::next_event`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rustici::{Client, event::Event};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = Client::connect("/var/run/charon.vici")?;
+    /// client.register_event("ike-updown")?;
+    /// loop {
+    ///     if let Event::IkeUpdown { up, ike } = client.next_typed_event()? {
+    ///         println!("{} #{} {}", ike.name, ike.uniqueid, if up { "up" } else { "down" });
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn next_typed_event(&mut self) -> Result<Event> {
+        let (name, msg) = self.next_event()?;
+        Event::parse(&name, &msg)
+    }
+
+    /// Wait at most `timeout` for the next event and decode it.
+    ///
+    /// Timeout behaviour is the same as [`Client::try_next_event`].
+    pub fn try_next_typed_event(&mut self, timeout: Duration) -> Result<Event> {
+        let (name, msg) = self.try_next_event(timeout)?;
+        Event::parse(&name, &msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ike(msg: Message, name: &str, id: &str) -> Message {
+        msg.section_start(name)
+            .kv_str("uniqueid", id)
+            .kv_str("state", "ESTABLISHED")
+    }
+
+    #[test]
+    fn ike_updown() {
+        let msg = ike(Message::new().kv_str("up", "yes"), "gw", "1").section_end();
+        match Event::parse("ike-updown", &msg).unwrap() {
+            Event::IkeUpdown { up, ike } => {
+                assert!(up);
+                assert_eq!(ike.name, "gw");
+                assert_eq!(ike.uniqueid, 1);
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+
+        // "up" is omitted when the SA goes down
+        let msg = ike(Message::new(), "gw", "1").section_end();
+        assert!(matches!(
+            Event::parse("ike-updown", &msg).unwrap(),
+            Event::IkeUpdown { up: false, .. }
+        ));
+    }
+
+    #[test]
+    fn ike_rekey() {
+        let msg = Message::new()
+            .section_start("gw")
+            .section_start("old")
+            .kv_str("uniqueid", "1")
+            .section_end()
+            .section_start("new")
+            .kv_str("uniqueid", "2")
+            .section_end()
+            .section_end();
+        match Event::parse("ike-rekey", &msg).unwrap() {
+            Event::IkeRekey { old, new } => {
+                assert_eq!((old.uniqueid, new.uniqueid), (1, 2));
+                assert_eq!(new.name, "gw");
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[test]
+    fn child_rekey() {
+        let msg = ike(Message::new(), "gw", "1")
+            .section_start("child-sas")
+            .section_start("net")
+            .section_start("old")
+            .kv_str("name", "net")
+            .kv_str("uniqueid", "4")
+            .section_end()
+            .section_start("new")
+            .kv_str("name", "net")
+            .kv_str("uniqueid", "5")
+            .section_end()
+            .section_end()
+            .section_end()
+            .section_end();
+        match Event::parse("child-rekey", &msg).unwrap() {
+            Event::ChildRekey { ike, old, new } => {
+                assert_eq!(ike.uniqueid, 1);
+                assert!(ike.child_sas.is_empty());
+                assert_eq!((old.uniqueid, new.uniqueid), (4, 5));
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[test]
+    fn ike_update() {
+        let msg = ike(
+            Message::new()
+                .kv_str("local-host", "192.0.2.9")
+                .kv_str("local-port", "4500")
+                .kv_str("remote-host", "198.51.100.1")
+                .kv_str("remote-port", "4500"),
+            "gw",
+            "1",
+        )
+        .section_end();
+        match Event::parse("ike-update", &msg).unwrap() {
+            Event::IkeUpdate {
+                local_host, ike, ..
+            } => {
+                assert_eq!(local_host, "192.0.2.9");
+                assert_eq!(ike.uniqueid, 1);
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[test]
+    fn unknown_events_pass_through() {
+        let msg = Message::new().kv_str("x", "y");
+        let ev = Event::parse("some-future-event", &msg).unwrap();
+        assert_eq!(ev.name(), "some-future-event");
+        assert!(matches!(ev, Event::Unknown { message, .. } if message == msg));
+    }
+}
@@ -0,0 +1,418 @@
+//! Prometheus metrics for charon.
+//!
+//! [`collect`] gathers `stats`, `get-counters`, `list-sas` and `get-pools`
+//! through a [`Client`] and renders them in the Prometheus text format.
+//! [`Exporter`] does so periodically and serves the latest result over HTTP
+//! at `/metrics`.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use rustici::exporter::Exporter;
+//! use rustici::Client;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let exporter = Exporter::bind("127.0.0.1:9814", Client::builder(), Duration::from_secs(15))?;
+//! exporter.run()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use crate::builder::ClientBuilder;
+use crate::client::Client;
+use crate::error::Result;
+use crate::wire::{Message, Section};
+
+/// Prefix of all metric names.
+const PREFIX: &str = "strongswan";
+
+/// Job priorities reported by `stats` for active workers and queues.
+const PRIORITIES: [&str; 4] = ["critical", "high", "medium", "low"];
+
+/// Prometheus text format builder.
+#[derive(Default)]
+struct Text {
+    out: String,
+}
+
+impl Text {
+    fn family(&mut self, name: &str, ty: &str, help: &str) {
+        let _ = writeln!(self.out, "# HELP {PREFIX}_{name} {help}");
+        let _ = writeln!(self.out, "# TYPE {PREFIX}_{name} {ty}");
+    }
+
+    fn sample<V: std::fmt::Display>(&mut self, name: &str, labels: &[(&str, &str)], value: V) {
+        let _ = write!(self.out, "{PREFIX}_{name}");
+        if !labels.is_empty() {
+            self.out.push('{');
+            for (i, (k, v)) in labels.iter().enumerate() {
+                if i > 0 {
+                    self.out.push(',');
+                }
+                let _ = write!(self.out, "{k}=\"{}\"", escape(v));
+            }
+            self.out.push('}');
+        }
+        let _ = writeln!(self.out, " {value}");
+    }
+}
+
+fn escape(v: &str) -> String {
+    v.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Collect all metrics through `client` in the Prometheus text format.
+pub fn collect(client: &mut Client) -> Result<String> {
+    let mut text = Text::default();
+    let stats = client.call("stats", &Message::new())?.tree()?;
+    write_stats(&mut text, &stats);
+
+    let counters = client.get_counters(None)?;
+    text.family(
+        "ike_messages_total",
+        "counter",
+        "IKE events and messages by type, from get-counters.",
+    );
+    for (counter, value) in counters.iter() {
+        text.sample(
+            "ike_messages_total",
+            &[("counter", counter.as_str())],
+            value,
+        );
+    }
+
+    let sas = client.list_sas(None)?;
+    let mut by_state = BTreeMap::new();
+    for sa in &sas {
+        *by_state.entry(sa.state.as_str()).or_insert(0u32) += 1;
+    }
+    text.family("ike_sas", "gauge", "IKE_SAs by state, from list-sas.");
+    for (state, n) in &by_state {
+        text.sample("ike_sas", &[("state", state)], n);
+    }
+    let children = sas
+        .iter()
+        .flat_map(|sa| sa.child_sas.iter().map(move |child| (sa, child)));
+    text.family(
+        "child_sa_bytes_total",
+        "counter",
+        "Bytes processed by CHILD_SA.",
+    );
+    for (sa, child) in children.clone() {
+        let id = child.uniqueid.to_string();
+        for (dir, v) in [("in", child.bytes_in), ("out", child.bytes_out)] {
+            let labels = [
+                ("ike", sa.name.as_str()),
+                ("child", &child.name),
+                ("child_id", &id),
+                ("direction", dir),
+            ];
+            text.sample("child_sa_bytes_total", &labels, v);
+        }
+    }
+    text.family(
+        "child_sa_packets_total",
+        "counter",
+        "Packets processed by CHILD_SA.",
+    );
+    for (sa, child) in children {
+        let id = child.uniqueid.to_string();
+        for (dir, v) in [("in", child.packets_in), ("out", child.packets_out)] {
+            let labels = [
+                ("ike", sa.name.as_str()),
+                ("child", &child.name),
+                ("child_id", &id),
+                ("direction", dir),
+            ];
+            text.sample("child_sa_packets_total", &labels, v);
+        }
+    }
+
+    let pools = client.get_pools(None, false)?;
+    text.family("pool_size", "gauge", "Addresses in a virtual IP pool.");
+    for pool in &pools {
+        text.sample("pool_size", &[("pool", &pool.name)], pool.size);
+    }
+    text.family(
+        "pool_leases",
+        "gauge",
+        "Leases of a virtual IP pool by status.",
+    );
+    for pool in &pools {
+        text.sample(
+            "pool_leases",
+            &[("pool", &pool.name), ("status", "online")],
+            pool.online,
+        );
+        text.sample(
+            "pool_leases",
+            &[("pool", &pool.name), ("status", "offline")],
+            pool.offline,
+        );
+    }
+    text.family(
+        "pool_utilization",
+        "gauge",
+        "Fraction of a pool's addresses leased online.",
+    );
+    for pool in &pools {
+        text.sample(
+            "pool_utilization",
+            &[("pool", &pool.name)],
+            pool.utilization(),
+        );
+    }
+    Ok(text.out)
+}
+
+fn write_stats(text: &mut Text, stats: &Section) {
+    let value = |sec: Option<&Section>, key: &str| sec.and_then(|s| s.parse::<u64>(key));
+    let workers = stats.section("workers");
+    let ikesas = stats.section("ikesas");
+
+    for (name, help, v) in [
+        ("workers", "Worker threads.", value(workers, "total")),
+        (
+            "workers_idle",
+            "Idle worker threads.",
+            value(workers, "idle"),
+        ),
+        (
+            "scheduled_jobs",
+            "Jobs scheduled for later.",
+            value(Some(stats), "scheduled"),
+        ),
+        (
+            "ike_sas_total",
+            "IKE_SAs, from stats.",
+            value(ikesas, "total"),
+        ),
+        (
+            "ike_sas_half_open",
+            "Half-open IKE_SAs, from stats.",
+            value(ikesas, "half-open"),
+        ),
+    ] {
+        if let Some(v) = v {
+            text.family(name, "gauge", help);
+            text.sample(name, &[], v);
+        }
+    }
+    for (name, help, sec) in [
+        (
+            "workers_active",
+            "Busy worker threads by job priority.",
+            workers.and_then(|w| w.section("active")),
+        ),
+        (
+            "queued_jobs",
+            "Queued jobs by priority.",
+            stats.section("queues"),
+        ),
+    ] {
+        if sec.is_some() {
+            text.family(name, "gauge", help);
+            for prio in PRIORITIES {
+                if let Some(v) = value(sec, prio) {
+                    text.sample(name, &[("priority", prio)], v);
+                }
+            }
+        }
+    }
+}
+
+struct Shared {
+    listener: TcpListener,
+    addr: SocketAddr,
+    builder: ClientBuilder,
+    interval: Duration,
+    latest: Mutex<String>,
+    stop: AtomicBool,
+    wake: Condvar,
+}
+
+/// Collects metrics periodically and serves them over HTTP. Clones share the
+/// same listener and state.
+#[derive(Clone)]
+pub struct Exporter {
+    shared: Arc<Shared>,
+}
+
+impl Exporter {
+    /// Listen on `addr` (e.g. `127.0.0.1:9814`), collecting every `interval`
+    /// through clients built by `builder`.
+    pub fn bind(addr: &str, builder: ClientBuilder, interval: Duration) -> Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        Ok(Self {
+            shared: Arc::new(Shared {
+                addr: listener.local_addr()?,
+                listener,
+                builder,
+                interval,
+                latest: Mutex::new(up_metric(false).out),
+                stop: AtomicBool::new(false),
+                wake: Condvar::new(),
+            }),
+        })
+    }
+
+    /// The address the HTTP listener is bound to.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.shared.addr
+    }
+
+    /// The metrics of the most recent collection.
+    ///
+    /// `strongswan_up` is 1 if it succeeded and 0 (with no other metrics) if
+    /// charon could not be queried or nothing was collected yet.
+    pub fn metrics(&self) -> String {
+        lock(&self.shared.latest).clone()
+    }
+
+    /// Collect periodically in the background and serve `/metrics` until
+    /// [`shutdown`](Self::shutdown) is called.
+    pub fn run(&self) -> Result<()> {
+        let shared = self.shared.clone();
+        let collector = thread::spawn(move || collect_loop(&shared));
+        let res = self.serve();
+        self.shutdown();
+        let _ = collector.join();
+        res
+    }
+
+    fn serve(&self) -> Result<()> {
+        while !self.shared.stop.load(Ordering::SeqCst) {
+            let stream = match self.shared.listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            if self.shared.stop.load(Ordering::SeqCst) {
+                break;
+            }
+            // Responses are small and cached, but a client may be slow to
+            // send its request; don't let it hold up other scrapes.
+            let exporter = self.clone();
+            thread::spawn(move || exporter.respond(stream));
+        }
+        Ok(())
+    }
+
+    fn respond(&self, mut stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        stream.set_write_timeout(Some(Duration::from_secs(5)))?;
+        let mut req = Vec::new();
+        let mut chunk = [0u8; 1024];
+        while !req.windows(4).any(|w| w == b"\r\n\r\n") && req.len() < 8192 {
+            match stream.read(&mut chunk)? {
+                0 => break,
+                n => req.extend_from_slice(&chunk[..n]),
+            }
+        }
+        let line = req.split(|&b| b == b'\r').next().unwrap_or_default();
+        let mut parts = line.split(|&b| b == b' ');
+        let (status, body) = match (parts.next(), parts.next()) {
+            (Some(b"GET"), Some(b"/metrics")) => ("200 OK", self.metrics()),
+            (Some(b"GET"), Some(b"/")) => ("200 OK", "strongSwan exporter: /metrics\n".into()),
+            (Some(b"GET"), _) => ("404 Not Found", "not found\n".into()),
+            _ => ("405 Method Not Allowed", "method not allowed\n".into()),
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {status}\r\n\
+             Content-Type: text/plain; version=0.0.4; charset=utf-8\r\n\
+             Content-Length: {}\r\n\
+             Connection: close\r\n\r\n{body}",
+            body.len()
+        )?;
+        stream.flush()
+    }
+
+    /// Stop [`run`](Self::run) and the background collection.
+    pub fn shutdown(&self) {
+        {
+            // Under the lock, so the collector cannot miss the wakeup
+            // between checking `stop` and waiting.
+            let _latest = lock(&self.shared.latest);
+            self.shared.stop.store(true, Ordering::SeqCst);
+            self.shared.wake.notify_all();
+        }
+        // Wake the accept() call.
+        let _ = TcpStream::connect(self.shared.addr);
+    }
+}
+
+fn lock<T>(m: &Mutex<T>) -> MutexGuard<'_, T> {
+    m.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn up_metric(up: bool) -> Text {
+    let mut text = Text::default();
+    text.family("up", "gauge", "Whether charon could be queried.");
+    text.sample("up", &[], u8::from(up));
+    text
+}
+
+fn collect_loop(shared: &Shared) {
+    let mut client = None;
+    while !shared.stop.load(Ordering::SeqCst) {
+        let res = match &mut client {
+            Some(c) => collect(c),
+            None => shared.builder.build().and_then(|mut c| {
+                let res = collect(&mut c);
+                client = Some(c);
+                res
+            }),
+        };
+        let metrics = match res {
+            Ok(metrics) => up_metric(true).out + &metrics,
+            Err(_) => {
+                // Start over with a fresh connection next time.
+                client = None;
+                up_metric(false).out
+            }
+        };
+        let mut latest = lock(&shared.latest);
+        *latest = metrics;
+        if !shared.stop.load(Ordering::SeqCst) {
+            let _ = shared.wake.wait_timeout(latest, shared.interval);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_rendering() {
+        let stats: Message = "uptime { running = 1h since = now }
+            workers { total = 16 idle = 10 active { critical = 4 high = 0 medium = 1 low = 1 } }
+            queues { critical = 0 high = 0 medium = 0 low = 2 }
+            scheduled = 3
+            ikesas { total = 5 half-open = 1 }"
+            .parse()
+            .unwrap();
+        let mut text = Text::default();
+        write_stats(&mut text, &stats.tree().unwrap());
+        let out = text.out;
+        assert!(out.contains("# TYPE strongswan_workers gauge\nstrongswan_workers 16\n"));
+        assert!(out.contains("strongswan_workers_active{priority=\"critical\"} 4\n"));
+        assert!(out.contains("strongswan_queued_jobs{priority=\"low\"} 2\n"));
+        assert!(out.contains("strongswan_ike_sas_half_open 1\n"));
+
+        let mut text = Text::default();
+        text.sample("x", &[("a", "q\"\\\n")], 1.5);
+        assert_eq!(text.out, "strongswan_x{a=\"q\\\"\\\\\\n\"} 1.5\n");
+    }
+}
@@ -0,0 +1,261 @@
+//! Logging events as JSON lines.
+//!
+//! [`EventLogger`] registers for a set of events on a
+//! [`ReconnectingClient`] and writes each one through a [`JsonLinesWriter`]
+//! as a single line:
+//!
+//! ```text
+//! {"time":"2026-10-18T09:14:03.512Z","event":"ike-updown","message":{"up":"yes","gw":{...}}}
+//! ```
+//!
+//! The message is rendered by [`Message::to_json`], which merges repeated
+//! keys into arrays so every line is valid JSON. When charon restarts, the
+//! logger reconnects, re-registers and writes a
+//! `{"time":...,"event":"reconnected","attempts":N}` line marking the gap.
+//!
+//! ```no_run
+//! use rustici::jsonlog::{EventLogger, JsonLinesWriter, DEFAULT_EVENTS};
+//! use rustici::Client;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = Client::builder().build_reconnecting()?;
+//! let writer = JsonLinesWriter::file("/var/log/charon-events.jsonl")?.rotate(64 << 20, 5);
+//! EventLogger::new(client, &DEFAULT_EVENTS, writer)?.run()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::Result;
+use crate::reconnect::{Notification, ReconnectingClient};
+use crate::wire::{json_string, Message};
+
+/// SA lifecycle events logged when no other set is configured.
+pub const DEFAULT_EVENTS: [&str; 6] = [
+    "ike-updown",
+    "child-updown",
+    "ike-rekey",
+    "child-rekey",
+    "ike-update",
+    "ike-reestablish",
+];
+
+/// Format `at` as an RFC 3339 UTC timestamp with milliseconds.
+pub fn timestamp(at: SystemTime) -> String {
+    let since = at.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since.as_secs();
+    let (days, rem) = ((secs / 86_400) as i64, secs % 86_400);
+    // Civil date from days since the epoch (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        rem / 3600,
+        rem / 60 % 60,
+        rem % 60,
+        since.subsec_millis()
+    )
+}
+
+/// The JSON line (without newline) for event `name` received `at`.
+pub fn event_line(at: SystemTime, name: &str, msg: &Message) -> String {
+    let mut line = String::from("{\"time\":\"");
+    line.push_str(&timestamp(at));
+    line.push_str("\",\"event\":");
+    json_string(&mut line, name);
+    line.push_str(",\"message\":");
+    line.push_str(&msg.to_json());
+    line.push('}');
+    line
+}
+
+enum Target {
+    Stream(Box<dyn Write + Send>),
+    File { path: PathBuf, file: File },
+}
+
+/// Writes JSON lines to a stream or to a file with size-based rotation.
+pub struct JsonLinesWriter {
+    target: Target,
+    rotate: Option<(u64, usize)>,
+    written: u64,
+}
+
+impl JsonLinesWriter {
+    /// Write to `out`.
+    pub fn new<W: Write + Send + 'static>(out: W) -> Self {
+        Self {
+            target: Target::Stream(Box::new(out)),
+            rotate: None,
+            written: 0,
+        }
+    }
+
+    /// Write to stdout.
+    pub fn stdout() -> Self {
+        Self::new(io::stdout())
+    }
+
+    /// Append to the file at `path`, creating it if needed.
+    pub fn file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = open(&path)?;
+        Ok(Self {
+            written: file.metadata()?.len(),
+            target: Target::File { path, file },
+            rotate: None,
+        })
+    }
+
+    /// Once the file exceeds `max_bytes`, rename it to `<path>.1` (shifting
+    /// older ones up to `<path>.<keep>`, dropping the oldest) and start a new
+    /// one. Ignored when not writing to a file.
+    pub fn rotate(mut self, max_bytes: u64, keep: usize) -> Self {
+        self.rotate = Some((max_bytes, keep));
+        self
+    }
+
+    /// Write `line` followed by a newline, rotating first if due.
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        if let (Target::File { path, file }, Some((max, keep))) = (&mut self.target, self.rotate) {
+            if self.written > 0 && self.written + line.len() as u64 >= max {
+                file.flush()?;
+                for i in (1..keep).rev() {
+                    let _ = fs::rename(numbered(path, i), numbered(path, i + 1));
+                }
+                if keep > 0 {
+                    fs::rename(&*path, numbered(path, 1))?;
+                } else {
+                    fs::remove_file(&*path)?;
+                }
+                *file = open(path)?;
+                self.written = 0;
+            }
+        }
+        let mut data = Vec::with_capacity(line.len() + 1);
+        data.extend_from_slice(line.as_bytes());
+        data.push(b'\n');
+        // One write per line keeps lines whole for concurrent readers.
+        match &mut self.target {
+            Target::Stream(out) => {
+                out.write_all(&data)?;
+                out.flush()?;
+            }
+            Target::File { file, .. } => file.write_all(&data)?,
+        }
+        self.written += data.len() as u64;
+        Ok(())
+    }
+
+    /// Write event `name` received `at`.
+    pub fn write_event(&mut self, at: SystemTime, name: &str, msg: &Message) -> io::Result<()> {
+        self.write_line(&event_line(at, name, msg))
+    }
+}
+
+impl std::fmt::Debug for JsonLinesWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = match &self.target {
+            Target::File { path, .. } => Some(path),
+            Target::Stream(_) => None,
+        };
+        f.debug_struct("JsonLinesWriter")
+            .field("path", &path)
+            .field("rotate", &self.rotate)
+            .field("written", &self.written)
+            .finish()
+    }
+}
+
+fn open(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn numbered(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{n}"));
+    PathBuf::from(name)
+}
+
+/// Writes every event received on a [`ReconnectingClient`] as a JSON line.
+pub struct EventLogger {
+    client: ReconnectingClient,
+    writer: JsonLinesWriter,
+}
+
+impl EventLogger {
+    /// Register for `events` on `client` and log them to `writer`.
+    pub fn new(
+        mut client: ReconnectingClient,
+        events: &[&str],
+        writer: JsonLinesWriter,
+    ) -> Result<Self> {
+        for name in events {
+            client.register_event(name)?;
+        }
+        Ok(Self { client, writer })
+    }
+
+    /// The client, e.g. to register further events.
+    pub fn client(&mut self) -> &mut ReconnectingClient {
+        &mut self.client
+    }
+
+    /// Wait for the next event (or reconnection) and log it.
+    pub fn log_next(&mut self) -> Result<()> {
+        let notification = self.client.next_event()?;
+        let now = SystemTime::now();
+        match notification {
+            Notification::Event(name, msg) => self.writer.write_event(now, &name, &msg)?,
+            Notification::Reconnected { attempts } => {
+                let line = format!(
+                    "{{\"time\":\"{}\",\"event\":\"reconnected\",\"attempts\":{attempts}}}",
+                    timestamp(now)
+                );
+                self.writer.write_line(&line)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Log events until an error occurs, e.g. when reconnecting gives up.
+    pub fn run(&mut self) -> Result<()> {
+        loop {
+            self.log_next()?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn lines_and_timestamps() {
+        assert_eq!(timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        let at = UNIX_EPOCH + Duration::from_millis(1_709_210_096_789);
+        assert_eq!(timestamp(at), "2024-02-29T12:34:56.789Z");
+        let msg = Message::new().kv_str("up", "yes");
+        assert_eq!(
+            event_line(at, "ike-updown", &msg),
+            "{\"time\":\"2024-02-29T12:34:56.789Z\",\"event\":\"ike-updown\",\"message\":{\"up\":\"yes\"}}"
+        );
+
+        // Two IKE_SAs of the same connection in one event.
+        let msg: Message = "gw { uniqueid = 1 } gw { uniqueid = 2 }".parse().unwrap();
+        assert!(event_line(at, "ike-rekey", &msg)
+            .ends_with("\"message\":{\"gw\":[{\"uniqueid\":\"1\"},{\"uniqueid\":\"2\"}]}}"));
+    }
+}
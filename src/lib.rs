@@ -6,7 +6,9 @@
 //! ### Status
 //! This is an early, intentionally small implementation. It focuses on correctness
 //! of the wire codec and a straightforward blocking client. It does **not** depend
-//! on libstrongswan or davici. No external crates are used.
+//! on libstrongswan or davici. The core uses only `std`; the optional
+//! dependencies are feature-gated: `log` (feature `log`) and `rustix`
+//! (feature `proxy`).
 //!
 //! See the `examples/` folder for usage.
 //!
@@ -22,12 +24,38 @@
 #![forbid(unsafe_code)]
 #![deny(missing_docs)]
 
+pub mod builder;
+pub mod capture;
+pub mod certs;
 pub mod client;
+pub mod config;
+pub mod conn;
+pub mod control;
+pub mod counters;
+pub mod creds;
+pub mod daemon;
 pub mod error;
+pub mod event;
+pub mod exporter;
+pub mod jsonlog;
+pub mod logging;
 pub mod packet;
+pub mod policies;
+pub mod pools;
+#[cfg(feature = "proxy")]
+pub mod proxy;
+pub mod reconcile;
+pub mod reconnect;
+pub mod sa;
+pub mod server;
+pub mod supervisor;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod tracker;
 pub mod wire;
 
 // Re-export primary types
+pub use crate::builder::ClientBuilder;
 pub use crate::client::Client;
 pub use crate::packet::{Packet, PacketType};
-pub use crate::wire::Message;
+pub use crate::wire::{Message, Section};
@@ -0,0 +1,323 @@
+//! Typed view of charon's `log` and `control-log` events.
+//!
+//! Both events share the same shape: a debug group, a level, the message
+//! text and, when the line relates to an IKE_SA, its name and unique id.
+//! `log` events additionally carry the thread that produced them.
+//!
+//! [`LogFilter`] selects records by group and level on the client side, and
+//! [`Client::subscribe_logs`] turns a connection into a filtered stream of
+//! records. With the `log` feature enabled, [`LogForwarder`] hands records to
+//! the [`log`](https://docs.rs/log) crate.
+
+use std::time::Duration;
+
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::wire::Message;
+
+/// A single log line emitted by charon.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogRecord {
+    /// Debug group, e.g. `ike`, `cfg`, `knl`, `net`.
+    pub group: String,
+    /// Log level (`-1` silent ... `4` private).
+    pub level: i32,
+    /// Thread identifier (only present on `log` events).
+    pub thread: Option<u32>,
+    /// Name of the IKE_SA the line relates to, if any.
+    pub ikesa_name: Option<String>,
+    /// Unique id of the IKE_SA the line relates to, if any.
+    pub ikesa_uniqueid: Option<u32>,
+    /// The log message text.
+    pub msg: String,
+}
+
+impl LogRecord {
+    /// Parse a `log` or `control-log` event message.
+    pub fn from_message(msg: &Message) -> Result<Self> {
+        let tree = msg.tree()?;
+        Ok(Self {
+            group: tree
+                .str("group")
+                .ok_or(Error::Protocol("log event without group"))?
+                .to_string(),
+            level: tree
+                .parse("level")
+                .ok_or(Error::Protocol("log event without level"))?,
+            thread: tree.parse("thread"),
+            ikesa_name: tree.str("ikesa-name").map(str::to_string),
+            ikesa_uniqueid: tree.parse("ikesa-uniqueid"),
+            msg: tree.str("msg").unwrap_or_default().to_string(),
+        })
+    }
+}
+
+/// Receiver for log records streamed while a command runs.
+///
+/// Implemented for any `FnMut(&LogRecord)`, so a closure is usually enough.
+pub trait LogSink {
+    /// Handle one log record.
+    fn log(&mut self, record: &LogRecord);
+}
+
+impl<F: FnMut(&LogRecord)> LogSink for F {
+    fn log(&mut self, record: &LogRecord) {
+        self(record)
+    }
+}
+
+/// Client-side filter on log group and level.
+///
+/// An empty filter passes everything. Groups are matched case-insensitively;
+/// the level is an upper bound, so `max_level(1)` keeps levels `-1` to `1`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogFilter {
+    groups: Vec<String>,
+    max_level: Option<i32>,
+}
+
+impl LogFilter {
+    /// A filter passing all records.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only pass records of `group` (may be given multiple times).
+    pub fn group(mut self, group: &str) -> Self {
+        self.groups.push(group.to_ascii_lowercase());
+        self
+    }
+
+    /// Only pass records up to `level`.
+    pub fn max_level(mut self, level: i32) -> Self {
+        self.max_level = Some(level);
+        self
+    }
+
+    /// Whether `record` passes the filter.
+    pub fn matches(&self, record: &LogRecord) -> bool {
+        if let Some(max) = self.max_level {
+            if record.level > max {
+                return false;
+            }
+        }
+        self.groups.is_empty()
+            || self
+                .groups
+                .iter()
+                .any(|g| g.eq_ignore_ascii_case(&record.group))
+    }
+
+    /// Wrap `sink` so it only sees records passing this filter.
+    pub fn sink<S: LogSink>(self, sink: S) -> Filtered<S> {
+        Filtered { filter: self, sink }
+    }
+}
+
+/// A [`LogSink`] forwarding only records that pass a [`LogFilter`].
+#[derive(Debug)]
+pub struct Filtered<S> {
+    filter: LogFilter,
+    sink: S,
+}
+
+impl<S: LogSink> LogSink for Filtered<S> {
+    fn log(&mut self, record: &LogRecord) {
+        if self.filter.matches(record) {
+            self.sink.log(record);
+        }
+    }
+}
+
+/// A filtered stream of `log` records, see [`Client::subscribe_logs`].
+///
+/// Other events arriving on the connection are discarded. The `log` event
+/// is unregistered again when the subscriber is dropped, unless the client
+/// was registered for it before.
+///
+/// As an iterator, it ends after the first error other than a malformed
+/// record.
+pub struct LogSubscriber<'a> {
+    client: &'a mut Client,
+    filter: LogFilter,
+    owned: bool,
+    done: bool,
+}
+
+impl LogSubscriber<'_> {
+    /// Block until the next matching record arrives.
+    pub fn next_record(&mut self) -> Result<LogRecord> {
+        loop {
+            let (name, msg) = self.client.next_event()?;
+            if let Some(rec) = self.accept(&name, &msg)? {
+                return Ok(rec);
+            }
+        }
+    }
+
+    /// Wait at most `timeout` for each incoming event until a matching
+    /// record arrives; returns [`Error::Timeout`] once the wait runs out.
+    pub fn try_next_record(&mut self, timeout: Duration) -> Result<LogRecord> {
+        loop {
+            let (name, msg) = self.client.try_next_event(timeout)?;
+            if let Some(rec) = self.accept(&name, &msg)? {
+                return Ok(rec);
+            }
+        }
+    }
+
+    fn accept(&self, name: &str, msg: &Message) -> Result<Option<LogRecord>> {
+        if name != "log" {
+            return Ok(None);
+        }
+        let rec = LogRecord::from_message(msg)?;
+        Ok(Some(rec).filter(|r| self.filter.matches(r)))
+    }
+}
+
+impl Iterator for LogSubscriber<'_> {
+    type Item = Result<LogRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let res = self.next_record();
+        self.done = matches!(&res, Err(e) if !matches!(e, Error::Protocol(_)));
+        Some(res)
+    }
+}
+
+impl Drop for LogSubscriber<'_> {
+    fn drop(&mut self) {
+        if self.owned {
+            let _ = self.client.unregister_event("log");
+        }
+    }
+}
+
+impl Client {
+    /// Register for `log` events and return a stream of records matching
+    /// `filter`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rustici::Client;
+    /// use rustici::logging::LogFilter;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = Client::connect("/var/run/charon.vici")?;
+    /// let filter = LogFilter::new().group("ike").group("cfg").max_level(1);
+    /// for rec in client.subscribe_logs(filter)? {
+    ///     let rec = rec?;
+    ///     println!("[{}] {}", rec.group, rec.msg);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn subscribe_logs(&mut self, filter: LogFilter) -> Result<LogSubscriber<'_>> {
+        let owned = !self.is_registered("log");
+        if owned {
+            self.register_event("log")?;
+        }
+        Ok(LogSubscriber {
+            client: self,
+            filter,
+            owned,
+            done: false,
+        })
+    }
+}
+
+/// A [`LogSink`] handing records to the `log` crate.
+///
+/// Records are logged with target `charon::<group>`. charon levels map to
+/// `log` levels as: `<= 0` → `Info`, `1` → `Debug`, `>= 2` → `Trace`.
+#[cfg(feature = "log")]
+#[derive(Debug, Clone, Default)]
+pub struct LogForwarder;
+
+#[cfg(feature = "log")]
+impl LogForwarder {
+    /// Map a charon log level to a `log` level.
+    pub fn level(level: i32) -> log::Level {
+        match level {
+            i32::MIN..=0 => log::Level::Info,
+            1 => log::Level::Debug,
+            _ => log::Level::Trace,
+        }
+    }
+}
+
+#[cfg(feature = "log")]
+impl LogSink for LogForwarder {
+    fn log(&mut self, record: &LogRecord) {
+        let target = format!("charon::{}", record.group);
+        let level = Self::level(record.level);
+        match (&record.ikesa_name, record.ikesa_uniqueid) {
+            (Some(name), Some(id)) => {
+                log::log!(target: &target, level, "{}[{}] {}", name, id, record.msg)
+            }
+            _ => log::log!(target: &target, level, "{}", record.msg),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_control_log() {
+        let msg = Message::new()
+            .kv_str("group", "ike")
+            .kv_str("level", "1")
+            .kv_str("ikesa-name", "gw")
+            .kv_str("ikesa-uniqueid", "7")
+            .kv_str("msg", "establishing CHILD_SA net");
+        let rec = LogRecord::from_message(&msg).unwrap();
+        assert_eq!(rec.group, "ike");
+        assert_eq!(rec.level, 1);
+        assert_eq!(rec.thread, None);
+        assert_eq!(rec.ikesa_name.as_deref(), Some("gw"));
+        assert_eq!(rec.ikesa_uniqueid, Some(7));
+        assert_eq!(rec.msg, "establishing CHILD_SA net");
+    }
+
+    #[test]
+    fn missing_group_is_an_error() {
+        let msg = Message::new().kv_str("level", "1").kv_str("msg", "x");
+        assert!(LogRecord::from_message(&msg).is_err());
+    }
+
+    fn record(group: &str, level: i32) -> LogRecord {
+        LogRecord {
+            group: group.to_string(),
+            level,
+            thread: None,
+            ikesa_name: None,
+            ikesa_uniqueid: None,
+            msg: String::new(),
+        }
+    }
+
+    #[test]
+    fn filter_by_group_and_level() {
+        let all = LogFilter::new();
+        assert!(all.matches(&record("knl", 4)));
+
+        let f = LogFilter::new().group("IKE").group("cfg").max_level(1);
+        assert!(f.matches(&record("ike", 1)));
+        assert!(f.matches(&record("cfg", -1)));
+        assert!(!f.matches(&record("ike", 2)));
+        assert!(!f.matches(&record("knl", 0)));
+
+        let mut seen = Vec::new();
+        let mut sink = f.sink(|r: &LogRecord| seen.push(r.group.clone()));
+        sink.log(&record("net", 0));
+        sink.log(&record("cfg", 0));
+        drop(sink);
+        assert_eq!(seen, ["cfg"]);
+    }
+}
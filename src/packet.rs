@@ -1,8 +1,18 @@
 //! Packet layer definitions (outside the message codec).
+//!
+//! On the socket every packet travels in a transport frame: a 32-bit
+//! big-endian length followed by that many bytes of packet data.
+//! [`Packet::encode`] produces such a frame; [`FrameReader`] reassembles
+//! frames from arbitrarily split reads so a read interrupted by a timeout
+//! can be resumed without losing sync.
 
+use crate::error::{Error, Result};
 use crate::wire::Message;
 use std::fmt;
 
+/// Default upper bound for the packet data of a single frame (512 KiB).
+pub const MAX_FRAME_LEN: usize = 512 * 1024;
+
 /// Top-level packet types in the VICI protocol.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
@@ -26,6 +36,21 @@ pub enum PacketType {
 }
 
 impl PacketType {
+    /// Map the type byte of a packet to its type.
+    pub fn from_u8(ty: u8) -> Option<Self> {
+        Some(match ty {
+            0 => PacketType::CmdRequest,
+            1 => PacketType::CmdResponse,
+            2 => PacketType::CmdUnknown,
+            3 => PacketType::EventRegister,
+            4 => PacketType::EventUnregister,
+            5 => PacketType::EventConfirm,
+            6 => PacketType::EventUnknown,
+            7 => PacketType::Event,
+            _ => return None,
+        })
+    }
+
     /// Whether this packet carries a "name" field (single-byte length + ASCII bytes).
     pub fn is_named(self) -> bool {
         matches!(
@@ -60,4 +85,158 @@ impl Packet {
     pub fn new(ty: PacketType, name: Option<String>, message: Option<Message>) -> Self {
         Self { ty, name, message }
     }
+
+    /// Encode into a transport frame (length header included).
+    ///
+    /// The size limit is left to the caller; only frames too large for the
+    /// length header are rejected.
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let mut frame = vec![0u8; 4];
+        frame.push(self.ty as u8);
+        if self.ty.is_named() {
+            let name = self
+                .name
+                .as_ref()
+                .ok_or(Error::Protocol("named packet missing name"))?;
+            encode_name(&mut frame, name)?;
+        }
+        if let Some(msg) = &self.message {
+            frame.extend_from_slice(&msg.encode()?);
+        }
+        let len = u32::try_from(frame.len() - 4).map_err(|_| Error::TooLong("packet"))?;
+        frame[..4].copy_from_slice(&len.to_be_bytes());
+        Ok(frame)
+    }
+
+    /// Decode the packet data of one frame (without the length header).
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        let (ty, mut rest) = data
+            .split_first()
+            .ok_or(Error::Protocol("unexpected EOF reading u8"))?;
+        let ty = PacketType::from_u8(*ty).ok_or(Error::Protocol("unknown packet type"))?;
+        let name = if ty.is_named() {
+            let (nm, r) = decode_name(rest)?;
+            rest = r;
+            Some(nm)
+        } else {
+            None
+        };
+        let message = if !rest.is_empty() {
+            Some(Message::decode(rest)?)
+        } else {
+            None
+        };
+        Ok(Packet { ty, name, message })
+    }
+}
+
+/// Incremental reassembly of transport frames.
+///
+/// Feed received bytes with [`push`](Self::push) and take complete frames
+/// with [`next_frame`](Self::next_frame); partial frames stay buffered.
+#[derive(Debug, Clone)]
+pub struct FrameReader {
+    buf: Vec<u8>,
+    max_len: usize,
+}
+
+impl Default for FrameReader {
+    fn default() -> Self {
+        Self::new(MAX_FRAME_LEN)
+    }
+}
+
+impl FrameReader {
+    /// A reader rejecting frames with more than `max_len` bytes of data.
+    pub fn new(max_len: usize) -> Self {
+        Self {
+            buf: Vec::new(),
+            max_len,
+        }
+    }
+
+    /// Append received bytes.
+    pub fn push(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Take the packet data of the next complete frame, if any.
+    ///
+    /// Fails with [`Error::TooLong`] if the next frame exceeds the limit; the
+    /// stream cannot be resynchronized after that.
+    pub fn next_frame(&mut self) -> Result<Option<Vec<u8>>> {
+        if self.buf.len() < 4 {
+            return Ok(None);
+        }
+        let len = u32::from_be_bytes([self.buf[0], self.buf[1], self.buf[2], self.buf[3]]) as usize;
+        if len > self.max_len {
+            return Err(Error::TooLong("frame"));
+        }
+        if self.buf.len() < 4 + len {
+            return Ok(None);
+        }
+        let frame = self.buf[4..4 + len].to_vec();
+        self.buf.drain(..4 + len);
+        Ok(Some(frame))
+    }
+
+    /// Whether bytes are buffered that do not form a complete frame yet,
+    /// or a complete frame is waiting to be taken.
+    pub fn has_buffered(&self) -> bool {
+        !self.buf.is_empty()
+    }
+}
+
+fn decode_name(input: &[u8]) -> Result<(String, &[u8])> {
+    if input.is_empty() {
+        return Err(Error::Protocol("unexpected EOF reading name length"));
+    }
+    let len = input[0] as usize;
+    let input = &input[1..];
+    if input.len() < len {
+        return Err(Error::Protocol("unexpected EOF reading name bytes"));
+    }
+    let name = String::from_utf8(input[..len].to_vec())?;
+    Ok((name, &input[len..]))
+}
+
+fn encode_name(out: &mut Vec<u8>, name: &str) -> Result<()> {
+    let bytes = name.as_bytes();
+    if bytes.len() > u8::MAX as usize {
+        return Err(Error::TooLong("packet name"));
+    }
+    out.push(bytes.len() as u8);
+    out.extend_from_slice(bytes);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_roundtrip_split_reads() {
+        let pkt = Packet::new(
+            PacketType::Event,
+            Some("log".into()),
+            Some(Message::new().kv_str("msg", "hello")),
+        );
+        let frame = pkt.encode().unwrap();
+        let mut reader = FrameReader::default();
+        for byte in &frame[..frame.len() - 1] {
+            reader.push(std::slice::from_ref(byte));
+            assert_eq!(reader.next_frame().unwrap(), None);
+        }
+        reader.push(&frame[frame.len() - 1..]);
+        let data = reader.next_frame().unwrap().unwrap();
+        assert_eq!(Packet::decode(&data).unwrap(), pkt);
+        assert!(!reader.has_buffered());
+    }
+
+    #[test]
+    fn oversized_frame_is_rejected() {
+        let mut reader = FrameReader::new(8);
+        reader.push(&9u32.to_be_bytes());
+        assert!(matches!(reader.next_frame(), Err(Error::TooLong(_))));
+    }
 }
@@ -0,0 +1,228 @@
+//! Trap and shunt policy listing and management.
+//!
+//! `install`/`uninstall` add or remove the trap policy of a CHILD_SA
+//! configuration (or a shunt policy for `pass`/`drop` children);
+//! `list-policies` reports what is currently installed.
+
+use crate::client::{expect_success, Client};
+use crate::conn::ChildMode;
+use crate::error::{Error, Result};
+use crate::wire::{Message, Section};
+
+/// Filter for `list-policies`.
+///
+/// charon lists nothing unless at least one of `trap`, `drop` or `pass` is
+/// requested; [`PolicyFilter::all`] enables all three.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PolicyFilter {
+    drop: bool,
+    pass: bool,
+    trap: bool,
+    child: Option<String>,
+    ike: Option<String>,
+}
+
+impl PolicyFilter {
+    /// Create a filter that matches nothing yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// List trap, drop and pass policies.
+    pub fn all() -> Self {
+        Self::new().trap(true).drop(true).pass(true)
+    }
+
+    /// Include drop shunt policies.
+    pub fn drop(mut self, enabled: bool) -> Self {
+        self.drop = enabled;
+        self
+    }
+
+    /// Include pass shunt policies.
+    pub fn pass(mut self, enabled: bool) -> Self {
+        self.pass = enabled;
+        self
+    }
+
+    /// Include trap policies.
+    pub fn trap(mut self, enabled: bool) -> Self {
+        self.trap = enabled;
+        self
+    }
+
+    /// Only list policies of this CHILD_SA configuration.
+    pub fn child(mut self, name: impl Into<String>) -> Self {
+        self.child = Some(name.into());
+        self
+    }
+
+    /// Only list policies of this IKE_SA configuration.
+    pub fn ike(mut self, name: impl Into<String>) -> Self {
+        self.ike = Some(name.into());
+        self
+    }
+
+    /// Encode the `list-policies` request message.
+    pub fn to_message(&self) -> Message {
+        let mut msg = Message::new();
+        if self.drop {
+            msg = msg.kv_str("drop", "yes");
+        }
+        if self.pass {
+            msg = msg.kv_str("pass", "yes");
+        }
+        if self.trap {
+            msg = msg.kv_str("trap", "yes");
+        }
+        if let Some(child) = &self.child {
+            msg = msg.kv_str("child", child);
+        }
+        if let Some(ike) = &self.ike {
+            msg = msg.kv_str("ike", ike);
+        }
+        msg
+    }
+}
+
+/// An installed policy as reported by `list-policies`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyInfo {
+    /// Key charon lists the policy under, e.g. `ike/child` or `child`.
+    pub key: String,
+    /// Owning CHILD_SA configuration.
+    pub child: String,
+    /// Owning IKE_SA configuration, if any.
+    pub ike: Option<String>,
+    /// Policy mode; `Pass`/`Drop` for shunts, the IPsec mode for traps.
+    pub mode: ChildMode,
+    /// Local traffic selectors.
+    pub local_ts: Vec<String>,
+    /// Remote traffic selectors.
+    pub remote_ts: Vec<String>,
+}
+
+impl PolicyInfo {
+    /// Parse all policies contained in a `list-policy` event message.
+    pub fn from_event(msg: &Message) -> Result<Vec<Self>> {
+        msg.tree()?
+            .sections()
+            .map(|(key, sec)| Self::from_section(key, sec))
+            .collect()
+    }
+
+    fn from_section(key: &str, sec: &Section) -> Result<Self> {
+        Ok(Self {
+            key: key.to_string(),
+            child: sec
+                .str("child")
+                .ok_or(Error::Protocol("policy without child"))?
+                .to_string(),
+            ike: sec.str("ike").map(str::to_string),
+            mode: sec
+                .parse("mode")
+                .ok_or(Error::Protocol("policy without mode"))?,
+            local_ts: sec.str_list("local-ts"),
+            remote_ts: sec.str_list("remote-ts"),
+        })
+    }
+
+    /// Whether this is a pass or drop shunt rather than a trap policy.
+    pub fn is_shunt(&self) -> bool {
+        matches!(self.mode, ChildMode::Pass | ChildMode::Drop)
+    }
+}
+
+impl Client {
+    /// List installed policies matching `filter`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rustici::Client;
+    /// use rustici::policies::PolicyFilter;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = Client::connect("/var/run/charon.vici")?;
+    /// client.install("net", Some("gw"))?;
+    /// let traps = client.list_policies(&PolicyFilter::new().trap(true).child("net"))?;
+    /// assert!(!traps.is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn list_policies(&mut self, filter: &PolicyFilter) -> Result<Vec<PolicyInfo>> {
+        self.collect_streamed(
+            "list-policies",
+            &filter.to_message(),
+            "list-policy",
+            |msg, out| {
+                out.append(&mut PolicyInfo::from_event(msg)?);
+                Ok(())
+            },
+        )
+    }
+
+    /// Install the trap or shunt policy of CHILD_SA configuration `child`.
+    pub fn install(&mut self, child: &str, ike: Option<&str>) -> Result<()> {
+        let resp = self.call("install", &policy_request(child, ike))?;
+        expect_success(&resp).map(|_| ())
+    }
+
+    /// Uninstall the trap or shunt policy of CHILD_SA configuration `child`.
+    pub fn uninstall(&mut self, child: &str, ike: Option<&str>) -> Result<()> {
+        let resp = self.call("uninstall", &policy_request(child, ike))?;
+        expect_success(&resp).map(|_| ())
+    }
+}
+
+fn policy_request(child: &str, ike: Option<&str>) -> Message {
+    let mut req = Message::new().kv_str("child", child);
+    if let Some(ike) = ike {
+        req = req.kv_str("ike", ike);
+    }
+    req
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_encoding() {
+        let tree = PolicyFilter::all().ike("gw").to_message().tree().unwrap();
+        assert_eq!(tree.flag("trap"), Some(true));
+        assert_eq!(tree.flag("drop"), Some(true));
+        assert_eq!(tree.flag("pass"), Some(true));
+        assert_eq!(tree.str("ike"), Some("gw"));
+        assert!(PolicyFilter::new().to_message().is_empty());
+    }
+
+    #[test]
+    fn list_policy_parsing() {
+        let msg = Message::new()
+            .section_start("gw/net")
+            .kv_str("child", "net")
+            .kv_str("ike", "gw")
+            .kv_str("mode", "TUNNEL")
+            .list_start("local-ts")
+            .list_item_str("10.1.0.0/16")
+            .list_end()
+            .list_start("remote-ts")
+            .list_item_str("10.2.0.0/16")
+            .list_end()
+            .section_end()
+            .section_start("lan-bypass")
+            .kv_str("child", "lan-bypass")
+            .kv_str("mode", "PASS")
+            .section_end();
+        let policies = PolicyInfo::from_event(&msg).unwrap();
+        assert_eq!(policies.len(), 2);
+        assert_eq!(policies[0].key, "gw/net");
+        assert_eq!(policies[0].ike.as_deref(), Some("gw"));
+        assert_eq!(policies[0].mode, ChildMode::Tunnel);
+        assert!(!policies[0].is_shunt());
+        assert_eq!(policies[0].remote_ts, ["10.2.0.0/16"]);
+        assert!(policies[1].is_shunt());
+        assert_eq!(policies[1].ike, None);
+    }
+}
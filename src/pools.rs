@@ -0,0 +1,252 @@
+//! Virtual IP pool management with lease inspection.
+
+use std::str::FromStr;
+
+use crate::client::{expect_success, Client};
+use crate::error::{Error, Result};
+use crate::wire::{Message, Section, Value};
+
+/// A virtual IP pool definition for `load-pool`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolConfig {
+    name: String,
+    addrs: String,
+    attributes: Vec<(String, Vec<String>)>,
+}
+
+impl PoolConfig {
+    /// Create a pool named `name` handing out `addrs` (a subnet or range).
+    pub fn new(name: impl Into<String>, addrs: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            addrs: addrs.into(),
+            attributes: Vec::new(),
+        }
+    }
+
+    /// Name of this pool.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Address subnet or range of this pool.
+    pub fn addrs(&self) -> &str {
+        &self.addrs
+    }
+
+    /// Add a value to a configuration attribute, e.g. `dns`, `nbns`,
+    /// `split_include` or a numeric attribute type.
+    pub fn attribute(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        let name = name.into();
+        let value = value.into();
+        match self.attributes.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, values)) => values.push(value),
+            None => self.attributes.push((name, vec![value])),
+        }
+        self
+    }
+
+    /// Add a DNS server handed out with leases from this pool.
+    pub fn dns(self, addr: impl Into<String>) -> Self {
+        self.attribute("dns", addr)
+    }
+
+    /// Encode the `load-pool` request message.
+    pub fn to_message(&self) -> Message {
+        let mut sec = Section::new();
+        sec.insert_str("addrs", &self.addrs);
+        for (name, values) in &self.attributes {
+            sec.insert_str_list(name.clone(), values);
+        }
+        let mut root = Section::new();
+        root.insert(self.name.clone(), Value::Section(sec));
+        root.to_message()
+    }
+}
+
+/// Whether a lease is currently in use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeaseStatus {
+    /// Assigned to a connected peer.
+    Online,
+    /// Reserved for a peer that is currently offline.
+    Offline,
+}
+
+impl FromStr for LeaseStatus {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "online" => Ok(LeaseStatus::Online),
+            "offline" => Ok(LeaseStatus::Offline),
+            _ => Err(Error::Protocol("unknown lease status")),
+        }
+    }
+}
+
+/// A single lease of a pool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lease {
+    /// Leased address.
+    pub address: String,
+    /// Identity of the peer holding the lease.
+    pub identity: String,
+    /// Lease status.
+    pub status: LeaseStatus,
+}
+
+/// A pool as reported by `get-pools`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolInfo {
+    /// Pool name.
+    pub name: String,
+    /// Base address of the pool.
+    pub base: String,
+    /// Number of addresses in the pool.
+    pub size: u32,
+    /// Number of leases in use.
+    pub online: u32,
+    /// Number of leases reserved for offline peers.
+    pub offline: u32,
+    /// Individual leases (only when requested).
+    pub leases: Vec<Lease>,
+}
+
+impl PoolInfo {
+    /// Parse a `get-pools` response.
+    pub fn from_response(msg: &Message) -> Result<Vec<Self>> {
+        msg.tree()?
+            .sections()
+            .map(|(name, sec)| Self::from_section(name, sec))
+            .collect()
+    }
+
+    fn from_section(name: &str, sec: &Section) -> Result<Self> {
+        let mut leases = Vec::new();
+        if let Some(section) = sec.section("leases") {
+            for (_, lease) in section.sections() {
+                leases.push(Lease {
+                    address: lease.str("address").unwrap_or_default().to_string(),
+                    identity: lease.str("identity").unwrap_or_default().to_string(),
+                    status: lease
+                        .parse("status")
+                        .ok_or(Error::Protocol("lease without status"))?,
+                });
+            }
+        }
+        Ok(Self {
+            name: name.to_string(),
+            base: sec
+                .str("base")
+                .ok_or(Error::Protocol("pool without base"))?
+                .to_string(),
+            size: sec.parse("size").unwrap_or(0),
+            online: sec.parse("online").unwrap_or(0),
+            offline: sec.parse("offline").unwrap_or(0),
+            leases,
+        })
+    }
+
+    /// Fraction of the pool currently online, between 0.0 and 1.0.
+    pub fn utilization(&self) -> f64 {
+        if self.size == 0 {
+            0.0
+        } else {
+            f64::from(self.online) / f64::from(self.size)
+        }
+    }
+}
+
+impl Client {
+    /// Load (or replace) a virtual IP pool.
+    pub fn load_pool(&mut self, pool: &PoolConfig) -> Result<()> {
+        let resp = self.call("load-pool", &pool.to_message())?;
+        expect_success(&resp).map(|_| ())
+    }
+
+    /// Unload the pool named `name`.
+    pub fn unload_pool(&mut self, name: &str) -> Result<()> {
+        let resp = self.call("unload-pool", &Message::new().kv_str("name", name))?;
+        expect_success(&resp).map(|_| ())
+    }
+
+    /// List loaded pools, optionally only `name`, including leases if `leases` is set.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rustici::Client;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = Client::connect("/var/run/charon.vici")?;
+    /// for pool in client.get_pools(None, true)? {
+    ///     println!("{} {}/{} online", pool.name, pool.online, pool.size);
+    ///     for lease in &pool.leases {
+    ///         println!("  {} {} {:?}", lease.address, lease.identity, lease.status);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_pools(&mut self, name: Option<&str>, leases: bool) -> Result<Vec<PoolInfo>> {
+        let mut req = Message::new();
+        if leases {
+            req = req.kv_str("leases", "yes");
+        }
+        if let Some(name) = name {
+            req = req.kv_str("name", name);
+        }
+        let resp = self.call("get-pools", &req)?;
+        PoolInfo::from_response(&resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pool_encoding_groups_attributes() {
+        let pool = PoolConfig::new("rw", "10.3.0.0/24")
+            .dns("10.3.0.1")
+            .attribute("split_include", "10.1.0.0/16")
+            .dns("10.3.0.2");
+        let tree = pool.to_message().tree().unwrap();
+        let rw = tree.section("rw").unwrap();
+        assert_eq!(rw.str("addrs"), Some("10.3.0.0/24"));
+        assert_eq!(rw.str_list("dns"), ["10.3.0.1", "10.3.0.2"]);
+        assert_eq!(rw.str_list("split_include"), ["10.1.0.0/16"]);
+    }
+
+    #[test]
+    fn get_pools_parsing() {
+        let msg = Message::new()
+            .section_start("rw")
+            .kv_str("base", "10.3.0.0")
+            .kv_str("size", "254")
+            .kv_str("online", "1")
+            .kv_str("offline", "1")
+            .section_start("leases")
+            .section_start("0")
+            .kv_str("address", "10.3.0.1")
+            .kv_str("identity", "carol@example.org")
+            .kv_str("status", "online")
+            .section_end()
+            .section_start("1")
+            .kv_str("address", "10.3.0.2")
+            .kv_str("identity", "dave@example.org")
+            .kv_str("status", "offline")
+            .section_end()
+            .section_end()
+            .section_end();
+        let pools = PoolInfo::from_response(&msg).unwrap();
+        assert_eq!(pools.len(), 1);
+        let rw = &pools[0];
+        assert_eq!(rw.base, "10.3.0.0");
+        assert_eq!((rw.size, rw.online, rw.offline), (254, 1, 1));
+        assert_eq!(rw.leases[0].identity, "carol@example.org");
+        assert_eq!(rw.leases[1].status, LeaseStatus::Offline);
+        assert!(rw.utilization() > 0.0);
+    }
+}
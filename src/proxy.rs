@@ -0,0 +1,470 @@
+//! Access-controlled VICI proxy (feature `proxy`).
+//!
+//! [`Proxy`] listens on its own UNIX socket in front of charon's, identifies
+//! each peer by its `SO_PEERCRED` uid/gid and checks every command and event
+//! registration against a [`Policy`]. Allowed packets are forwarded to charon
+//! unchanged; denied commands are answered with `success = no` and denied
+//! registrations with `EventUnknown`, without charon ever seeing them.
+//!
+//! ```no_run
+//! use std::os::unix::fs::PermissionsExt;
+//! use rustici::proxy::{Policy, Proxy, Rule};
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let policy = Policy::deny_all()
+//!     .rule(Rule::uid(0).allow_command("*").allow_event("*"))
+//!     .rule(Rule::gid(977).read_only());
+//! let proxy = Proxy::bind("/run/vici-proxy.sock", "/var/run/charon.vici", policy)?;
+//! std::fs::set_permissions(proxy.path(), std::fs::Permissions::from_mode(0o666))?;
+//! proxy.run()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::net::Shutdown;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+
+use crate::error::{Error, Result};
+use crate::packet::{FrameReader, Packet, PacketType, MAX_FRAME_LEN};
+use crate::server::SocketFile;
+use crate::wire::Message;
+
+/// Error message of denied commands.
+pub const DENIED: &str = "permission denied by VICI proxy policy";
+
+/// Outcome of a policy check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// Forward to charon.
+    Allow,
+    /// Answer locally with a failure.
+    Deny,
+}
+
+/// Credentials of a connected peer, as reported by `SO_PEERCRED`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Peer {
+    /// Effective user ID.
+    pub uid: u32,
+    /// Effective (primary) group ID.
+    pub gid: u32,
+    /// Process ID at connect time.
+    pub pid: i32,
+}
+
+impl Peer {
+    /// Credentials of the process at the other end of `stream`.
+    pub fn of(stream: &UnixStream) -> Result<Self> {
+        let cred = rustix::net::sockopt::get_socket_peercred(stream).map_err(io::Error::from)?;
+        Ok(Self {
+            uid: cred.uid.as_raw(),
+            gid: cred.gid.as_raw(),
+            pid: cred.pid.as_raw_nonzero().get(),
+        })
+    }
+}
+
+/// Whom a [`Rule`] applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Principal {
+    Any,
+    Uid(u32),
+    Gid(u32),
+}
+
+/// Allowed and denied commands and events for one uid, gid or everyone.
+///
+/// Patterns are exact names or prefixes ending in `*` (`list-*`, `*`); the
+/// first matching pattern decides.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    principal: Principal,
+    commands: Vec<(Access, String)>,
+    events: Vec<(Access, String)>,
+}
+
+impl Rule {
+    fn new(principal: Principal) -> Self {
+        Self {
+            principal,
+            commands: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// A rule for every peer.
+    pub fn any() -> Self {
+        Self::new(Principal::Any)
+    }
+
+    /// A rule for peers with user ID `uid`.
+    pub fn uid(uid: u32) -> Self {
+        Self::new(Principal::Uid(uid))
+    }
+
+    /// A rule for peers with primary group ID `gid`.
+    ///
+    /// `SO_PEERCRED` carries no supplementary groups.
+    pub fn gid(gid: u32) -> Self {
+        Self::new(Principal::Gid(gid))
+    }
+
+    /// Allow commands matching `pattern`.
+    pub fn allow_command(mut self, pattern: &str) -> Self {
+        self.commands.push((Access::Allow, pattern.to_string()));
+        self
+    }
+
+    /// Deny commands matching `pattern`.
+    pub fn deny_command(mut self, pattern: &str) -> Self {
+        self.commands.push((Access::Deny, pattern.to_string()));
+        self
+    }
+
+    /// Allow registering for events matching `pattern`.
+    pub fn allow_event(mut self, pattern: &str) -> Self {
+        self.events.push((Access::Allow, pattern.to_string()));
+        self
+    }
+
+    /// Deny registering for events matching `pattern`.
+    pub fn deny_event(mut self, pattern: &str) -> Self {
+        self.events.push((Access::Deny, pattern.to_string()));
+        self
+    }
+
+    /// Allow the commands that only read state (`version`, `stats`,
+    /// `list-*`, `get-*`) and the events they stream (`list-*`).
+    pub fn read_only(self) -> Self {
+        self.allow_command("version")
+            .allow_command("stats")
+            .allow_command("list-*")
+            .allow_command("get-*")
+            .allow_event("list-*")
+    }
+
+    fn applies_to(&self, peer: &Peer) -> bool {
+        match self.principal {
+            Principal::Any => true,
+            Principal::Uid(uid) => peer.uid == uid,
+            Principal::Gid(gid) => peer.gid == gid,
+        }
+    }
+}
+
+/// Ordered [`Rule`]s plus a default.
+///
+/// The first pattern matching the name in any rule that applies to the peer
+/// decides; if none does, the default applies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Policy {
+    rules: Vec<Rule>,
+    default: Access,
+}
+
+impl Policy {
+    /// Deny everything not explicitly allowed.
+    pub fn deny_all() -> Self {
+        Self {
+            rules: Vec::new(),
+            default: Access::Deny,
+        }
+    }
+
+    /// Allow everything not explicitly denied.
+    pub fn allow_all() -> Self {
+        Self {
+            rules: Vec::new(),
+            default: Access::Allow,
+        }
+    }
+
+    /// Append `rule`; earlier rules take precedence.
+    pub fn rule(mut self, rule: Rule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Whether `peer` may run `command`.
+    pub fn check_command(&self, peer: &Peer, command: &str) -> Access {
+        self.check(peer, command, |rule| &rule.commands)
+    }
+
+    /// Whether `peer` may register for `event`.
+    pub fn check_event(&self, peer: &Peer, event: &str) -> Access {
+        self.check(peer, event, |rule| &rule.events)
+    }
+
+    fn check<F>(&self, peer: &Peer, name: &str, patterns: F) -> Access
+    where
+        F: Fn(&Rule) -> &Vec<(Access, String)>,
+    {
+        self.rules
+            .iter()
+            .filter(|rule| rule.applies_to(peer))
+            .flat_map(|rule| patterns(rule).iter())
+            .find(|(_, pattern)| matches(pattern, name))
+            .map_or(self.default, |(access, _)| *access)
+    }
+}
+
+fn matches(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => pattern == name,
+    }
+}
+
+struct Shared {
+    listener: UnixListener,
+    socket: SocketFile,
+    upstream: PathBuf,
+    policy: Mutex<Arc<Policy>>,
+    conns: Mutex<Vec<Arc<UnixStream>>>,
+    stop: AtomicBool,
+}
+
+/// A filtering proxy in front of charon's VICI socket. Clones share the same
+/// listener, policy and connections.
+#[derive(Clone)]
+pub struct Proxy {
+    shared: Arc<Shared>,
+}
+
+impl Proxy {
+    /// Listen on `path` (replacing a stale socket file, but failing if
+    /// something still listens on it) and forward allowed traffic to the
+    /// charon socket at `upstream`.
+    ///
+    /// Restrict access to charon's socket itself, or peers can bypass the
+    /// proxy.
+    pub fn bind<P, Q>(path: P, upstream: Q, policy: Policy) -> Result<Self>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let (listener, socket) = SocketFile::bind(path.as_ref())?;
+        Ok(Self {
+            shared: Arc::new(Shared {
+                listener,
+                socket,
+                upstream: upstream.as_ref().to_path_buf(),
+                policy: Mutex::new(Arc::new(policy)),
+                conns: Mutex::new(Vec::new()),
+                stop: AtomicBool::new(false),
+            }),
+        })
+    }
+
+    /// Path of the proxy socket.
+    pub fn path(&self) -> &Path {
+        self.shared.socket.path()
+    }
+
+    /// Replace the policy; it applies to all later packets, including those
+    /// on open connections.
+    pub fn set_policy(&self, policy: Policy) {
+        *lock(&self.shared.policy) = Arc::new(policy);
+    }
+
+    /// Accept and proxy connections, one thread each, until
+    /// [`shutdown`](Self::shutdown) is called.
+    pub fn run(&self) -> Result<()> {
+        while !self.shared.stop.load(Ordering::SeqCst) {
+            let stream = match self.shared.listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            if self.shared.stop.load(Ordering::SeqCst) {
+                break;
+            }
+            let stream = Arc::new(stream);
+            lock(&self.shared.conns).push(stream.clone());
+            let shared = self.shared.clone();
+            thread::spawn(move || {
+                let _ = serve(&stream, &shared);
+                let _ = stream.shutdown(Shutdown::Both);
+                lock(&shared.conns).retain(|c| !Arc::ptr_eq(c, &stream));
+            });
+        }
+        Ok(())
+    }
+
+    /// Stop [`run`](Self::run) and close all connections.
+    pub fn shutdown(&self) {
+        self.shared.stop.store(true, Ordering::SeqCst);
+        // Wake the accept() call.
+        let _ = UnixStream::connect(self.shared.socket.path());
+        for conn in lock(&self.shared.conns).drain(..) {
+            let _ = conn.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+fn lock<T>(m: &Mutex<T>) -> MutexGuard<'_, T> {
+    m.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn write_frame(mut stream: &UnixStream, payload: &[u8]) -> io::Result<()> {
+    let mut frame = Vec::with_capacity(4 + payload.len());
+    frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    frame.extend_from_slice(payload);
+    stream.write_all(&frame)
+}
+
+/// Call `f` with each frame read from `stream` until EOF or an error.
+fn read_frames<F>(mut stream: &UnixStream, mut f: F) -> Result<()>
+where
+    F: FnMut(Vec<u8>) -> Result<()>,
+{
+    let mut reader = FrameReader::new(MAX_FRAME_LEN);
+    let mut chunk = [0u8; 8192];
+    loop {
+        match reader.next_frame()? {
+            Some(frame) => f(frame)?,
+            None => match stream.read(&mut chunk)? {
+                0 => return Ok(()),
+                n => reader.push(&chunk[..n]),
+            },
+        }
+    }
+}
+
+/// The client end of a proxied connection.
+///
+/// VICI pairs replies with requests by order only, so a denial must not
+/// overtake the replies to requests forwarded before it.
+struct Downstream {
+    stream: UnixStream,
+    /// One entry per unanswered request: `None` while charon owes the reply,
+    /// or the encoded local denial waiting for its turn.
+    pending: VecDeque<Option<Vec<u8>>>,
+}
+
+impl Downstream {
+    /// Send the replies at the front of the queue that are ready.
+    fn flush(&mut self) -> io::Result<()> {
+        while let Some(Some(reply)) = self.pending.front() {
+            self.stream.write_all(reply)?;
+            self.pending.pop_front();
+        }
+        Ok(())
+    }
+}
+
+fn is_reply(frame: &[u8]) -> bool {
+    matches!(
+        frame.first().copied().and_then(PacketType::from_u8),
+        Some(
+            PacketType::CmdResponse
+                | PacketType::CmdUnknown
+                | PacketType::EventConfirm
+                | PacketType::EventUnknown
+        )
+    )
+}
+
+fn serve(client: &UnixStream, shared: &Shared) -> Result<()> {
+    let peer = Peer::of(client)?;
+    let upstream = UnixStream::connect(&shared.upstream)?;
+    // Frames to the client come from both directions; write each whole.
+    let down = Arc::new(Mutex::new(Downstream {
+        stream: client.try_clone()?,
+        pending: VecDeque::new(),
+    }));
+
+    let pump = {
+        let upstream = upstream.try_clone()?;
+        let down = down.clone();
+        thread::spawn(move || {
+            let _ = read_frames(&upstream, |frame| {
+                let mut down = lock(&down);
+                write_frame(&down.stream, &frame)?;
+                if is_reply(&frame) {
+                    down.pending.pop_front();
+                    down.flush()?;
+                }
+                Ok(())
+            });
+            let _ = lock(&down).stream.shutdown(Shutdown::Both);
+        })
+    };
+
+    let res = read_frames(client, |frame| {
+        let pkt = Packet::decode(&frame)?;
+        let name = pkt.name.as_deref().unwrap_or_default();
+        let policy = lock(&shared.policy).clone();
+        let denial = match pkt.ty {
+            PacketType::CmdRequest if policy.check_command(&peer, name) == Access::Deny => {
+                let msg = Message::new()
+                    .kv_str("success", "no")
+                    .kv_str("errmsg", DENIED);
+                Some(Packet::new(PacketType::CmdResponse, None, Some(msg)))
+            }
+            PacketType::EventRegister if policy.check_event(&peer, name) == Access::Deny => {
+                Some(Packet::new(PacketType::EventUnknown, None, None))
+            }
+            _ => None,
+        };
+        let mut down = lock(&down);
+        match denial {
+            Some(reply) => {
+                down.pending.push_back(Some(reply.encode()?));
+                down.flush()?;
+            }
+            None => {
+                if pkt.ty.is_named() && pkt.ty != PacketType::Event {
+                    down.pending.push_back(None);
+                }
+                drop(down);
+                write_frame(&upstream, &frame)?;
+            }
+        }
+        Ok(())
+    });
+    let _ = upstream.shutdown(Shutdown::Both);
+    pump.join()
+        .map_err(|_| Error::Protocol("proxy forwarding thread panicked"))?;
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MONITOR: Peer = Peer {
+        uid: 1000,
+        gid: 977,
+        pid: 1,
+    };
+    const ROOT: Peer = Peer {
+        uid: 0,
+        gid: 0,
+        pid: 1,
+    };
+
+    #[test]
+    fn first_matching_pattern_wins() {
+        let policy = Policy::deny_all()
+            .rule(Rule::uid(0).allow_command("*").allow_event("*"))
+            .rule(Rule::gid(977).deny_command("list-certs").read_only());
+
+        assert_eq!(policy.check_command(&ROOT, "unload-conn"), Access::Allow);
+        assert_eq!(policy.check_command(&MONITOR, "list-sas"), Access::Allow);
+        assert_eq!(policy.check_command(&MONITOR, "list-certs"), Access::Deny);
+        assert_eq!(policy.check_command(&MONITOR, "clear-creds"), Access::Deny);
+        assert_eq!(policy.check_event(&MONITOR, "list-sa"), Access::Allow);
+        assert_eq!(policy.check_event(&MONITOR, "log"), Access::Deny);
+
+        let open = Policy::allow_all().rule(Rule::any().deny_command("clear-*"));
+        assert_eq!(open.check_command(&MONITOR, "clear-creds"), Access::Deny);
+        assert_eq!(open.check_command(&MONITOR, "load-conn"), Access::Allow);
+    }
+}
@@ -0,0 +1,454 @@
+//! Declarative reconciliation of charon's loaded configuration.
+//!
+//! A [`Desired`] state of certificates, private keys, shared secrets, pools
+//! and connections is compared against what charon reports (`list-certs`,
+//! `get-keys`, `get-shared`, `get-pools` and `get-conns`). The resulting
+//! [`Plan`] lists only the `load-*`/`unload-*` calls needed to converge, and
+//! can be inspected before [`Reconciler::apply`] executes it.
+//!
+//! charon does not report the contents of loaded objects, so a
+//! [`Reconciler`] remembers what it loaded: an object it loaded with the same
+//! request earlier is left alone, anything else present under a desired name
+//! is reloaded. Keep one reconciler per daemon for the lifetime of the
+//! control loop.
+//!
+//! ```no_run
+//! use rustici::config::SwanctlConf;
+//! use rustici::reconcile::{Desired, Reconciler};
+//! use rustici::Client;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut client = Client::connect("/var/run/charon.vici")?;
+//! let desired = Desired::from_conf(&SwanctlConf::load("/etc/swanctl/swanctl.conf")?)?;
+//! let mut reconciler = Reconciler::new();
+//! let plan = reconciler.plan(&mut client, &desired)?;
+//! print!("{plan}");
+//! let summary = reconciler.apply(&mut client, &desired, &plan)?;
+//! assert!(summary.is_ok());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+
+use crate::certs::CertFilter;
+use crate::client::{expect_success, Client};
+use crate::config::{decode_base64, LoadCount, LoadSummary, SwanctlConf};
+use crate::conn::ConnectionConfig;
+use crate::creds::{CertFlag, CertType, KeyType, SharedSecret};
+use crate::error::Result;
+use crate::pools::PoolConfig;
+use crate::wire::Message;
+
+/// Kind of object managed by the reconciler, in loading order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Kind {
+    /// A certificate, CRL or public key (`load-cert`; never unloaded, as
+    /// VICI has no command for it).
+    Cert,
+    /// A private key (`load-key`/`unload-key`).
+    Key,
+    /// A shared secret (`load-shared`/`unload-shared`).
+    Shared,
+    /// A virtual IP pool (`load-pool`/`unload-pool`).
+    Pool,
+    /// A connection (`load-conn`/`unload-conn`).
+    Conn,
+}
+
+impl Kind {
+    /// Lowercase name, e.g. `conn`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Kind::Cert => "cert",
+            Kind::Key => "key",
+            Kind::Shared => "shared",
+            Kind::Pool => "pool",
+            Kind::Conn => "conn",
+        }
+    }
+
+    fn load_command(self) -> &'static str {
+        match self {
+            Kind::Cert => "load-cert",
+            Kind::Key => "load-key",
+            Kind::Shared => "load-shared",
+            Kind::Pool => "load-pool",
+            Kind::Conn => "load-conn",
+        }
+    }
+}
+
+/// What a [`Step`] does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Load an object charon does not have.
+    Load,
+    /// Load an object again because it changed or was not loaded by this
+    /// reconciler.
+    Reload,
+    /// Unload an object that is no longer desired.
+    Unload,
+}
+
+impl Action {
+    /// Lowercase name, e.g. `reload`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Action::Load => "load",
+            Action::Reload => "reload",
+            Action::Unload => "unload",
+        }
+    }
+}
+
+/// One call of a [`Plan`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    /// What to do.
+    pub action: Action,
+    /// Kind of object.
+    pub kind: Kind,
+    /// Name of the object in [`Desired`]; for unloads, the name or
+    /// identifier charon reports.
+    pub name: String,
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.action.as_str(),
+            self.kind.as_str(),
+            self.name
+        )
+    }
+}
+
+/// Calls needed to converge, loads before unloads.
+///
+/// Displays as one step per line, e.g. `unload conn old`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Plan {
+    /// The calls, in execution order.
+    pub steps: Vec<Step>,
+    /// Desired objects already loaded as requested.
+    pub unchanged: Vec<(Kind, String)>,
+}
+
+impl Plan {
+    /// Whether charon already matches the desired state.
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            writeln!(f, "{step}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The objects charon should have loaded, keyed by kind and name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Desired {
+    items: BTreeMap<(Kind, String), Message>,
+}
+
+impl Desired {
+    /// An empty state: reconciling it unloads everything unloadable.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Everything `conf` and its credential directories define, named as
+    /// [`Client::load_all`] reports them.
+    pub fn from_conf(conf: &SwanctlConf) -> Result<Self> {
+        let mut desired = Self::new();
+        for (path, ty, flag) in conf.cert_files() {
+            let data = fs::read(&path)?;
+            desired = desired.cert(path.display().to_string(), ty, flag, &data);
+        }
+        for (path, ty) in conf.key_files() {
+            let data = fs::read(&path)?;
+            desired = desired.key(path.display().to_string(), ty, &data);
+        }
+        for (id, secret) in conf.shared_secrets()? {
+            desired = desired.shared(id, secret);
+        }
+        for (name, msg) in conf.pools() {
+            desired.items.insert((Kind::Pool, name), msg);
+        }
+        for (name, msg) in conf.connections()? {
+            desired.items.insert((Kind::Conn, name), msg);
+        }
+        Ok(desired)
+    }
+
+    /// Require the certificate (PEM or DER) `data` under `name`.
+    pub fn cert(
+        mut self,
+        name: impl Into<String>,
+        ty: CertType,
+        flag: Option<CertFlag>,
+        data: &[u8],
+    ) -> Self {
+        let mut msg = Message::new().kv_str("type", ty.as_str());
+        if let Some(flag) = flag {
+            msg = msg.kv_str("flag", flag.as_str());
+        }
+        msg = msg.kv_bytes("data", data);
+        self.items.insert((Kind::Cert, name.into()), msg);
+        self
+    }
+
+    /// Require the private key (PEM or DER) `data` under `name`.
+    pub fn key(mut self, name: impl Into<String>, ty: KeyType, data: &[u8]) -> Self {
+        let msg = Message::new()
+            .kv_str("type", ty.as_str())
+            .kv_bytes("data", data);
+        self.items.insert((Kind::Key, name.into()), msg);
+        self
+    }
+
+    /// Require `secret` with identifier `id`.
+    pub fn shared(mut self, id: impl Into<String>, secret: SharedSecret) -> Self {
+        let id = id.into();
+        let msg = secret.id(id.clone()).to_message();
+        self.items.insert((Kind::Shared, id), msg);
+        self
+    }
+
+    /// Require `pool`.
+    pub fn pool(mut self, pool: &PoolConfig) -> Self {
+        self.items
+            .insert((Kind::Pool, pool.name().to_string()), pool.to_message());
+        self
+    }
+
+    /// Require `conn`.
+    pub fn conn(mut self, conn: &ConnectionConfig) -> Self {
+        self.items
+            .insert((Kind::Conn, conn.name().to_string()), conn.to_message());
+        self
+    }
+
+    /// Names of the desired objects of `kind`.
+    pub fn names(&self, kind: Kind) -> impl Iterator<Item = &str> {
+        self.items
+            .keys()
+            .filter(move |(k, _)| *k == kind)
+            .map(|(_, name)| name.as_str())
+    }
+}
+
+/// Plans and applies the calls converging charon on a [`Desired`] state,
+/// remembering what it loaded across runs.
+#[derive(Debug, Clone, Default)]
+pub struct Reconciler {
+    applied: BTreeMap<(Kind, String), Message>,
+    key_ids: BTreeMap<String, String>,
+}
+
+impl Reconciler {
+    /// A reconciler that has not loaded anything yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compare `desired` against charon's current state.
+    ///
+    /// Private keys are only known by identifier, which charon derives from
+    /// the key; a key this reconciler has not loaded before is planned as a
+    /// load, and keys it turns out to be are not unloaded when applying.
+    pub fn plan(&self, client: &mut Client, desired: &Desired) -> Result<Plan> {
+        let certs: BTreeSet<Vec<u8>> = client
+            .list_certs(&CertFilter::new())?
+            .into_iter()
+            .map(|cert| cert.data)
+            .collect();
+        let mut current = BTreeMap::new();
+        current.insert(Kind::Key, client.get_keys()?);
+        current.insert(Kind::Shared, client.get_shared()?);
+        current.insert(
+            Kind::Pool,
+            client
+                .get_pools(None, false)?
+                .into_iter()
+                .map(|pool| pool.name)
+                .collect(),
+        );
+        current.insert(Kind::Conn, client.get_conns()?);
+
+        let mut plan = Plan::default();
+        let mut claimed = BTreeSet::new();
+        for ((kind, name), msg) in &desired.items {
+            let unchanged = self.applied.get(&(*kind, name.clone())) == Some(msg);
+            let present = match kind {
+                Kind::Cert => certs.contains(&der(msg)),
+                Kind::Key => match self.key_ids.get(name) {
+                    Some(id) if current[kind].contains(id) => {
+                        if unchanged {
+                            claimed.insert(id.clone());
+                        }
+                        true
+                    }
+                    _ => false,
+                },
+                _ => current[kind].contains(name),
+            };
+            let action = match (present, unchanged || *kind == Kind::Cert) {
+                (true, true) => {
+                    plan.unchanged.push((*kind, name.clone()));
+                    continue;
+                }
+                (true, false) => Action::Reload,
+                (false, _) => Action::Load,
+            };
+            plan.steps.push(Step {
+                action,
+                kind: *kind,
+                name: name.clone(),
+            });
+        }
+        for kind in [Kind::Conn, Kind::Pool, Kind::Shared, Kind::Key] {
+            for name in &current[&kind] {
+                let wanted = match kind {
+                    Kind::Key => claimed.contains(name),
+                    _ => desired.items.contains_key(&(kind, name.clone())),
+                };
+                if !wanted {
+                    plan.steps.push(Step {
+                        action: Action::Unload,
+                        kind,
+                        name: name.clone(),
+                    });
+                }
+            }
+        }
+        Ok(plan)
+    }
+
+    /// Execute `plan`, made by [`plan`](Self::plan) for `desired`.
+    ///
+    /// Calls charon rejects are reported in the summary; I/O and protocol
+    /// errors abort.
+    pub fn apply(
+        &mut self,
+        client: &mut Client,
+        desired: &Desired,
+        plan: &Plan,
+    ) -> Result<LoadSummary> {
+        let mut summary = LoadSummary::default();
+        let mut loaded_keys = BTreeSet::new();
+        for step in &plan.steps {
+            let key = (step.kind, step.name.clone());
+            let count = count(&mut summary, step.kind);
+            if step.action == Action::Unload {
+                if step.kind == Kind::Key && loaded_keys.contains(&step.name) {
+                    continue;
+                }
+                let res = match step.kind {
+                    Kind::Key => client.unload_key(&step.name),
+                    Kind::Shared => client.unload_shared(&step.name),
+                    Kind::Pool => client.unload_pool(&step.name),
+                    Kind::Conn => client.unload_conn(&step.name),
+                    Kind::Cert => continue,
+                };
+                if res.is_ok() {
+                    self.applied.remove(&key);
+                    if step.kind == Kind::Key {
+                        self.key_ids.retain(|_, id| *id != step.name);
+                    }
+                }
+                count.track(&step.name, res, true)?;
+                continue;
+            }
+            let Some(msg) = desired.items.get(&key) else {
+                continue;
+            };
+            let res = client
+                .call(step.kind.load_command(), msg)
+                .and_then(|resp| expect_success(&resp));
+            let res = res.map(|tree| {
+                if let Some(id) = tree.str("id") {
+                    loaded_keys.insert(id.to_string());
+                    self.key_ids.insert(step.name.clone(), id.to_string());
+                }
+                self.applied.insert(key, msg.clone());
+            });
+            count.track(&step.name, res, false)?;
+        }
+        Ok(summary)
+    }
+
+    /// Plan and apply in one go, returning both.
+    pub fn reconcile(
+        &mut self,
+        client: &mut Client,
+        desired: &Desired,
+    ) -> Result<(Plan, LoadSummary)> {
+        let plan = self.plan(client, desired)?;
+        let summary = self.apply(client, desired, &plan)?;
+        Ok((plan, summary))
+    }
+}
+
+fn count(summary: &mut LoadSummary, kind: Kind) -> &mut LoadCount {
+    match kind {
+        Kind::Cert => &mut summary.certs,
+        Kind::Key => &mut summary.keys,
+        Kind::Shared => &mut summary.shared,
+        Kind::Pool => &mut summary.pools,
+        Kind::Conn => &mut summary.conns,
+    }
+}
+
+/// The DER encoding of a `load-cert` request's data, as `list-certs`
+/// reports it.
+fn der(msg: &Message) -> Vec<u8> {
+    let data = msg
+        .tree()
+        .ok()
+        .and_then(|t| t.bytes("data").map(<[u8]>::to_vec))
+        .unwrap_or_default();
+    let Ok(text) = std::str::from_utf8(&data) else {
+        return data;
+    };
+    if !text.trim_start().starts_with("-----BEGIN") {
+        return data;
+    }
+    let body: String = text
+        .lines()
+        .skip_while(|l| !l.starts_with("-----BEGIN"))
+        .skip(1)
+        .take_while(|l| !l.starts_with("-----END"))
+        .filter(|l| !l.contains(':'))
+        .collect();
+    decode_base64(&body).unwrap_or(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pem_certs_compare_as_der() {
+        let pem = b"-----BEGIN CERTIFICATE-----\nAAEC\nAw==\n-----END CERTIFICATE-----\n";
+        let desired = Desired::new().cert("a", CertType::X509, None, pem).cert(
+            "b",
+            CertType::X509,
+            None,
+            &[9, 9],
+        );
+        let der: Vec<_> = desired.items.values().map(der).collect();
+        assert_eq!(der, [vec![0, 1, 2, 3], vec![9, 9]]);
+        assert_eq!(desired.names(Kind::Cert).collect::<Vec<_>>(), ["a", "b"]);
+    }
+}
@@ -0,0 +1,309 @@
+//! A [`Client`] wrapper that survives daemon restarts.
+//!
+//! When charon restarts, the socket of a [`Client`] breaks and all of its
+//! event registrations are gone. [`ReconnectingClient`] notices the broken
+//! connection, reconnects following a [`Backoff`] policy, re-registers every
+//! event that was registered before, and reports the gap to the caller as
+//! [`Notification::Reconnected`].
+
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use crate::builder::ClientBuilder;
+use crate::client::Client;
+use crate::error::Result;
+use crate::wire::Message;
+
+/// Exponential backoff between reconnection attempts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    factor: u32,
+    max_attempts: Option<u32>,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(100),
+            max: Duration::from_secs(30),
+            factor: 2,
+            max_attempts: None,
+        }
+    }
+}
+
+impl Backoff {
+    /// 100ms initial delay, doubling up to 30s, retrying forever.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delay before the first reconnection attempt.
+    pub fn initial(mut self, delay: Duration) -> Self {
+        self.initial = delay;
+        self
+    }
+
+    /// Upper bound for the delay between attempts.
+    pub fn max(mut self, delay: Duration) -> Self {
+        self.max = delay;
+        self
+    }
+
+    /// Multiplier applied to the delay after each failed attempt.
+    pub fn factor(mut self, factor: u32) -> Self {
+        self.factor = factor.max(1);
+        self
+    }
+
+    /// Give up after `attempts` failed attempts (default: never).
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = Some(attempts);
+        self
+    }
+
+    /// Delay before attempt number `attempt` (starting at 1).
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let mut delay = self.initial;
+        for _ in 1..attempt {
+            delay = delay.saturating_mul(self.factor);
+            if delay >= self.max {
+                return self.max;
+            }
+        }
+        delay.min(self.max)
+    }
+
+    /// Whether to give up after `attempt` failed attempts.
+    pub(crate) fn exhausted(&self, attempt: u32) -> bool {
+        self.max_attempts.map_or(false, |m| attempt >= m)
+    }
+}
+
+/// What [`ReconnectingClient::next_event`] hands out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Notification {
+    /// An event (name and message), as from [`Client::next_event`].
+    Event(String, Message),
+    /// The connection was lost and re-established; events in between were
+    /// missed and state derived from them should be refreshed.
+    Reconnected {
+        /// Number of connection attempts it took.
+        attempts: u32,
+    },
+}
+
+/// A [`Client`] that reconnects and re-registers its events when the
+/// connection to charon breaks.
+pub struct ReconnectingClient {
+    builder: ClientBuilder,
+    client: Client,
+    backoff: Backoff,
+    /// Attempts of reconnects made during calls, not yet reported.
+    reconnected: Option<u32>,
+}
+
+impl ReconnectingClient {
+    /// Connect to the VICI socket at `path`.
+    ///
+    /// The initial connection is not retried.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rustici::reconnect::{Notification, ReconnectingClient};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = ReconnectingClient::connect("/var/run/charon.vici")?;
+    /// client.register_event("ike-updown")?;
+    /// loop {
+    ///     match client.next_event()? {
+    ///         Notification::Event(name, _) => println!("event {name}"),
+    ///         Notification::Reconnected { .. } => println!("charon restarted"),
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn connect<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_builder(ClientBuilder::new().path(path))
+    }
+
+    pub(crate) fn from_builder(builder: ClientBuilder) -> Result<Self> {
+        let client = builder.build()?;
+        Ok(Self {
+            backoff: builder.backoff(),
+            builder,
+            client,
+            reconnected: None,
+        })
+    }
+
+    /// Use `backoff` for future reconnections.
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// The underlying client, e.g. for typed commands.
+    ///
+    /// Errors returned by commands on it do not trigger a reconnect; call
+    /// [`reconnect`](Self::reconnect) on errors that
+    /// [`is_disconnect`](crate::error::Error::is_disconnect).
+    pub fn client(&mut self) -> &mut Client {
+        &mut self.client
+    }
+
+    /// Set the read timeout, re-applied after every reconnect.
+    pub fn set_read_timeout(&mut self, to: Option<Duration>) -> Result<()> {
+        self.client.set_read_timeout(to)?;
+        self.builder.set_read_timeout(to);
+        Ok(())
+    }
+
+    /// Set the write timeout, re-applied after every reconnect.
+    pub fn set_write_timeout(&mut self, to: Option<Duration>) -> Result<()> {
+        self.client.set_write_timeout(to)?;
+        self.builder.set_write_timeout(to);
+        Ok(())
+    }
+
+    /// Register for event `name`; it is re-registered after reconnects.
+    pub fn register_event(&mut self, name: &str) -> Result<()> {
+        let res = self.client.register_event(name);
+        self.handle_error(res)
+    }
+
+    /// Unregister from event `name`.
+    pub fn unregister_event(&mut self, name: &str) -> Result<()> {
+        let res = self.client.unregister_event(name);
+        self.handle_error(res)
+    }
+
+    /// Send a command and wait for its response.
+    ///
+    /// A command interrupted by a broken connection is not retried, as it may
+    /// already have taken effect; the connection is re-established, the
+    /// original error returned, and [`Notification::Reconnected`] handed out
+    /// by the next [`next_event`](Self::next_event).
+    pub fn call(&mut self, command: &str, request: &Message) -> Result<Message> {
+        let res = self.client.call(command, request);
+        self.handle_error(res)
+    }
+
+    /// Block until the next event arrives, reconnecting if needed.
+    ///
+    /// A reconnect made by an earlier call is reported first.
+    pub fn next_event(&mut self) -> Result<Notification> {
+        if let Some(attempts) = self.reconnected.take() {
+            return Ok(Notification::Reconnected { attempts });
+        }
+        match self.client.next_event() {
+            Ok((name, msg)) => Ok(Notification::Event(name, msg)),
+            Err(e) if e.is_disconnect() => {
+                let attempts = self.reconnect()?;
+                Ok(Notification::Reconnected { attempts })
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Wait at most `timeout` for the next event, reconnecting if needed.
+    pub fn try_next_event(&mut self, timeout: Duration) -> Result<Notification> {
+        if let Some(attempts) = self.reconnected.take() {
+            return Ok(Notification::Reconnected { attempts });
+        }
+        match self.client.try_next_event(timeout) {
+            Ok((name, msg)) => Ok(Notification::Event(name, msg)),
+            Err(e) if e.is_disconnect() => {
+                let attempts = self.reconnect()?;
+                Ok(Notification::Reconnected { attempts })
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Reconnect if `res` failed because the connection broke, then hand
+    /// back `res` unchanged (or the error that ended reconnecting).
+    ///
+    /// The reconnect is reported by the next [`next_event`](Self::next_event).
+    fn handle_error<T>(&mut self, res: Result<T>) -> Result<T> {
+        if let Err(e) = &res {
+            if e.is_disconnect() {
+                let attempts = self.reconnect()?;
+                *self.reconnected.get_or_insert(0) += attempts;
+            }
+        }
+        res
+    }
+
+    /// Re-establish the connection following the backoff policy and
+    /// re-register the events registered on it; returns the number of
+    /// attempts it took.
+    ///
+    /// Fails with the last connection error once `max_attempts` is reached.
+    pub fn reconnect(&mut self) -> Result<u32> {
+        // Registrations left over from aborted streams are not restored.
+        let aborted = self.client.aborted_events();
+        let events: Vec<String> = self
+            .client
+            .registered_events()
+            .iter()
+            .filter(|name| !aborted.contains(name))
+            .cloned()
+            .collect();
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            thread::sleep(self.backoff.delay(attempt));
+            match self.try_connect(&events) {
+                Ok(client) => {
+                    self.client = client;
+                    return Ok(attempt);
+                }
+                Err(e) => {
+                    if self.backoff.exhausted(attempt) {
+                        return Err(e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn try_connect(&self, events: &[String]) -> Result<Client> {
+        let mut client = self.builder.build()?;
+        for name in events {
+            client.register_event(name)?;
+        }
+        Ok(client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_delays() {
+        let b = Backoff::new()
+            .initial(Duration::from_millis(50))
+            .max(Duration::from_millis(300))
+            .factor(3);
+        assert_eq!(b.delay(1), Duration::from_millis(50));
+        assert_eq!(b.delay(2), Duration::from_millis(150));
+        assert_eq!(b.delay(3), Duration::from_millis(300));
+        assert_eq!(b.delay(40), Duration::from_millis(300));
+    }
+
+    #[test]
+    fn disconnect_classification() {
+        use crate::error::Error;
+
+        let eof = Error::Io(std::io::Error::from(std::io::ErrorKind::UnexpectedEof));
+        assert!(eof.is_disconnect());
+        assert!(!Error::Timeout.is_disconnect());
+        assert!(!Error::Protocol("x").is_disconnect());
+    }
+}
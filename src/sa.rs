@@ -0,0 +1,333 @@
+//! Typed IKE_SA and CHILD_SA descriptions.
+//!
+//! charon uses the same section layout for an IKE_SA in `list-sa` events
+//! and in the `ike-*`/`child-*` notifications; [`IkeSa::from_section`]
+//! parses that layout once for all of them. Fields charon omits for a
+//! given SA state are left as `None` or empty.
+
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::wire::{Message, Section};
+
+/// An IKE_SA.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IkeSa {
+    /// Name of the connection the IKE_SA belongs to.
+    pub name: String,
+    /// Unique IKE_SA identifier.
+    pub uniqueid: u32,
+    /// IKE major version.
+    pub version: Option<u8>,
+    /// IKE_SA state, e.g. `CONNECTING`, `ESTABLISHED`, `DELETING`.
+    pub state: String,
+    /// Local address.
+    pub local_host: Option<String>,
+    /// Local port.
+    pub local_port: Option<u16>,
+    /// Local identity.
+    pub local_id: Option<String>,
+    /// Remote address.
+    pub remote_host: Option<String>,
+    /// Remote port.
+    pub remote_port: Option<u16>,
+    /// Remote identity.
+    pub remote_id: Option<String>,
+    /// Remote XAuth identity.
+    pub remote_xauth_id: Option<String>,
+    /// Remote EAP identity.
+    pub remote_eap_id: Option<String>,
+    /// Whether we are the original initiator.
+    pub initiator: bool,
+    /// Initiator SPI (hex).
+    pub initiator_spi: Option<String>,
+    /// Responder SPI (hex).
+    pub responder_spi: Option<String>,
+    /// Whether the local host is behind NAT.
+    pub nat_local: bool,
+    /// Whether the remote host is behind NAT.
+    pub nat_remote: bool,
+    /// Negotiated encryption algorithm.
+    pub encr_alg: Option<String>,
+    /// Encryption key size.
+    pub encr_keysize: Option<u32>,
+    /// Negotiated integrity algorithm.
+    pub integ_alg: Option<String>,
+    /// Integrity key size.
+    pub integ_keysize: Option<u32>,
+    /// Negotiated pseudo-random function.
+    pub prf_alg: Option<String>,
+    /// Negotiated Diffie-Hellman group.
+    pub dh_group: Option<String>,
+    /// Seconds since the IKE_SA was established.
+    pub established: Option<u64>,
+    /// Seconds until the IKE_SA is rekeyed.
+    pub rekey_time: Option<u64>,
+    /// Seconds until the IKE_SA is reauthenticated.
+    pub reauth_time: Option<u64>,
+    /// Virtual IPs assigned to us.
+    pub local_vips: Vec<String>,
+    /// Virtual IPs assigned to the peer.
+    pub remote_vips: Vec<String>,
+    /// Queued tasks.
+    pub tasks_queued: Vec<String>,
+    /// Active tasks.
+    pub tasks_active: Vec<String>,
+    /// Passive tasks.
+    pub tasks_passive: Vec<String>,
+    /// CHILD_SAs of this IKE_SA.
+    pub child_sas: Vec<ChildSa>,
+}
+
+impl IkeSa {
+    /// Parse the section describing the IKE_SA of connection `name`.
+    pub fn from_section(name: &str, sec: &Section) -> Result<Self> {
+        let mut child_sas = Vec::new();
+        if let Some(children) = sec.section("child-sas") {
+            for (key, child) in children.sections() {
+                child_sas.push(ChildSa::from_section(key, child)?);
+            }
+        }
+        Ok(Self {
+            name: name.to_string(),
+            uniqueid: sec
+                .parse("uniqueid")
+                .ok_or(Error::Protocol("IKE_SA without uniqueid"))?,
+            version: sec.parse("version"),
+            state: sec.str("state").unwrap_or_default().to_string(),
+            local_host: string(sec, "local-host"),
+            local_port: sec.parse("local-port"),
+            local_id: string(sec, "local-id"),
+            remote_host: string(sec, "remote-host"),
+            remote_port: sec.parse("remote-port"),
+            remote_id: string(sec, "remote-id"),
+            remote_xauth_id: string(sec, "remote-xauth-id"),
+            remote_eap_id: string(sec, "remote-eap-id"),
+            initiator: sec.flag("initiator").unwrap_or(false),
+            initiator_spi: string(sec, "initiator-spi"),
+            responder_spi: string(sec, "responder-spi"),
+            nat_local: sec.flag("nat-local").unwrap_or(false),
+            nat_remote: sec.flag("nat-remote").unwrap_or(false),
+            encr_alg: string(sec, "encr-alg"),
+            encr_keysize: sec.parse("encr-keysize"),
+            integ_alg: string(sec, "integ-alg"),
+            integ_keysize: sec.parse("integ-keysize"),
+            prf_alg: string(sec, "prf-alg"),
+            dh_group: string(sec, "dh-group"),
+            established: sec.parse("established"),
+            rekey_time: sec.parse("rekey-time"),
+            reauth_time: sec.parse("reauth-time"),
+            local_vips: sec.str_list("local-vips"),
+            remote_vips: sec.str_list("remote-vips"),
+            tasks_queued: sec.str_list("tasks-queued"),
+            tasks_active: sec.str_list("tasks-active"),
+            tasks_passive: sec.str_list("tasks-passive"),
+            child_sas,
+        })
+    }
+
+    /// Parse all IKE_SAs contained in a `list-sa` event message.
+    pub fn from_list_sa(msg: &Message) -> Result<Vec<Self>> {
+        msg.tree()?
+            .sections()
+            .map(|(name, sec)| Self::from_section(name, sec))
+            .collect()
+    }
+
+    /// Look up a CHILD_SA by its unique id.
+    pub fn child(&self, uniqueid: u32) -> Option<&ChildSa> {
+        self.child_sas.iter().find(|c| c.uniqueid == uniqueid)
+    }
+}
+
+/// A CHILD_SA.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChildSa {
+    /// Key charon lists the CHILD_SA under (`<name>-<uniqueid>` in `list-sa`).
+    pub key: String,
+    /// Name of the CHILD_SA configuration.
+    pub name: String,
+    /// Unique CHILD_SA identifier.
+    pub uniqueid: u32,
+    /// Reqid of the CHILD_SA.
+    pub reqid: Option<u32>,
+    /// CHILD_SA state, e.g. `INSTALLED`, `REKEYED`, `DELETING`.
+    pub state: String,
+    /// IPsec mode, e.g. `TUNNEL`.
+    pub mode: Option<String>,
+    /// IPsec protocol, e.g. `ESP`.
+    pub protocol: Option<String>,
+    /// Whether ESP is UDP-encapsulated.
+    pub encap: bool,
+    /// Inbound SPI (hex).
+    pub spi_in: Option<String>,
+    /// Outbound SPI (hex).
+    pub spi_out: Option<String>,
+    /// Negotiated encryption algorithm.
+    pub encr_alg: Option<String>,
+    /// Encryption key size.
+    pub encr_keysize: Option<u32>,
+    /// Negotiated integrity algorithm.
+    pub integ_alg: Option<String>,
+    /// Integrity key size.
+    pub integ_keysize: Option<u32>,
+    /// Diffie-Hellman group used for PFS.
+    pub dh_group: Option<String>,
+    /// Bytes received.
+    pub bytes_in: u64,
+    /// Packets received.
+    pub packets_in: u64,
+    /// Seconds since the last inbound packet.
+    pub use_in: Option<u64>,
+    /// Bytes sent.
+    pub bytes_out: u64,
+    /// Packets sent.
+    pub packets_out: u64,
+    /// Seconds since the last outbound packet.
+    pub use_out: Option<u64>,
+    /// Seconds until the CHILD_SA is rekeyed.
+    pub rekey_time: Option<u64>,
+    /// Seconds until the CHILD_SA expires.
+    pub life_time: Option<u64>,
+    /// Seconds since the CHILD_SA was installed.
+    pub install_time: Option<u64>,
+    /// Local traffic selectors.
+    pub local_ts: Vec<String>,
+    /// Remote traffic selectors.
+    pub remote_ts: Vec<String>,
+}
+
+impl ChildSa {
+    /// Parse the section listed under `key` in an IKE_SA's `child-sas`.
+    pub fn from_section(key: &str, sec: &Section) -> Result<Self> {
+        Ok(Self {
+            key: key.to_string(),
+            name: sec.str("name").unwrap_or(key).to_string(),
+            uniqueid: sec
+                .parse("uniqueid")
+                .ok_or(Error::Protocol("CHILD_SA without uniqueid"))?,
+            reqid: sec.parse("reqid"),
+            state: sec.str("state").unwrap_or_default().to_string(),
+            mode: string(sec, "mode"),
+            protocol: string(sec, "protocol"),
+            encap: sec.flag("encap").unwrap_or(false),
+            spi_in: string(sec, "spi-in"),
+            spi_out: string(sec, "spi-out"),
+            encr_alg: string(sec, "encr-alg"),
+            encr_keysize: sec.parse("encr-keysize"),
+            integ_alg: string(sec, "integ-alg"),
+            integ_keysize: sec.parse("integ-keysize"),
+            dh_group: string(sec, "dh-group"),
+            bytes_in: sec.parse("bytes-in").unwrap_or(0),
+            packets_in: sec.parse("packets-in").unwrap_or(0),
+            use_in: sec.parse("use-in"),
+            bytes_out: sec.parse("bytes-out").unwrap_or(0),
+            packets_out: sec.parse("packets-out").unwrap_or(0),
+            use_out: sec.parse("use-out"),
+            rekey_time: sec.parse("rekey-time"),
+            life_time: sec.parse("life-time"),
+            install_time: sec.parse("install-time"),
+            local_ts: sec.str_list("local-ts"),
+            remote_ts: sec.str_list("remote-ts"),
+        })
+    }
+}
+
+impl Client {
+    /// List IKE_SAs, optionally only those of connection `ike`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rustici::Client;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = Client::connect("/var/run/charon.vici")?;
+    /// for ike in client.list_sas(None)? {
+    ///     println!("{} #{} {}", ike.name, ike.uniqueid, ike.state);
+    ///     for child in &ike.child_sas {
+    ///         println!("  {} {:?} === {:?}", child.name, child.local_ts, child.remote_ts);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn list_sas(&mut self, ike: Option<&str>) -> Result<Vec<IkeSa>> {
+        let mut req = Message::new();
+        if let Some(ike) = ike {
+            req = req.kv_str("ike", ike);
+        }
+        self.collect_streamed("list-sas", &req, "list-sa", |msg, out| {
+            out.append(&mut IkeSa::from_list_sa(msg)?);
+            Ok(())
+        })
+    }
+}
+
+fn string(sec: &Section, key: &str) -> Option<String> {
+    sec.str(key).map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Message {
+        Message::new()
+            .section_start("gw")
+            .kv_str("uniqueid", "3")
+            .kv_str("version", "2")
+            .kv_str("state", "ESTABLISHED")
+            .kv_str("local-host", "192.0.2.1")
+            .kv_str("local-port", "4500")
+            .kv_str("remote-host", "198.51.100.1")
+            .kv_str("initiator", "yes")
+            .kv_str("nat-remote", "yes")
+            .kv_str("established", "120")
+            .list_start("remote-vips")
+            .list_item_str("10.3.0.1")
+            .list_end()
+            .section_start("child-sas")
+            .section_start("net-7")
+            .kv_str("name", "net")
+            .kv_str("uniqueid", "7")
+            .kv_str("state", "INSTALLED")
+            .kv_str("bytes-in", "1024")
+            .kv_str("packets-out", "5")
+            .list_start("local-ts")
+            .list_item_str("10.1.0.0/16")
+            .list_end()
+            .section_end()
+            .section_end()
+            .section_end()
+    }
+
+    #[test]
+    fn list_sa_parsing() {
+        let sas = IkeSa::from_list_sa(&sample()).unwrap();
+        assert_eq!(sas.len(), 1);
+        let gw = &sas[0];
+        assert_eq!(gw.name, "gw");
+        assert_eq!(gw.uniqueid, 3);
+        assert_eq!(gw.version, Some(2));
+        assert_eq!(gw.local_port, Some(4500));
+        assert!(gw.initiator);
+        assert!(gw.nat_remote && !gw.nat_local);
+        assert_eq!(gw.remote_vips, ["10.3.0.1"]);
+
+        let net = gw.child(7).unwrap();
+        assert_eq!(net.key, "net-7");
+        assert_eq!(net.name, "net");
+        assert_eq!(net.bytes_in, 1024);
+        assert_eq!(net.packets_out, 5);
+        assert_eq!(net.local_ts, ["10.1.0.0/16"]);
+    }
+
+    #[test]
+    fn missing_uniqueid_is_an_error() {
+        let msg = Message::new()
+            .section_start("gw")
+            .kv_str("state", "CONNECTING")
+            .section_end();
+        assert!(IkeSa::from_list_sa(&msg).is_err());
+    }
+}
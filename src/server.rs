@@ -0,0 +1,495 @@
+//! Server side of the VICI protocol.
+//!
+//! [`Server`] accepts connections on a UNIX or TCP socket, decodes packets
+//! with the same codec the client uses, dispatches command requests to
+//! registered handlers and keeps track of which events each connection has
+//! registered for, so events can be broadcast to exactly those clients.
+//!
+//! ```no_run
+//! use std::thread;
+//! use rustici::server::{Response, Server};
+//! use rustici::Message;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let server = Server::bind("unix:///tmp/my.vici")?;
+//! server.handle("version", |_req| {
+//!     Response::Message(Message::new().kv_str("daemon", "my-daemon"))
+//! });
+//! let broadcaster = server.clone();
+//! thread::spawn(move || server.run());
+//! broadcaster.broadcast("ike-updown", &Message::new().kv_str("up", "yes"));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+
+use crate::client::Transport;
+use crate::error::{Error, Result};
+use crate::packet::{FrameReader, Packet, PacketType, MAX_FRAME_LEN};
+use crate::wire::Message;
+
+/// What a handler answers to a command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Response {
+    /// A `CmdResponse` carrying this message.
+    Message(Message),
+    /// A `CmdUnknown` packet, as for commands without a handler.
+    Unknown,
+    /// No reply; the handler wrote one itself or the client is left waiting.
+    None,
+    /// Close the connection without a reply.
+    Close,
+}
+
+/// A command request being handled.
+pub struct Request<'a> {
+    command: &'a str,
+    message: &'a Message,
+    conn: &'a Conn,
+}
+
+impl Request<'_> {
+    /// Name of the command.
+    pub fn command(&self) -> &str {
+        self.command
+    }
+
+    /// The request message.
+    pub fn message(&self) -> &Message {
+        self.message
+    }
+
+    /// Whether the requesting connection is registered for `event`.
+    pub fn is_registered(&self, event: &str) -> bool {
+        lock(&self.conn.events).contains(event)
+    }
+
+    /// Stream `event` to the requesting connection before the response, as
+    /// `list-sas` does with `list-sa`.
+    ///
+    /// Like charon, this only sends to a connection registered for `event`;
+    /// returns whether it was sent.
+    pub fn stream(&self, event: &str, msg: &Message) -> Result<bool> {
+        if !self.is_registered(event) {
+            return Ok(false);
+        }
+        let pkt = Packet::new(
+            PacketType::Event,
+            Some(event.to_string()),
+            Some(msg.clone()),
+        );
+        self.conn.send(&pkt)?;
+        Ok(true)
+    }
+
+    /// Write raw bytes to the requesting connection, bypassing framing.
+    #[cfg(feature = "testing")]
+    pub(crate) fn write_raw(&self, bytes: &[u8]) -> Result<()> {
+        lock(&self.conn.writer).write_all(bytes)?;
+        Ok(())
+    }
+}
+
+type Handler = Box<dyn FnMut(&Request<'_>) -> Response + Send>;
+type EventFilter = Box<dyn Fn(&str) -> bool + Send + Sync>;
+
+struct Conn {
+    writer: Mutex<Transport>,
+    events: Mutex<BTreeSet<String>>,
+}
+
+impl Conn {
+    fn send(&self, pkt: &Packet) -> Result<()> {
+        let frame = pkt.encode()?;
+        lock(&self.writer).write_all(&frame)?;
+        Ok(())
+    }
+}
+
+enum Listener {
+    Unix(UnixListener, SocketFile),
+    Tcp(TcpListener, SocketAddr),
+}
+
+struct Shared {
+    listener: Listener,
+    handlers: Mutex<HashMap<String, Arc<Mutex<Handler>>>>,
+    fallback: Mutex<Option<Arc<Mutex<Handler>>>>,
+    event_filter: Mutex<Option<EventFilter>>,
+    conns: Mutex<Vec<Arc<Conn>>>,
+    max_frame: usize,
+    stop: AtomicBool,
+}
+
+/// A VICI server. Clones share the same listener, handlers and connections.
+#[derive(Clone)]
+pub struct Server {
+    shared: Arc<Shared>,
+}
+
+impl Server {
+    /// Listen on a `unix:///path` or `tcp://host:port` URI.
+    pub fn bind(uri: &str) -> Result<Self> {
+        if let Some(path) = uri.strip_prefix("unix://") {
+            Self::bind_unix(path)
+        } else if let Some(addr) = uri.strip_prefix("tcp://") {
+            Self::bind_tcp(addr)
+        } else {
+            Err(Error::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unsupported VICI URI: {uri}"),
+            )))
+        }
+    }
+
+    /// Listen on the UNIX socket `path`, replacing a stale socket file.
+    ///
+    /// Fails with [`io::ErrorKind::AddrInUse`] if something is still
+    /// listening on `path`.
+    pub fn bind_unix<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let (listener, socket) = SocketFile::bind(path.as_ref())?;
+        Ok(Self::new(Listener::Unix(listener, socket)))
+    }
+
+    /// Listen on TCP address `addr`, e.g. `127.0.0.1:4502`.
+    pub fn bind_tcp(addr: &str) -> Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let local = listener.local_addr()?;
+        Ok(Self::new(Listener::Tcp(listener, local)))
+    }
+
+    fn new(listener: Listener) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                listener,
+                handlers: Mutex::new(HashMap::new()),
+                fallback: Mutex::new(None),
+                event_filter: Mutex::new(None),
+                conns: Mutex::new(Vec::new()),
+                max_frame: MAX_FRAME_LEN,
+                stop: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// The UNIX socket path, if listening on one.
+    pub fn path(&self) -> Option<&Path> {
+        match &self.shared.listener {
+            Listener::Unix(_, socket) => Some(socket.path()),
+            Listener::Tcp(..) => None,
+        }
+    }
+
+    /// The TCP address, if listening on one (useful after binding port 0).
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        match &self.shared.listener {
+            Listener::Tcp(_, addr) => Some(*addr),
+            Listener::Unix(..) => None,
+        }
+    }
+
+    /// Handle command `name` with `handler`, replacing an earlier one.
+    ///
+    /// Calls to the same handler are serialized; different handlers run
+    /// concurrently for different connections.
+    pub fn handle<F>(&self, name: &str, handler: F) -> &Self
+    where
+        F: FnMut(&Request<'_>) -> Response + Send + 'static,
+    {
+        lock(&self.shared.handlers)
+            .insert(name.to_string(), Arc::new(Mutex::new(Box::new(handler))));
+        self
+    }
+
+    /// Handle commands without a specific handler (default: `CmdUnknown`).
+    pub fn fallback<F>(&self, handler: F) -> &Self
+    where
+        F: FnMut(&Request<'_>) -> Response + Send + 'static,
+    {
+        *lock(&self.shared.fallback) = Some(Arc::new(Mutex::new(Box::new(handler))));
+        self
+    }
+
+    /// Accept event registrations only for names `filter` returns `true`
+    /// for; others get `EventUnknown` (default: accept all).
+    pub fn event_filter<F>(&self, filter: F) -> &Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        *lock(&self.shared.event_filter) = Some(Box::new(filter));
+        self
+    }
+
+    /// Send `event` to every connection registered for it; returns the number
+    /// of connections it reached.
+    pub fn broadcast(&self, event: &str, msg: &Message) -> usize {
+        let pkt = Packet::new(
+            PacketType::Event,
+            Some(event.to_string()),
+            Some(msg.clone()),
+        );
+        let conns = lock(&self.shared.conns).clone();
+        conns
+            .iter()
+            .filter(|c| lock(&c.events).contains(event))
+            .filter(|c| c.send(&pkt).is_ok())
+            .count()
+    }
+
+    /// Events at least one connection is registered for.
+    pub fn registrations(&self) -> BTreeSet<String> {
+        lock(&self.shared.conns)
+            .iter()
+            .flat_map(|c| lock(&c.events).clone())
+            .collect()
+    }
+
+    /// Number of open connections.
+    pub fn connections(&self) -> usize {
+        lock(&self.shared.conns).len()
+    }
+
+    /// Close all open connections; the server keeps accepting new ones.
+    pub fn disconnect_all(&self) {
+        for conn in lock(&self.shared.conns).drain(..) {
+            let _ = lock(&conn.writer).shutdown();
+        }
+    }
+
+    /// Accept and serve connections, one thread each, until
+    /// [`shutdown`](Self::shutdown) is called.
+    pub fn run(&self) -> Result<()> {
+        while !self.shared.stop.load(Ordering::SeqCst) {
+            let stream = match &self.shared.listener {
+                Listener::Unix(l, _) => l.accept().map(|(s, _)| Transport::Unix(s)),
+                Listener::Tcp(l, _) => l.accept().map(|(s, _)| Transport::Tcp(s)),
+            };
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            if self.shared.stop.load(Ordering::SeqCst) {
+                break;
+            }
+            let conn = Arc::new(Conn {
+                writer: Mutex::new(stream.try_clone()?),
+                events: Mutex::new(BTreeSet::new()),
+            });
+            lock(&self.shared.conns).push(conn.clone());
+            let shared = self.shared.clone();
+            thread::spawn(move || {
+                serve(stream, &conn, &shared);
+                let _ = lock(&conn.writer).shutdown();
+                lock(&shared.conns).retain(|c| !Arc::ptr_eq(c, &conn));
+            });
+        }
+        Ok(())
+    }
+
+    /// Stop [`run`](Self::run) and close all connections.
+    pub fn shutdown(&self) {
+        self.shared.stop.store(true, Ordering::SeqCst);
+        // Wake the accept() call.
+        match &self.shared.listener {
+            Listener::Unix(_, socket) => drop(UnixStream::connect(socket.path())),
+            Listener::Tcp(_, addr) => drop(TcpStream::connect(addr)),
+        }
+        self.disconnect_all();
+    }
+}
+
+/// A UNIX socket file we bound; removed on drop unless it was replaced.
+#[derive(Debug)]
+pub(crate) struct SocketFile {
+    path: PathBuf,
+    id: (u64, u64),
+}
+
+impl SocketFile {
+    /// Bind `path`, first removing a socket file nobody listens on anymore.
+    pub(crate) fn bind(path: &Path) -> io::Result<(UnixListener, Self)> {
+        match UnixStream::connect(path) {
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("{} is in use", path.display()),
+                ))
+            }
+            Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => {
+                // Connecting to any other kind of file is refused too.
+                if fs::symlink_metadata(path)?.file_type().is_socket() {
+                    fs::remove_file(path)?;
+                }
+            }
+            Err(_) => {}
+        }
+        let listener = UnixListener::bind(path)?;
+        let meta = fs::symlink_metadata(path)?;
+        let socket = Self {
+            path: path.to_path_buf(),
+            id: (meta.dev(), meta.ino()),
+        };
+        Ok((listener, socket))
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        let ours =
+            fs::symlink_metadata(&self.path).map_or(false, |m| (m.dev(), m.ino()) == self.id);
+        if ours {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+fn lock<T>(m: &Mutex<T>) -> MutexGuard<'_, T> {
+    // A panicking handler must not take the whole server down with it.
+    m.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn serve(mut stream: Transport, conn: &Conn, shared: &Shared) {
+    let mut reader = FrameReader::new(shared.max_frame);
+    let mut chunk = [0u8; 8192];
+    loop {
+        let frame = match reader.next_frame() {
+            Ok(Some(frame)) => frame,
+            Ok(None) => match stream.read(&mut chunk) {
+                Ok(0) | Err(_) => return,
+                Ok(n) => {
+                    reader.push(&chunk[..n]);
+                    continue;
+                }
+            },
+            Err(_) => return,
+        };
+        let pkt = match Packet::decode(&frame) {
+            Ok(pkt) => pkt,
+            Err(_) => return,
+        };
+        if dispatch(pkt, conn, shared).is_err() {
+            return;
+        }
+    }
+}
+
+/// Answer one packet; an error closes the connection.
+fn dispatch(pkt: Packet, conn: &Conn, shared: &Shared) -> Result<()> {
+    let name = pkt.name.unwrap_or_default();
+    match pkt.ty {
+        PacketType::CmdRequest => {
+            let message = pkt.message.unwrap_or_default();
+            let handler = lock(&shared.handlers)
+                .get(&name)
+                .cloned()
+                .or_else(|| lock(&shared.fallback).clone());
+            let req = Request {
+                command: &name,
+                message: &message,
+                conn,
+            };
+            let resp = match handler {
+                Some(handler) => {
+                    let mut handler = lock(&handler);
+                    handler(&req)
+                }
+                None => Response::Unknown,
+            };
+            match resp {
+                Response::Message(msg) => {
+                    conn.send(&Packet::new(PacketType::CmdResponse, None, Some(msg)))
+                }
+                Response::Unknown => conn.send(&Packet::new(PacketType::CmdUnknown, None, None)),
+                Response::None => Ok(()),
+                Response::Close => Err(Error::Protocol("closed by handler")),
+            }
+        }
+        PacketType::EventRegister => {
+            let accepted = lock(&shared.event_filter)
+                .as_ref()
+                .map_or(true, |filter| filter(&name));
+            if accepted {
+                lock(&conn.events).insert(name);
+                conn.send(&Packet::new(PacketType::EventConfirm, None, None))
+            } else {
+                conn.send(&Packet::new(PacketType::EventUnknown, None, None))
+            }
+        }
+        PacketType::EventUnregister => {
+            lock(&conn.events).remove(&name);
+            conn.send(&Packet::new(PacketType::EventConfirm, None, None))
+        }
+        _ => Err(Error::Protocol("unexpected packet from client")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::Client;
+
+    #[test]
+    fn dispatch_and_broadcast_over_tcp() {
+        let server = Server::bind("tcp://127.0.0.1:0").unwrap();
+        server.handle("echo", |req| Response::Message(req.message().clone()));
+        let addr = server.local_addr().unwrap();
+        let runner = server.clone();
+        let handle = thread::spawn(move || runner.run());
+
+        let mut client = Client::builder()
+            .uri(&format!("tcp://{addr}"))
+            .unwrap()
+            .build()
+            .unwrap();
+        let req = Message::new().kv_str("hello", "world");
+        assert_eq!(client.call("echo", &req).unwrap(), req);
+        assert!(matches!(
+            client.call("nope", &Message::new()),
+            Err(Error::UnknownCommand(_))
+        ));
+
+        client.register_event("ike-updown").unwrap();
+        assert!(server.registrations().contains("ike-updown"));
+        assert_eq!(server.broadcast("ike-updown", &req), 1);
+        assert_eq!(client.next_event().unwrap().0, "ike-updown");
+
+        server.shutdown();
+        handle.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn unix_socket_file_handling() {
+        let path = std::env::temp_dir().join(format!("rustici-server-{}.sock", std::process::id()));
+        // A socket file left behind by a dead server is replaced.
+        drop(UnixListener::bind(&path).unwrap());
+        let server = Server::bind_unix(&path).unwrap();
+        // A live one is not.
+        let err = Server::bind_unix(&path).err().unwrap();
+        assert!(matches!(err, Error::Io(e) if e.kind() == io::ErrorKind::AddrInUse));
+
+        // Only our own socket file is removed on drop.
+        fs::remove_file(&path).unwrap();
+        let other = UnixListener::bind(&path).unwrap();
+        drop(server);
+        assert!(path.exists());
+        drop(other);
+        let server = Server::bind_unix(&path).unwrap();
+        drop(server);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,425 @@
+//! Keeping CHILD_SAs up.
+//!
+//! A [`Supervisor`] watches `ike-updown` and `child-updown` for a set of
+//! tunnels, each a CHILD_SA configuration of a connection, and initiates the
+//! ones that go down unexpectedly again, backing off exponentially while
+//! initiation keeps failing. Tunnels terminated through
+//! [`Supervisor::terminate`] stay down until [`Supervisor::start`].
+//!
+//! The supervisor only learns about SAs through the events it is fed; after
+//! a daemon restart (see [`ReconnectingClient`](crate::reconnect::ReconnectingClient))
+//! call [`Supervisor::resync`]. Give the client an
+//! [`event_buffer`](crate::builder::ClientBuilder::event_buffer), or updown
+//! events arriving while it initiates, terminates or lists SAs are lost.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use rustici::supervisor::Supervisor;
+//! use rustici::Client;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut client = Client::builder().event_buffer(64).build()?;
+//! let mut supervisor = Supervisor::new().tunnel("gw", "net").tunnel("gw", "voip");
+//! supervisor.attach(&mut client)?;
+//! loop {
+//!     supervisor.run_once(&mut client, Duration::from_secs(60))?;
+//!     for (ike, child, health) in supervisor.tunnels() {
+//!         println!("{ike}/{child}: {:?}", health.state);
+//!     }
+//! }
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use crate::client::Client;
+use crate::control::{ControlOutcome, InitiateRequest, TerminateRequest};
+use crate::error::{Error, Result};
+use crate::event::Event;
+use crate::reconnect::Backoff;
+use crate::sa::IkeSa;
+
+/// Events the supervisor consumes.
+pub const SUPERVISED_EVENTS: [&str; 3] = ["ike-updown", "child-updown", "child-rekey"];
+
+/// Whether a tunnel is up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TunnelState {
+    /// The CHILD_SA is installed.
+    Up,
+    /// The CHILD_SA is down; unless stopped, initiation is scheduled.
+    Down,
+    /// Initiation failed as often as the backoff policy allows; the tunnel is
+    /// no longer initiated until [`Supervisor::start`].
+    Failed,
+}
+
+/// Health of one supervised tunnel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TunnelHealth {
+    /// Current state.
+    pub state: TunnelState,
+    /// When the state last changed.
+    pub since: Instant,
+    /// Whether the tunnel was terminated through the supervisor and is kept
+    /// down.
+    pub stopped: bool,
+    /// Unique id of the installed CHILD_SA.
+    pub child_id: Option<u32>,
+    /// Failed initiations since the tunnel was last up.
+    pub failures: u32,
+    /// Initiations issued by the supervisor that succeeded.
+    pub restarts: u32,
+    /// Reason of the last failed initiation.
+    pub last_error: Option<String>,
+    /// When the next initiation is due.
+    pub retry_at: Option<Instant>,
+}
+
+impl TunnelHealth {
+    fn new() -> Self {
+        Self {
+            state: TunnelState::Down,
+            since: Instant::now(),
+            stopped: false,
+            child_id: None,
+            failures: 0,
+            restarts: 0,
+            last_error: None,
+            retry_at: None,
+        }
+    }
+
+    fn set(&mut self, state: TunnelState) {
+        if self.state != state {
+            self.state = state;
+            self.since = Instant::now();
+        }
+    }
+
+    fn up(&mut self, child_id: u32) {
+        self.set(TunnelState::Up);
+        self.child_id = Some(child_id);
+        self.failures = 0;
+        self.retry_at = None;
+    }
+
+    /// Mark down, scheduling initiation after `delay` unless stopped.
+    fn down(&mut self, delay: Duration) {
+        self.set(TunnelState::Down);
+        self.child_id = None;
+        if !self.stopped {
+            self.retry_at = Some(Instant::now() + delay);
+        }
+    }
+}
+
+/// Re-initiates supervised tunnels when they go down unexpectedly.
+#[derive(Debug, Clone)]
+pub struct Supervisor {
+    tunnels: BTreeMap<(String, String), TunnelHealth>,
+    backoff: Backoff,
+    timeout: Option<Duration>,
+}
+
+impl Default for Supervisor {
+    fn default() -> Self {
+        Self {
+            tunnels: BTreeMap::new(),
+            backoff: Backoff::new().initial(Duration::from_secs(1)),
+            timeout: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+impl Supervisor {
+    /// A supervisor without tunnels, retrying after 1s, doubling up to 30s,
+    /// forever.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delays between initiations of a tunnel that went down or failed to
+    /// come up; the first delay applies after an unexpected down.
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// How long an initiation may take (default 30s; `None` waits as long as
+    /// charon does).
+    pub fn initiate_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Supervise CHILD_SA `child` of connection `ike`.
+    pub fn tunnel(mut self, ike: impl Into<String>, child: impl Into<String>) -> Self {
+        self.tunnels
+            .insert((ike.into(), child.into()), TunnelHealth::new());
+        self
+    }
+
+    /// Register for [`SUPERVISED_EVENTS`] on `client` and [`resync`](Self::resync).
+    pub fn attach(&mut self, client: &mut Client) -> Result<()> {
+        for name in SUPERVISED_EVENTS {
+            if !client.is_registered(name) {
+                client.register_event(name)?;
+            }
+        }
+        self.resync(client)
+    }
+
+    /// Refresh the state from `list-sas`; tunnels that are not up are due for
+    /// initiation immediately (unless stopped or failed).
+    pub fn resync(&mut self, client: &mut Client) -> Result<()> {
+        let sas = client.list_sas(None)?;
+        for ((ike, child), health) in &mut self.tunnels {
+            let installed = sas
+                .iter()
+                .filter(|sa| sa.name == *ike)
+                .flat_map(|sa| &sa.child_sas)
+                .find(|c| c.name == *child && c.state == "INSTALLED");
+            match installed {
+                Some(c) => health.up(c.uniqueid),
+                None if health.state == TunnelState::Failed => {}
+                None => health.down(Duration::ZERO),
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply one event; returns whether it affected a supervised tunnel.
+    pub fn apply(&mut self, event: &Event) -> bool {
+        match event {
+            Event::ChildUpdown { up, ike } => {
+                let mut hit = false;
+                for child in &ike.child_sas {
+                    let key = (ike.name.clone(), child.name.clone());
+                    let Some(health) = self.tunnels.get_mut(&key) else {
+                        continue;
+                    };
+                    if *up {
+                        health.up(child.uniqueid);
+                        hit = true;
+                    } else if health.child_id.map_or(true, |id| id == child.uniqueid) {
+                        health.down(self.backoff.delay(1));
+                        hit = true;
+                    }
+                }
+                hit
+            }
+            Event::IkeUpdown { up: false, ike } => self.ike_down(ike),
+            Event::ChildRekey { ike, old, new } => {
+                let key = (ike.name.clone(), new.name.clone());
+                match self.tunnels.get_mut(&key) {
+                    Some(health) if health.child_id == Some(old.uniqueid) => {
+                        health.child_id = Some(new.uniqueid);
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// CHILD_SAs are usually reported down individually; this catches those
+    /// that went down with their IKE_SA without a `child-updown`.
+    fn ike_down(&mut self, ike: &IkeSa) -> bool {
+        let mut hit = false;
+        for ((name, _), health) in &mut self.tunnels {
+            let gone = health
+                .child_id
+                .map_or(false, |id| ike.child_sas.iter().any(|c| c.uniqueid == id));
+            if *name == ike.name && gone {
+                health.down(self.backoff.delay(1));
+                hit = true;
+            }
+        }
+        hit
+    }
+
+    /// Wait up to `max_wait` for an event, shortened to when the next
+    /// initiation is due, apply it, then initiate all due tunnels.
+    ///
+    /// Returns the event, or `None` if none arrived in time.
+    pub fn run_once(&mut self, client: &mut Client, max_wait: Duration) -> Result<Option<Event>> {
+        let now = Instant::now();
+        let wait = self
+            .tunnels
+            .values()
+            .filter_map(|h| h.retry_at)
+            .min()
+            .map_or(max_wait, |at| {
+                at.saturating_duration_since(now).min(max_wait)
+            });
+        let event = if wait.is_zero() {
+            None
+        } else {
+            match client.try_next_typed_event(wait) {
+                Ok(event) => {
+                    self.apply(&event);
+                    Some(event)
+                }
+                Err(Error::Timeout) => None,
+                Err(e) => return Err(e),
+            }
+        };
+        self.initiate_due(client)?;
+        Ok(event)
+    }
+
+    /// Initiate every tunnel whose retry is due.
+    ///
+    /// charon rejecting an initiation is not an error; it is recorded in the
+    /// tunnel's health and retried later.
+    pub fn initiate_due(&mut self, client: &mut Client) -> Result<()> {
+        let now = Instant::now();
+        for ((ike, child), health) in &mut self.tunnels {
+            if health.stopped || health.retry_at.map_or(true, |at| at > now) {
+                continue;
+            }
+            let mut req = InitiateRequest::new()
+                .ike(ike.as_str())
+                .child(child.as_str());
+            if let Some(timeout) = self.timeout {
+                req = req.timeout(timeout);
+            }
+            let outcome = match client.initiate(&req) {
+                Ok(outcome) => outcome,
+                Err(Error::CommandFailed(msg)) => ControlOutcome {
+                    errmsg: Some(msg),
+                    ..ControlOutcome::default()
+                },
+                Err(e) => return Err(e),
+            };
+            if outcome.success {
+                health.restarts += 1;
+                health.set(TunnelState::Up);
+                health.failures = 0;
+                health.last_error = None;
+                health.retry_at = None;
+            } else {
+                health.failures += 1;
+                health.last_error = outcome.errmsg;
+                if self.backoff.exhausted(health.failures) {
+                    health.set(TunnelState::Failed);
+                    health.retry_at = None;
+                } else {
+                    health.retry_at =
+                        Some(Instant::now() + self.backoff.delay(health.failures + 1));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Terminate CHILD_SA `child` of `ike` and keep it down until
+    /// [`start`](Self::start).
+    pub fn terminate(
+        &mut self,
+        client: &mut Client,
+        ike: &str,
+        child: &str,
+    ) -> Result<ControlOutcome> {
+        let mut req = TerminateRequest::new().ike(ike);
+        let health = self.tunnels.get_mut(&(ike.to_string(), child.to_string()));
+        if let Some(health) = health {
+            health.stopped = true;
+            health.retry_at = None;
+            if let Some(id) = health.child_id {
+                req = req.child_id(id);
+            }
+        }
+        req = req.child(child);
+        if let Some(timeout) = self.timeout {
+            req = req.timeout(timeout);
+        }
+        let outcome = client.terminate(&req)?;
+        if outcome.success {
+            if let Some(health) = self.tunnels.get_mut(&(ike.to_string(), child.to_string())) {
+                health.down(Duration::ZERO);
+            }
+        }
+        Ok(outcome)
+    }
+
+    /// Supervise CHILD_SA `child` of `ike` again (adding it if needed) after
+    /// [`terminate`](Self::terminate) or a failure, initiating it on the
+    /// next [`run_once`](Self::run_once) if it is down.
+    pub fn start(&mut self, ike: &str, child: &str) {
+        let health = self
+            .tunnels
+            .entry((ike.to_string(), child.to_string()))
+            .or_insert_with(TunnelHealth::new);
+        health.stopped = false;
+        health.failures = 0;
+        if health.state != TunnelState::Up {
+            health.set(TunnelState::Down);
+            health.retry_at = Some(Instant::now());
+        }
+    }
+
+    /// Health of CHILD_SA `child` of `ike`, if supervised.
+    pub fn health(&self, ike: &str, child: &str) -> Option<&TunnelHealth> {
+        self.tunnels.get(&(ike.to_string(), child.to_string()))
+    }
+
+    /// All supervised tunnels as `(ike, child, health)`.
+    pub fn tunnels(&self) -> impl Iterator<Item = (&str, &str, &TunnelHealth)> {
+        self.tunnels
+            .iter()
+            .map(|((ike, child), health)| (ike.as_str(), child.as_str(), health))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sa::ChildSa;
+
+    fn child_event(up: bool, id: u32) -> Event {
+        Event::ChildUpdown {
+            up,
+            ike: IkeSa {
+                name: "gw".into(),
+                uniqueid: 1,
+                child_sas: vec![ChildSa {
+                    name: "net".into(),
+                    uniqueid: id,
+                    ..ChildSa::default()
+                }],
+                ..IkeSa::default()
+            },
+        }
+    }
+
+    #[test]
+    fn tracks_updown_and_respects_stop() {
+        let mut sup = Supervisor::new()
+            .backoff(Backoff::new().initial(Duration::from_secs(5)))
+            .tunnel("gw", "net");
+        assert!(sup.apply(&child_event(true, 7)));
+        let health = sup.health("gw", "net").unwrap();
+        assert_eq!((health.state, health.child_id), (TunnelState::Up, Some(7)));
+
+        // A stale CHILD_SA going down does not affect the tunnel.
+        assert!(!sup.apply(&child_event(false, 6)));
+        assert!(sup.apply(&child_event(false, 7)));
+        let health = sup.health("gw", "net").unwrap();
+        assert_eq!(health.state, TunnelState::Down);
+        assert!(health.retry_at.unwrap() > Instant::now() + Duration::from_secs(4));
+
+        sup.tunnels
+            .get_mut(&("gw".into(), "net".into()))
+            .unwrap()
+            .stopped = true;
+        sup.apply(&child_event(true, 8));
+        sup.apply(&child_event(false, 8));
+        assert_eq!(sup.health("gw", "net").unwrap().retry_at, None);
+        sup.start("gw", "net");
+        assert!(sup.health("gw", "net").unwrap().retry_at.is_some());
+    }
+}
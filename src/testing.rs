@@ -0,0 +1,234 @@
+//! An in-process VICI server for tests (feature `testing`).
+//!
+//! [`MockServer`] listens on a fresh UNIX socket in the temp directory and
+//! answers commands with scripted [`Reply`]s. It tracks event registrations
+//! per connection, emits events on demand and can inject faults, so code
+//! built on rustici can be tested without a running charon.
+//!
+//! ```
+//! use rustici::testing::{MockServer, Reply};
+//! use rustici::Message;
+//!
+//! let server = MockServer::start().unwrap();
+//! server.on_command("version", |_| {
+//!     Reply::Response(Message::new().kv_str("daemon", "charon"))
+//! });
+//! let mut client = server.connect().unwrap();
+//! let resp = client.call("version", &Message::new()).unwrap();
+//! assert_eq!(resp.tree().unwrap().str("daemon"), Some("charon"));
+//! ```
+
+use std::collections::BTreeSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+
+use crate::client::Client;
+use crate::error::Result;
+use crate::packet::{Packet, PacketType};
+use crate::server::{Request, Response, Server};
+use crate::wire::Message;
+
+/// How the server answers a command.
+#[derive(Debug, Clone)]
+pub enum Reply {
+    /// A `CmdResponse` with this message.
+    Response(Message),
+    /// Stream `events` as `event` packets (to connections registered for
+    /// `event`, as charon does), then respond with `response`.
+    Stream {
+        /// Name of the streamed event, e.g. `list-sa`.
+        event: String,
+        /// One message per streamed event.
+        events: Vec<Message>,
+        /// The final response.
+        response: Message,
+    },
+    /// A `CmdUnknown` packet.
+    Unknown,
+    /// Send only the first `bytes` bytes of a response frame carrying this
+    /// message, then close the connection.
+    Truncated(Message, usize),
+    /// Send these raw bytes as-is.
+    Raw(Vec<u8>),
+    /// Do not answer at all.
+    Silent,
+    /// Close the connection without answering.
+    Close,
+}
+
+/// A scripted VICI server on a temporary UNIX socket.
+///
+/// Commands without a handler are answered with `CmdUnknown`. The socket is
+/// removed and all connections are closed when the server is dropped.
+pub struct MockServer {
+    server: Server,
+    path: PathBuf,
+    rejected_events: Arc<Mutex<BTreeSet<String>>>,
+    requests: Arc<Mutex<Vec<(String, Message)>>>,
+    runner: Option<JoinHandle<Result<()>>>,
+}
+
+impl MockServer {
+    /// Bind a fresh socket in the temp directory and start serving.
+    pub fn start() -> io::Result<Self> {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "rustici-mock-{}-{}.sock",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        Self::start_at(path)
+    }
+
+    /// Bind `path` (replacing a stale socket file) and start serving.
+    pub fn start_at<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let server = Server::bind_unix(&path).map_err(into_io)?;
+
+        let rejected_events = Arc::new(Mutex::new(BTreeSet::new()));
+        let rejected = rejected_events.clone();
+        server.event_filter(move |name| !lock(&rejected).contains(name));
+
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let log = requests.clone();
+        server.fallback(move |req| {
+            record(&log, req);
+            Response::Unknown
+        });
+
+        let runner = server.clone();
+        Ok(Self {
+            server,
+            path,
+            rejected_events,
+            requests,
+            runner: Some(thread::spawn(move || runner.run())),
+        })
+    }
+
+    /// Path of the listening socket.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The underlying [`Server`].
+    pub fn server(&self) -> &Server {
+        &self.server
+    }
+
+    /// Connect a [`Client`] to this server.
+    pub fn connect(&self) -> Result<Client> {
+        Client::connect(&self.path)
+    }
+
+    /// Answer command `name` with whatever `handler` returns for the request.
+    ///
+    /// Replaces an earlier handler for the same command.
+    pub fn on_command<F>(&self, name: &str, mut handler: F) -> &Self
+    where
+        F: FnMut(&Message) -> Reply + Send + 'static,
+    {
+        let log = self.requests.clone();
+        self.server.handle(name, move |req| {
+            record(&log, req);
+            respond(req, handler(req.message()))
+        });
+        self
+    }
+
+    /// Answer registrations for event `name` with `EventUnknown`.
+    pub fn reject_event(&self, name: &str) -> &Self {
+        lock(&self.rejected_events).insert(name.to_string());
+        self
+    }
+
+    /// Send event `name` to every connection registered for it; returns the
+    /// number of connections it was sent to.
+    pub fn emit(&self, name: &str, msg: &Message) -> usize {
+        self.server.broadcast(name, msg)
+    }
+
+    /// Events any connection is currently registered for.
+    pub fn registrations(&self) -> BTreeSet<String> {
+        self.server.registrations()
+    }
+
+    /// Whether any connection is registered for event `name`.
+    pub fn is_registered(&self, name: &str) -> bool {
+        self.server.registrations().contains(name)
+    }
+
+    /// All commands received so far, with their request messages.
+    pub fn requests(&self) -> Vec<(String, Message)> {
+        lock(&self.requests).clone()
+    }
+
+    /// Number of currently open connections.
+    pub fn connections(&self) -> usize {
+        self.server.connections()
+    }
+
+    /// Close all open connections, e.g. to simulate a daemon restart.
+    pub fn disconnect_all(&self) {
+        self.server.disconnect_all();
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.server.shutdown();
+        if let Some(runner) = self.runner.take() {
+            let _ = runner.join();
+        }
+    }
+}
+
+fn lock<T>(m: &Mutex<T>) -> MutexGuard<'_, T> {
+    m.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn into_io(e: crate::error::Error) -> io::Error {
+    match e {
+        crate::error::Error::Io(e) => e,
+        other => io::Error::new(io::ErrorKind::Other, other.to_string()),
+    }
+}
+
+fn record(log: &Mutex<Vec<(String, Message)>>, req: &Request<'_>) {
+    lock(log).push((req.command().to_string(), req.message().clone()));
+}
+
+fn respond(req: &Request<'_>, reply: Reply) -> Response {
+    match reply {
+        Reply::Response(msg) => Response::Message(msg),
+        Reply::Stream {
+            event,
+            events,
+            response,
+        } => {
+            for msg in &events {
+                if req.stream(&event, msg).is_err() {
+                    return Response::Close;
+                }
+            }
+            Response::Message(response)
+        }
+        Reply::Unknown => Response::Unknown,
+        Reply::Truncated(msg, bytes) => {
+            let pkt = Packet::new(PacketType::CmdResponse, None, Some(msg));
+            if let Ok(frame) = pkt.encode() {
+                let _ = req.write_raw(&frame[..bytes.min(frame.len())]);
+            }
+            Response::Close
+        }
+        Reply::Raw(bytes) => match req.write_raw(&bytes) {
+            Ok(()) => Response::None,
+            Err(_) => Response::Close,
+        },
+        Reply::Silent => Response::None,
+        Reply::Close => Response::Close,
+    }
+}
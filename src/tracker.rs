@@ -0,0 +1,428 @@
+//! Live view of charon's IKE_SAs and CHILD_SAs.
+//!
+//! [`SaTracker::attach`] registers for the SA lifecycle events, seeds its
+//! state from `list-sas` and then keeps it current as events are fed to it
+//! through [`SaTracker::update`]. Events that arrive while `list-sas` is
+//! streaming are buffered and applied on top of the seed, so no transition
+//! is lost between the two.
+//!
+//! Every change applied to the state is also queued as an [`SaChange`];
+//! drain them with [`SaTracker::changes`].
+
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+
+use crate::client::Client;
+use crate::error::Result;
+use crate::event::Event;
+use crate::sa::{ChildSa, IkeSa};
+use crate::wire::Message;
+
+/// Events the tracker consumes.
+pub const TRACKED_EVENTS: [&str; 5] = [
+    "ike-updown",
+    "child-updown",
+    "ike-rekey",
+    "child-rekey",
+    "ike-update",
+];
+
+/// A change applied to the tracked state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SaChange {
+    /// The state was replaced by a fresh `list-sas` snapshot.
+    Seeded,
+    /// An IKE_SA was established or updated.
+    IkeUp(u32),
+    /// An IKE_SA went down and was removed.
+    IkeDown(u32),
+    /// An IKE_SA was replaced by a rekeyed one.
+    IkeRekeyed {
+        /// Unique id of the replaced IKE_SA.
+        old: u32,
+        /// Unique id of the new IKE_SA.
+        new: u32,
+    },
+    /// The endpoints of an IKE_SA changed.
+    IkeUpdated(u32),
+    /// A CHILD_SA was installed.
+    ChildUp {
+        /// Unique id of the owning IKE_SA.
+        ike: u32,
+        /// Unique id of the CHILD_SA.
+        child: u32,
+    },
+    /// A CHILD_SA went down and was removed.
+    ChildDown {
+        /// Unique id of the owning IKE_SA.
+        ike: u32,
+        /// Unique id of the CHILD_SA.
+        child: u32,
+    },
+    /// A CHILD_SA was replaced by a rekeyed one.
+    ChildRekeyed {
+        /// Unique id of the owning IKE_SA.
+        ike: u32,
+        /// Unique id of the replaced CHILD_SA.
+        old: u32,
+        /// Unique id of the new CHILD_SA.
+        new: u32,
+    },
+}
+
+/// Event-driven cache of all IKE_SAs and their CHILD_SAs.
+#[derive(Debug, Clone, Default)]
+pub struct SaTracker {
+    sas: BTreeMap<u32, IkeSa>,
+    changes: VecDeque<SaChange>,
+}
+
+impl SaTracker {
+    /// An empty tracker; populate it with [`seed`](Self::seed) and
+    /// [`apply`](Self::apply).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register for [`TRACKED_EVENTS`] on `client` and seed from `list-sas`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rustici::Client;
+    /// use rustici::tracker::SaTracker;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = Client::connect("/var/run/charon.vici")?;
+    /// let mut tracker = SaTracker::attach(&mut client)?;
+    /// loop {
+    ///     tracker.update(&mut client)?;
+    ///     for change in tracker.changes() {
+    ///         println!("{change:?}");
+    ///     }
+    ///     println!("{} IKE_SAs", tracker.len());
+    /// }
+    /// # }
+    /// ```
+    pub fn attach(client: &mut Client) -> Result<Self> {
+        for name in TRACKED_EVENTS {
+            if !client.is_registered(name) {
+                client.register_event(name)?;
+            }
+        }
+        let mut tracker = Self::new();
+        tracker.resync(client)?;
+        Ok(tracker)
+    }
+
+    /// Re-seed from `list-sas`, applying events that race with the listing.
+    pub fn resync(&mut self, client: &mut Client) -> Result<()> {
+        let mut listed = Vec::new();
+        let mut pending = Vec::new();
+        let mut failure = None;
+        client.with_event("list-sa", |client| {
+            client.call_streaming("list-sas", &Message::new(), |name, msg| {
+                if name == "list-sa" {
+                    match IkeSa::from_list_sa(msg) {
+                        Ok(mut sas) => listed.append(&mut sas),
+                        Err(e) if failure.is_none() => failure = Some(e),
+                        Err(_) => {}
+                    }
+                } else {
+                    pending.push((name.to_string(), msg.clone()));
+                }
+            })
+        })?;
+        if let Some(e) = failure {
+            return Err(e);
+        }
+
+        self.seed(listed);
+        for (name, msg) in pending {
+            self.apply(&Event::parse(&name, &msg)?);
+        }
+        Ok(())
+    }
+
+    /// Block for the next event on `client` and apply it.
+    pub fn update(&mut self, client: &mut Client) -> Result<Event> {
+        let event = client.next_typed_event()?;
+        self.apply(&event);
+        Ok(event)
+    }
+
+    /// Like [`update`](Self::update), waiting at most `timeout`.
+    pub fn try_update(&mut self, client: &mut Client, timeout: Duration) -> Result<Event> {
+        let event = client.try_next_typed_event(timeout)?;
+        self.apply(&event);
+        Ok(event)
+    }
+
+    /// Replace the state with `sas`, e.g. the result of [`Client::list_sas`].
+    pub fn seed(&mut self, sas: Vec<IkeSa>) {
+        self.sas = sas.into_iter().map(|sa| (sa.uniqueid, sa)).collect();
+        self.changes.push_back(SaChange::Seeded);
+    }
+
+    /// Apply one event; returns whether it changed the state.
+    ///
+    /// Events the tracker does not consume are ignored.
+    pub fn apply(&mut self, event: &Event) -> bool {
+        let before = self.changes.len();
+        match event {
+            Event::IkeUpdown { up: true, ike } => {
+                upsert(&mut self.sas, ike.clone());
+                self.changes.push_back(SaChange::IkeUp(ike.uniqueid));
+            }
+            Event::IkeUpdown { up: false, ike } if self.sas.remove(&ike.uniqueid).is_some() => {
+                self.changes.push_back(SaChange::IkeDown(ike.uniqueid));
+            }
+            Event::ChildUpdown { up, ike } => {
+                let id = ike.uniqueid;
+                let children = ike.child_sas.clone();
+                let mut parent = ike.clone();
+                parent.child_sas.clear();
+                let entry = upsert(&mut self.sas, parent);
+                for child in children {
+                    let child_id = child.uniqueid;
+                    if *up {
+                        upsert_child(entry, child);
+                        self.changes.push_back(SaChange::ChildUp {
+                            ike: id,
+                            child: child_id,
+                        });
+                    } else if remove_child(entry, child_id) {
+                        self.changes.push_back(SaChange::ChildDown {
+                            ike: id,
+                            child: child_id,
+                        });
+                    }
+                }
+            }
+            Event::IkeRekey { old, new } => {
+                let mut new = new.clone();
+                if let Some(prev) = self.sas.remove(&old.uniqueid) {
+                    // CHILD_SAs migrate to the new IKE_SA.
+                    if new.child_sas.is_empty() {
+                        new.child_sas = prev.child_sas;
+                    }
+                }
+                self.changes.push_back(SaChange::IkeRekeyed {
+                    old: old.uniqueid,
+                    new: new.uniqueid,
+                });
+                upsert(&mut self.sas, new);
+            }
+            Event::ChildRekey { ike, old, new } => {
+                let entry = upsert(&mut self.sas, ike.clone());
+                remove_child(entry, old.uniqueid);
+                upsert_child(entry, new.clone());
+                self.changes.push_back(SaChange::ChildRekeyed {
+                    ike: ike.uniqueid,
+                    old: old.uniqueid,
+                    new: new.uniqueid,
+                });
+            }
+            Event::IkeUpdate {
+                local_host,
+                local_port,
+                remote_host,
+                remote_port,
+                ike,
+            } => {
+                let entry = upsert(&mut self.sas, ike.clone());
+                entry.local_host = Some(local_host.clone());
+                entry.local_port = Some(*local_port);
+                entry.remote_host = Some(remote_host.clone());
+                entry.remote_port = Some(*remote_port);
+                self.changes.push_back(SaChange::IkeUpdated(ike.uniqueid));
+            }
+            _ => {}
+        }
+        self.changes.len() != before
+    }
+
+    /// Drain the queued changes, oldest first.
+    ///
+    /// Changes accumulate until drained; consumers that only query the
+    /// state should call [`clear_changes`](Self::clear_changes) regularly.
+    pub fn changes(&mut self) -> impl Iterator<Item = SaChange> + '_ {
+        self.changes.drain(..)
+    }
+
+    /// Drop all queued changes.
+    pub fn clear_changes(&mut self) {
+        self.changes.clear();
+    }
+
+    /// Look up an IKE_SA by its unique id.
+    pub fn get(&self, uniqueid: u32) -> Option<&IkeSa> {
+        self.sas.get(&uniqueid)
+    }
+
+    /// All IKE_SAs of connection `name`.
+    pub fn by_name<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a IkeSa> + 'a {
+        self.sas.values().filter(move |sa| sa.name == name)
+    }
+
+    /// All tracked IKE_SAs, ordered by unique id.
+    pub fn iter(&self) -> impl Iterator<Item = &IkeSa> {
+        self.sas.values()
+    }
+
+    /// A copy of the current state, ordered by unique id.
+    pub fn snapshot(&self) -> Vec<IkeSa> {
+        self.sas.values().cloned().collect()
+    }
+
+    /// Number of tracked IKE_SAs.
+    pub fn len(&self) -> usize {
+        self.sas.len()
+    }
+
+    /// Whether no IKE_SA is tracked.
+    pub fn is_empty(&self) -> bool {
+        self.sas.is_empty()
+    }
+}
+
+/// Insert or refresh an IKE_SA. An update without CHILD_SAs keeps the ones
+/// already known.
+fn upsert(sas: &mut BTreeMap<u32, IkeSa>, mut ike: IkeSa) -> &mut IkeSa {
+    if let Some(prev) = sas.remove(&ike.uniqueid) {
+        if ike.child_sas.is_empty() {
+            ike.child_sas = prev.child_sas;
+        }
+    }
+    sas.entry(ike.uniqueid).or_insert(ike)
+}
+
+fn upsert_child(ike: &mut IkeSa, child: ChildSa) {
+    match ike
+        .child_sas
+        .iter_mut()
+        .find(|c| c.uniqueid == child.uniqueid)
+    {
+        Some(slot) => *slot = child,
+        None => ike.child_sas.push(child),
+    }
+}
+
+fn remove_child(ike: &mut IkeSa, uniqueid: u32) -> bool {
+    let before = ike.child_sas.len();
+    ike.child_sas.retain(|c| c.uniqueid != uniqueid);
+    ike.child_sas.len() != before
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ike(id: u32, children: &[u32]) -> IkeSa {
+        IkeSa {
+            name: "gw".into(),
+            uniqueid: id,
+            state: "ESTABLISHED".into(),
+            child_sas: children.iter().map(|&c| child(c)).collect(),
+            ..IkeSa::default()
+        }
+    }
+
+    fn child(id: u32) -> ChildSa {
+        ChildSa {
+            key: format!("net-{id}"),
+            name: "net".into(),
+            uniqueid: id,
+            state: "INSTALLED".into(),
+            ..ChildSa::default()
+        }
+    }
+
+    #[test]
+    fn lifecycle() {
+        let mut t = SaTracker::new();
+        t.seed(vec![ike(1, &[10])]);
+        assert!(t.apply(&Event::IkeUpdown {
+            up: true,
+            ike: ike(2, &[]),
+        }));
+        assert!(t.apply(&Event::ChildUpdown {
+            up: true,
+            ike: ike(2, &[20]),
+        }));
+        assert_eq!(t.len(), 2);
+        assert_eq!(t.get(2).unwrap().child_sas.len(), 1);
+
+        // child rekey swaps the CHILD_SA in place
+        t.apply(&Event::ChildRekey {
+            ike: ike(2, &[]),
+            old: child(20),
+            new: child(21),
+        });
+        assert!(t.get(2).unwrap().child(21).is_some());
+        assert!(t.get(2).unwrap().child(20).is_none());
+
+        // IKE rekey migrates children to the new IKE_SA
+        t.apply(&Event::IkeRekey {
+            old: ike(1, &[]),
+            new: ike(3, &[]),
+        });
+        assert!(t.get(1).is_none());
+        assert!(t.get(3).unwrap().child(10).is_some());
+
+        t.apply(&Event::IkeUpdate {
+            local_host: "192.0.2.9".into(),
+            local_port: 4500,
+            remote_host: "198.51.100.1".into(),
+            remote_port: 4500,
+            ike: ike(3, &[]),
+        });
+        assert_eq!(t.get(3).unwrap().local_host.as_deref(), Some("192.0.2.9"));
+        assert!(t.get(3).unwrap().child(10).is_some());
+
+        t.apply(&Event::ChildUpdown {
+            up: false,
+            ike: ike(2, &[21]),
+        });
+        assert!(t.get(2).unwrap().child_sas.is_empty());
+        t.apply(&Event::IkeUpdown {
+            up: false,
+            ike: ike(2, &[]),
+        });
+        assert_eq!(t.snapshot().len(), 1);
+
+        let changes: Vec<_> = t.changes().collect();
+        assert_eq!(
+            changes,
+            [
+                SaChange::Seeded,
+                SaChange::IkeUp(2),
+                SaChange::ChildUp { ike: 2, child: 20 },
+                SaChange::ChildRekeyed {
+                    ike: 2,
+                    old: 20,
+                    new: 21
+                },
+                SaChange::IkeRekeyed { old: 1, new: 3 },
+                SaChange::IkeUpdated(3),
+                SaChange::ChildDown { ike: 2, child: 21 },
+                SaChange::IkeDown(2),
+            ]
+        );
+        assert_eq!(t.changes().count(), 0);
+    }
+
+    #[test]
+    fn unrelated_events_are_ignored() {
+        let mut t = SaTracker::new();
+        let ev = Event::Unknown {
+            name: "list-sa".into(),
+            message: Message::new(),
+        };
+        assert!(!t.apply(&ev));
+        assert!(!t.apply(&Event::IkeUpdown {
+            up: false,
+            ike: ike(9, &[]),
+        }));
+    }
+}
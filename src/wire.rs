@@ -188,6 +188,234 @@ impl Message {
         }
         Ok(Self { elements })
     }
+
+    /// Whether this message has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Build the nested [`Section`] view of this message.
+    ///
+    /// Fails if sections or lists are unbalanced, or if list items or
+    /// section ends appear outside of their container.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rustici::Message;
+    ///
+    /// let msg = Message::new()
+    ///     .kv_str("success", "yes")
+    ///     .section_start("conn")
+    ///     .list_start("local_addrs")
+    ///     .list_item_str("10.0.0.1")
+    ///     .list_end()
+    ///     .section_end();
+    ///
+    /// let root = msg.tree().unwrap();
+    /// assert_eq!(root.str("success"), Some("yes"));
+    /// assert_eq!(root.section("conn").unwrap().str_list("local_addrs"), ["10.0.0.1"]);
+    /// ```
+    pub fn tree(&self) -> Result<Section> {
+        let mut stack: Vec<(String, Section)> = Vec::new();
+        let mut current = Section::new();
+        let mut list: Option<(String, Vec<Vec<u8>>)> = None;
+
+        for el in &self.elements {
+            if let Some((name, items)) = list.as_mut() {
+                match el {
+                    Element::ListItem(v) => items.push(v.clone()),
+                    Element::ListEnd => {
+                        let name = std::mem::take(name);
+                        let items = std::mem::take(items);
+                        current.entries.push((name, Value::List(items)));
+                        list = None;
+                    }
+                    _ => return Err(Error::Protocol("unterminated list")),
+                }
+                continue;
+            }
+            match el {
+                Element::SectionStart(name) => {
+                    let parent = std::mem::take(&mut current);
+                    stack.push((name.clone(), parent));
+                }
+                Element::SectionEnd => {
+                    let (name, parent) = stack
+                        .pop()
+                        .ok_or(Error::Protocol("section end without section"))?;
+                    let child = std::mem::replace(&mut current, parent);
+                    current.entries.push((name, Value::Section(child)));
+                }
+                Element::KeyValue(k, v) => {
+                    current.entries.push((k.clone(), Value::Bytes(v.clone())))
+                }
+                Element::ListStart(name) => list = Some((name.clone(), Vec::new())),
+                Element::ListItem(_) => return Err(Error::Protocol("list item outside of list")),
+                Element::ListEnd => return Err(Error::Protocol("list end without list")),
+            }
+        }
+        if list.is_some() {
+            return Err(Error::Protocol("unterminated list"));
+        }
+        if !stack.is_empty() {
+            return Err(Error::Protocol("unterminated section"));
+        }
+        Ok(current)
+    }
+}
+
+/// A value in the nested view of a [`Message`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    /// The raw value of a key/value pair.
+    Bytes(Vec<u8>),
+    /// The raw items of a list.
+    List(Vec<Vec<u8>>),
+    /// A nested section.
+    Section(Section),
+}
+
+/// An ordered set of named entries: either the message root or a nested section.
+///
+/// This is a read-side convenience on top of the flat element sequence;
+/// use [`Message::tree`] to obtain one and [`Section::to_message`] to go back.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Section {
+    entries: Vec<(String, Value)>,
+}
+
+impl Section {
+    /// Create an empty section.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Borrow all entries in wire order.
+    pub fn entries(&self) -> &[(String, Value)] {
+        &self.entries
+    }
+
+    /// Whether this section has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Append an entry (duplicate keys are kept, as on the wire).
+    pub fn insert(&mut self, key: impl Into<String>, value: Value) {
+        self.entries.push((key.into(), value));
+    }
+
+    /// Append a string key/value pair.
+    pub fn insert_str(&mut self, key: impl Into<String>, value: impl AsRef<str>) {
+        self.insert(key, Value::Bytes(value.as_ref().as_bytes().to_vec()));
+    }
+
+    /// Append a list of string items.
+    pub fn insert_str_list<I, S>(&mut self, key: impl Into<String>, items: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let items = items
+            .into_iter()
+            .map(|s| s.as_ref().as_bytes().to_vec())
+            .collect();
+        self.insert(key, Value::List(items));
+    }
+
+    /// First value stored under `key`.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entries.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    /// Raw bytes of the key/value pair `key`.
+    pub fn bytes(&self, key: &str) -> Option<&[u8]> {
+        match self.get(key)? {
+            Value::Bytes(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// The key/value pair `key` as a string, if it is valid UTF-8.
+    pub fn str(&self, key: &str) -> Option<&str> {
+        std::str::from_utf8(self.bytes(key)?).ok()
+    }
+
+    /// The key/value pair `key` parsed with [`std::str::FromStr`].
+    pub fn parse<T: std::str::FromStr>(&self, key: &str) -> Option<T> {
+        self.str(key)?.parse().ok()
+    }
+
+    /// The key/value pair `key` interpreted as a VICI boolean (`yes`/`no`).
+    pub fn flag(&self, key: &str) -> Option<bool> {
+        match self.str(key)? {
+            "yes" | "true" | "1" => Some(true),
+            "no" | "false" | "0" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Raw items of the list `key`.
+    pub fn list(&self, key: &str) -> Option<&[Vec<u8>]> {
+        match self.get(key)? {
+            Value::List(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Items of the list `key` as strings; missing lists yield an empty vector
+    /// and non-UTF-8 items are converted lossily.
+    pub fn str_list(&self, key: &str) -> Vec<String> {
+        self.list(key)
+            .unwrap_or_default()
+            .iter()
+            .map(|v| String::from_utf8_lossy(v).into_owned())
+            .collect()
+    }
+
+    /// The nested section `key`.
+    pub fn section(&self, key: &str) -> Option<&Section> {
+        match self.get(key)? {
+            Value::Section(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Iterate over all nested sections with their names.
+    pub fn sections(&self) -> impl Iterator<Item = (&str, &Section)> {
+        self.entries.iter().filter_map(|(k, v)| match v {
+            Value::Section(s) => Some((k.as_str(), s)),
+            _ => None,
+        })
+    }
+
+    /// Flatten this section back into a [`Message`].
+    pub fn to_message(&self) -> Message {
+        let mut msg = Message::new();
+        self.write_into(&mut msg);
+        msg
+    }
+
+    fn write_into(&self, msg: &mut Message) {
+        for (k, v) in &self.entries {
+            match v {
+                Value::Bytes(b) => msg.push(Element::KeyValue(k.clone(), b.clone())),
+                Value::List(items) => {
+                    msg.push(Element::ListStart(k.clone()));
+                    for item in items {
+                        msg.push(Element::ListItem(item.clone()));
+                    }
+                    msg.push(Element::ListEnd);
+                }
+                Value::Section(s) => {
+                    msg.push(Element::SectionStart(k.clone()));
+                    s.write_into(msg);
+                    msg.push(Element::SectionEnd);
+                }
+            }
+        }
+    }
 }
 
 fn encode_name(out: &mut Vec<u8>, name: &str) -> Result<()> {
@@ -270,8 +498,13 @@ fn decode_element(input: &[u8]) -> Result<(Element, &[u8])> {
     }
 }
 
+/// One line per element; the alternate form (`{:#}`) is an indented
+/// `name { key = value list = [a, b] }` notation closer to swanctl's.
 impl fmt::Display for Message {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return self.fmt_pretty(f);
+        }
         for el in &self.elements {
             match el {
                 Element::SectionStart(n) => writeln!(f, "<section {n}>")?,
@@ -292,6 +525,383 @@ impl fmt::Display for Message {
     }
 }
 
+impl Message {
+    fn fmt_pretty(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut depth = 0;
+        let mut in_list = None;
+        for el in &self.elements {
+            match el {
+                Element::SectionStart(n) => {
+                    let n = literal(n.as_bytes());
+                    writeln!(f, "{:indent$}{n} {{", "", indent = depth * 2)?;
+                    depth += 1;
+                }
+                Element::SectionEnd => {
+                    depth = depth.saturating_sub(1);
+                    writeln!(f, "{:indent$}}}", "", indent = depth * 2)?;
+                }
+                Element::KeyValue(k, v) => {
+                    let k = literal(k.as_bytes());
+                    writeln!(f, "{:indent$}{k} = {}", "", literal(v), indent = depth * 2)?
+                }
+                Element::ListStart(n) => {
+                    let n = literal(n.as_bytes());
+                    write!(f, "{:indent$}{n} = [", "", indent = depth * 2)?;
+                    in_list = Some(true);
+                }
+                Element::ListItem(v) => {
+                    if in_list == Some(false) {
+                        f.write_str(", ")?;
+                    }
+                    in_list = Some(false);
+                    f.write_str(&literal(v))?;
+                }
+                Element::ListEnd => {
+                    writeln!(f, "]")?;
+                    in_list = None;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Render as a JSON object: sections become objects, lists arrays and
+    /// values strings (non-UTF-8 values as `0x`-prefixed hex, as in
+    /// `Display`).
+    ///
+    /// A key repeated within a section becomes one array of all its values,
+    /// in order, at the position of its first occurrence.
+    pub fn to_json(&self) -> String {
+        let mut els = self.elements.iter();
+        let mut entries = Vec::new();
+        // A stray section end must not cut the root object short.
+        while els.len() > 0 {
+            json_entries(&mut els, &mut entries);
+        }
+        let mut out = String::new();
+        json_object(&mut out, &entries);
+        out
+    }
+}
+
+enum Json<'a> {
+    Value(&'a [u8]),
+    Object(Vec<(&'a str, Vec<Json<'a>>)>),
+    Array(Vec<&'a [u8]>),
+}
+
+/// Collect the entries of a section up to its end, merging repeated keys.
+fn json_entries<'a>(
+    els: &mut std::slice::Iter<'a, Element>,
+    entries: &mut Vec<(&'a str, Vec<Json<'a>>)>,
+) {
+    while let Some(el) = els.next() {
+        let (key, value) = match el {
+            Element::SectionStart(n) => {
+                let mut section = Vec::new();
+                json_entries(els, &mut section);
+                (n, Json::Object(section))
+            }
+            Element::ListStart(n) => {
+                let mut items = Vec::new();
+                for el in els.by_ref() {
+                    match el {
+                        Element::ListItem(v) => items.push(v.as_slice()),
+                        _ => break,
+                    }
+                }
+                (n, Json::Array(items))
+            }
+            Element::KeyValue(k, v) => (k, Json::Value(v)),
+            Element::SectionEnd => return,
+            Element::ListItem(_) | Element::ListEnd => continue,
+        };
+        match entries.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, values)) => values.push(value),
+            None => entries.push((key, vec![value])),
+        }
+    }
+}
+
+fn json_object(out: &mut String, entries: &[(&str, Vec<Json<'_>>)]) {
+    out.push('{');
+    for (i, (key, values)) in entries.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        json_string(out, key);
+        out.push(':');
+        match values.as_slice() {
+            [value] => json_node(out, value),
+            values => {
+                out.push('[');
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    json_node(out, value);
+                }
+                out.push(']');
+            }
+        }
+    }
+    out.push('}');
+}
+
+fn json_node(out: &mut String, node: &Json<'_>) {
+    match node {
+        Json::Value(v) => json_value(out, v),
+        Json::Object(entries) => json_object(out, entries),
+        Json::Array(items) => {
+            out.push('[');
+            for (i, v) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                json_value(out, v);
+            }
+            out.push(']');
+        }
+    }
+}
+
+/// Parses the notation of the alternate `Display` form, e.g.
+/// `conn { version = 2 local_addrs = [10.0.0.1, 10.0.0.2] }`.
+///
+/// Entries may be separated by whitespace or newlines and list items by
+/// commas or whitespace. Names are bare words or `"quoted strings"`; values
+/// may also be `0x`-prefixed hex for binary data. `#` starts a comment.
+impl std::str::FromStr for Message {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parser = LiteralParser {
+            input: s.as_bytes(),
+            pos: 0,
+        };
+        let mut msg = Message::new();
+        if parser.entries(&mut msg)? {
+            return Err(parser.error_at(parser.pos - 1, "unbalanced `}`"));
+        }
+        Ok(msg)
+    }
+}
+
+struct LiteralParser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl LiteralParser<'_> {
+    /// A [`Error::Literal`] for byte `offset` of the input.
+    fn error_at(&self, offset: usize, what: &str) -> Error {
+        Error::Literal(format!("byte {offset}: {what}"))
+    }
+
+    /// A [`Error::Literal`] for the current position, naming what is there.
+    fn error(&self, what: &str) -> Error {
+        let found = match self.input.get(self.pos) {
+            Some(&c) => format!("`{}`", c as char),
+            None => "end of input".to_string(),
+        };
+        self.error_at(self.pos, &format!("{what}, found {found}"))
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        // Skip whitespace and comments before every token.
+        while let Some(&c) = self.input.get(self.pos) {
+            if c == b'#' {
+                while !matches!(self.input.get(self.pos), None | Some(b'\n')) {
+                    self.pos += 1;
+                }
+            } else if c.is_ascii_whitespace() {
+                self.pos += 1;
+            } else {
+                return Some(c);
+            }
+        }
+        None
+    }
+
+    /// Parse entries into `msg` until `}` (returns true) or the end (false).
+    fn entries(&mut self, msg: &mut Message) -> Result<bool> {
+        loop {
+            match self.peek() {
+                None => return Ok(false),
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(true);
+                }
+                _ => {}
+            }
+            let name = self.word()?;
+            match self.peek() {
+                Some(b'{') => {
+                    let start = self.pos;
+                    self.pos += 1;
+                    msg.push(Element::SectionStart(name));
+                    if !self.entries(msg)? {
+                        return Err(self.error_at(start, "unterminated section"));
+                    }
+                    msg.push(Element::SectionEnd);
+                }
+                Some(b'=') => {
+                    self.pos += 1;
+                    if self.peek() == Some(b'[') {
+                        let start = self.pos;
+                        self.pos += 1;
+                        msg.push(Element::ListStart(name));
+                        loop {
+                            match self.peek() {
+                                Some(b']') => break,
+                                Some(b',') => self.pos += 1,
+                                Some(_) => {
+                                    let item = self.value()?;
+                                    msg.push(Element::ListItem(item));
+                                }
+                                None => return Err(self.error_at(start, "unterminated list")),
+                            }
+                        }
+                        self.pos += 1;
+                        msg.push(Element::ListEnd);
+                    } else {
+                        let value = self.value()?;
+                        msg.push(Element::KeyValue(name, value));
+                    }
+                }
+                _ => return Err(self.error("expected `{` or `=`")),
+            }
+        }
+    }
+
+    fn bare(&mut self) -> &[u8] {
+        let start = self.pos;
+        while let Some(&c) = self.input.get(self.pos) {
+            if c.is_ascii_whitespace() || b"{}[],=\"'#".contains(&c) {
+                break;
+            }
+            self.pos += 1;
+        }
+        &self.input[start..self.pos]
+    }
+
+    /// A bare or quoted name.
+    fn word(&mut self) -> Result<String> {
+        if self.peek() == Some(b'"') {
+            return Ok(String::from_utf8(self.value()?)?);
+        }
+        let word = self.bare();
+        if word.is_empty() {
+            return Err(self.error("expected a name"));
+        }
+        Ok(String::from_utf8(word.to_vec())?)
+    }
+
+    fn value(&mut self) -> Result<Vec<u8>> {
+        if self.peek() == Some(b'"') {
+            let start = self.pos;
+            self.pos += 1;
+            let mut out = Vec::new();
+            loop {
+                let c = *self
+                    .input
+                    .get(self.pos)
+                    .ok_or_else(|| self.error_at(start, "unterminated string"))?;
+                self.pos += 1;
+                match c {
+                    b'"' => return Ok(out),
+                    b'\\' => {
+                        let c = *self
+                            .input
+                            .get(self.pos)
+                            .ok_or_else(|| self.error_at(start, "unterminated string"))?;
+                        self.pos += 1;
+                        out.push(match c {
+                            b'n' => b'\n',
+                            b't' => b'\t',
+                            b'r' => b'\r',
+                            c => c,
+                        });
+                    }
+                    c => out.push(c),
+                }
+            }
+        }
+        let start = self.pos;
+        if self.bare().is_empty() {
+            return Err(self.error("expected a value"));
+        }
+        let word = &self.input[start..self.pos];
+        match word.strip_prefix(b"0x") {
+            Some(hex) if hex.len() % 2 == 0 => hex
+                .chunks(2)
+                .map(|pair| {
+                    std::str::from_utf8(pair)
+                        .ok()
+                        .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                        .ok_or_else(|| self.error_at(start, "invalid hex value"))
+                })
+                .collect(),
+            Some(_) => Err(self.error_at(start, "odd-length hex value")),
+            None => Ok(word.to_vec()),
+        }
+    }
+}
+
+/// A value as it appears in the pretty notation: bare if unambiguous,
+/// otherwise quoted, or `0x`-prefixed hex if it is not UTF-8.
+fn literal(v: &[u8]) -> String {
+    let s = match std::str::from_utf8(v) {
+        Ok(s) => s,
+        Err(_) => return format!("0x{}", hex(v)),
+    };
+    let bare = !s.is_empty()
+        && !s.starts_with("0x")
+        && !s
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || "{}[],=\"'#".contains(c));
+    if bare {
+        return s.to_string();
+    }
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn json_value(out: &mut String, v: &[u8]) {
+    match std::str::from_utf8(v) {
+        Ok(s) => json_string(out, s),
+        Err(_) => json_string(out, &format!("0x{}", hex(v))),
+    }
+}
+
+pub(crate) fn json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
 fn hex(bytes: &[u8]) -> String {
     const HEX: &[u8; 16] = b"0123456789abcdef";
     let mut s = String::with_capacity(bytes.len() * 2);
@@ -321,4 +931,144 @@ mod tests {
         let decoded = Message::decode(&encoded).unwrap();
         assert_eq!(msg, decoded);
     }
+
+    #[test]
+    fn tree_roundtrip() {
+        let msg = Message::new()
+            .kv_str("a", "1")
+            .section_start("outer")
+            .section_start("inner")
+            .kv_bytes("bin", [0xff, 0x00])
+            .section_end()
+            .list_start("l")
+            .list_item_str("x")
+            .list_end()
+            .section_end();
+
+        let tree = msg.tree().unwrap();
+        assert_eq!(tree.parse::<u32>("a"), Some(1));
+        let outer = tree.section("outer").unwrap();
+        assert_eq!(
+            outer.section("inner").unwrap().bytes("bin"),
+            Some(&[0xff, 0x00][..])
+        );
+        assert_eq!(outer.str_list("l"), ["x"]);
+        assert_eq!(tree.to_message(), msg);
+    }
+
+    #[test]
+    fn tree_rejects_unbalanced() {
+        assert!(Message::new().section_start("s").tree().is_err());
+        assert!(Message::new().section_end().tree().is_err());
+        assert!(Message::new().list_start("l").tree().is_err());
+        assert!(Message::new().list_item_str("x").tree().is_err());
+    }
+
+    #[test]
+    fn pretty_and_json_rendering() {
+        let msg = Message::new()
+            .kv_str("success", "yes")
+            .section_start("gw")
+            .kv_str("remote-id", "CN=moon, O=strongSwan")
+            .list_start("local_addrs")
+            .list_item_str("10.0.0.1")
+            .list_item_str("10.0.0.2")
+            .list_end()
+            .kv_bytes("spi", [0xc0, 0xff])
+            .section_end();
+
+        assert_eq!(
+            format!("{msg:#}"),
+            "success = yes\n\
+             gw {\n\
+             \x20 remote-id = \"CN=moon, O=strongSwan\"\n\
+             \x20 local_addrs = [10.0.0.1, 10.0.0.2]\n\
+             \x20 spi = 0xc0ff\n\
+             }\n"
+        );
+        assert_eq!(
+            msg.to_json(),
+            r#"{"success":"yes","gw":{"remote-id":"CN=moon, O=strongSwan","local_addrs":["10.0.0.1","10.0.0.2"],"spi":"0xc0ff"}}"#
+        );
+        assert_eq!(Message::new().to_json(), "{}");
+
+        // Repeated keys are merged into arrays, keeping the JSON valid.
+        let msg: Message = "a = 1 s { x = 1 } a = 2 s { x = 2 } b = []"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            msg.to_json(),
+            r#"{"a":["1","2"],"s":[{"x":"1"},{"x":"2"}],"b":[]}"#
+        );
+    }
+
+    #[test]
+    fn parse_literal_notation() {
+        let msg: Message = r#"
+            # a connection
+            conn { version=2 local_addrs=[10.0.0.1 10.0.0.2,10.0.0.3]
+                remote { id = "CN=moon, O=strongSwan" }
+            }
+            spi = 0xc0ff
+            empty = []
+        "#
+        .parse()
+        .unwrap();
+        let tree = msg.tree().unwrap();
+        let conn = tree.section("conn").unwrap();
+        assert_eq!(conn.str("version"), Some("2"));
+        assert_eq!(
+            conn.str_list("local_addrs"),
+            ["10.0.0.1", "10.0.0.2", "10.0.0.3"]
+        );
+        assert_eq!(
+            conn.section("remote").unwrap().str("id"),
+            Some("CN=moon, O=strongSwan")
+        );
+        assert_eq!(tree.bytes("spi"), Some(&[0xc0, 0xff][..]));
+        assert!(tree.list("empty").unwrap().is_empty());
+
+        // The alternate Display form parses back to the same message.
+        let odd = Message::new()
+            .kv_str("quoted", "a \"b\" \\ c\n")
+            .kv_str("hexlike", "0x12")
+            .kv_str("blank", "")
+            .kv_bytes("bin", [0xff, 0x00]);
+        // Names with separators, as certificate subjects used as IDs have.
+        let names = Message::new()
+            .section_start("CN=moon, O=org")
+            .kv_str("0x1", "a")
+            .list_start("a b")
+            .list_item_str("c")
+            .list_end()
+            .section_end()
+            .section_start("")
+            .section_end();
+        assert!(format!("{names:#}").starts_with("\"CN=moon, O=org\" {\n"));
+        let full = msg.clone();
+        for m in [full, odd, names] {
+            assert_eq!(format!("{m:#}").parse::<Message>().unwrap(), m);
+        }
+
+        for bad in ["a", "a {", "}", "a = [b", "a = 0xabc", "a = \"b", "= b"] {
+            assert!(bad.parse::<Message>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn literal_errors_report_offset() {
+        let err = |s: &str| match s.parse::<Message>() {
+            Err(Error::Literal(msg)) => msg,
+            other => panic!("{s}: {other:?}"),
+        };
+        assert_eq!(
+            err("conn { version = 2 local_addrs 10.0.0.1 }"),
+            "byte 31: expected `{` or `=`, found `1`"
+        );
+        assert_eq!(err("a = 1 }"), "byte 6: unbalanced `}`");
+        assert_eq!(err("a { b = [c"), "byte 8: unterminated list");
+        assert_eq!(err("a = 0xabc"), "byte 4: odd-length hex value");
+        assert_eq!(err("a ="), "byte 3: expected a value, found end of input");
+        assert_eq!(err("= b"), "byte 0: expected a name, found `=`");
+    }
 }
@@ -0,0 +1,83 @@
+//! Integration tests for `ClientBuilder` options.
+
+mod common;
+
+use common::{listen, read_frame, write_frame};
+use rustici::{error::Error, Client, Message};
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+/// Answer each command with an `ike-updown` event followed by a response
+/// holding `pad` bytes of padding.
+fn serve(name: &str, pad: usize) -> (PathBuf, thread::JoinHandle<()>) {
+    let (socket_path, listener) = listen(name);
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        while read_frame(&mut stream).is_ok() {
+            let mut event = vec![7, 10];
+            event.extend_from_slice(b"ike-updown");
+            event.extend_from_slice(&[3, 2, b'u', b'p', 0, 3, b'y', b'e', b's']);
+            write_frame(&mut stream, &event);
+
+            let mut resp = vec![1, 3, 3, b'p', b'a', b'd'];
+            resp.extend_from_slice(&(pad as u16).to_be_bytes());
+            resp.resize(resp.len() + pad, b'x');
+            write_frame(&mut stream, &resp);
+        }
+    });
+    (socket_path, server)
+}
+
+#[test]
+fn event_buffer_keeps_events_received_during_calls() {
+    let (socket_path, server) = serve("builder-buffer", 0);
+    let mut client = Client::builder()
+        .path(&socket_path)
+        .read_timeout(Some(Duration::from_secs(2)))
+        .event_buffer(1)
+        .build()
+        .unwrap();
+
+    client.call("version", &Message::new()).unwrap();
+    client.call("version", &Message::new()).unwrap();
+    // The buffer holds one event; the older one was dropped.
+    assert_eq!(client.dropped_events(), 1);
+    let (name, _) = client.try_next_event(Duration::from_millis(50)).unwrap();
+    assert_eq!(name, "ike-updown");
+    assert!(matches!(
+        client.try_next_event(Duration::from_millis(50)),
+        Err(Error::Timeout)
+    ));
+
+    drop(client);
+    server.join().unwrap();
+    let _ = std::fs::remove_file(&socket_path);
+}
+
+#[test]
+fn max_frame_size_is_enforced() {
+    let (socket_path, server) = serve("builder-frame", 1024);
+    let mut client = Client::builder()
+        .uri(&format!("unix://{}", socket_path.display()))
+        .unwrap()
+        .max_frame_size(512)
+        .build()
+        .unwrap();
+
+    let big = Message::new().kv_str("data", "x".repeat(600));
+    assert!(matches!(
+        client.call("version", &big),
+        Err(Error::TooLong("packet"))
+    ));
+
+    assert!(matches!(
+        client.call("version", &Message::new()),
+        Err(Error::TooLong("frame"))
+    ));
+    assert!(client.is_poisoned());
+
+    drop(client);
+    server.join().unwrap();
+    let _ = std::fs::remove_file(&socket_path);
+}
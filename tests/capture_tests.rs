@@ -0,0 +1,72 @@
+//! Record a session against `MockServer` and replay it with `Replayer`.
+#![cfg(feature = "testing")]
+
+use rustici::capture::{CaptureReader, Direction, Recorder, Replayer};
+use rustici::testing::{MockServer, Reply};
+use rustici::{error::Error, Message};
+
+fn record_session(path: &std::path::Path) {
+    let server = MockServer::start().unwrap();
+    server.on_command("version", |_| {
+        Reply::Response(Message::new().kv_str("daemon", "charon"))
+    });
+    server.on_command("list-sas", |_| Reply::Stream {
+        event: "list-sa".into(),
+        events: vec![Message::new()
+            .section_start("gw")
+            .kv_str("uniqueid", "1")
+            .section_end()],
+        response: Message::new(),
+    });
+
+    let mut client = server.connect().unwrap();
+    client.record(Recorder::create(path).unwrap());
+    client.call("version", &Message::new()).unwrap();
+    assert_eq!(client.list_sas(None).unwrap().len(), 1);
+    client.stop_recording().unwrap().finish().unwrap();
+}
+
+#[test]
+fn replays_recorded_session() {
+    let path = std::env::temp_dir().join(format!("rustici-{}.vicicap", std::process::id()));
+    record_session(&path);
+
+    let records: Vec<_> = CaptureReader::open(&path)
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    // version, register list-sa, list-sas, unregister list-sa.
+    let sent = records
+        .iter()
+        .filter(|r| r.direction == Direction::Sent)
+        .count();
+    assert_eq!(sent, 4);
+    assert_eq!(
+        records[0].packet().unwrap().name.as_deref(),
+        Some("version")
+    );
+
+    let replay = Replayer::open(&path).unwrap().spawn().unwrap();
+    let mut client = replay.connect().unwrap();
+    let resp = client.call("version", &Message::new()).unwrap();
+    assert_eq!(resp.tree().unwrap().str("daemon"), Some("charon"));
+    let sas = client.list_sas(None).unwrap();
+    assert_eq!(sas[0].name, "gw");
+    drop(client);
+    replay.finish().unwrap();
+
+    // A client that deviates from the capture is detected.
+    let replay = Replayer::new(records).spawn().unwrap();
+    let mut client = replay.connect().unwrap();
+    assert!(client.call("stats", &Message::new()).is_err());
+    assert!(matches!(replay.finish(), Err(Error::Protocol(_))));
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn serve_leaves_live_socket_alone() {
+    let server = MockServer::start().unwrap();
+    let err = Replayer::new(Vec::new()).serve(server.path()).unwrap_err();
+    assert!(matches!(err, Error::Io(e) if e.kind() == std::io::ErrorKind::AddrInUse));
+    assert!(server.connect().is_ok());
+}
@@ -0,0 +1,90 @@
+//! Run the `rustici` binary against a `MockServer`.
+#![cfg(all(feature = "cli", feature = "testing"))]
+
+use rustici::testing::{MockServer, Reply};
+use rustici::Message;
+use std::process::Command;
+
+fn rustici(server: &MockServer, args: &[&str]) -> (bool, String) {
+    let uri = format!("unix://{}", server.path().display());
+    let out = Command::new(env!("CARGO_BIN_EXE_rustici"))
+        .args(["--uri", &uri])
+        .args(args)
+        .output()
+        .unwrap();
+    (out.status.success(), String::from_utf8(out.stdout).unwrap())
+}
+
+#[test]
+fn lists_sas_in_all_formats() {
+    let server = MockServer::start().unwrap();
+    server.on_command("list-sas", |_| Reply::Stream {
+        event: "list-sa".into(),
+        events: vec![Message::new()
+            .section_start("gw")
+            .kv_str("state", "ESTABLISHED")
+            .section_end()],
+        response: Message::new(),
+    });
+
+    let (ok, out) = rustici(&server, &["--list-sas"]);
+    assert!(ok);
+    assert!(out.starts_with("list-sa event {\n  gw {\n    state = ESTABLISHED\n  }\n}\n"));
+
+    let (_, out) = rustici(&server, &["-l", "--raw"]);
+    assert!(out.starts_with("list-sa event {gw { state = ESTABLISHED }}\n"));
+
+    let (_, out) = rustici(&server, &["-l", "--json"]);
+    assert_eq!(
+        out.lines().next(),
+        Some(r#"{"type":"event","name":"list-sa","message":{"gw":{"state":"ESTABLISHED"}}}"#)
+    );
+}
+
+#[test]
+fn failures_set_the_exit_status() {
+    let server = MockServer::start().unwrap();
+    server.on_command("initiate", |_| {
+        Reply::Response(
+            Message::new()
+                .kv_str("success", "no")
+                .kv_str("errmsg", "no config named 'gw'"),
+        )
+    });
+    let (ok, out) = rustici(&server, &["--initiate", "--child", "gw", "--json"]);
+    assert!(!ok);
+    assert!(out.contains(r#""errmsg":"no config named 'gw'""#));
+    let req = server.requests()[0].1.tree().unwrap();
+    assert_eq!(req.str("child"), Some("gw"));
+
+    let (ok, _) = rustici(&server, &["--no-such-option"]);
+    assert!(!ok);
+}
+
+#[test]
+fn raw_tool_sends_message_literals() {
+    let server = MockServer::start().unwrap();
+    server.on_command("load-conn", |_| {
+        Reply::Response(Message::new().kv_str("success", "yes"))
+    });
+    let uri = format!("unix://{}", server.path().display());
+    let out = Command::new(env!("CARGO_BIN_EXE_rustici-raw"))
+        .args([
+            "-u",
+            &uri,
+            "load-conn",
+            "conn { version=2 local_addrs=[10.0.0.1] }",
+        ])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    assert_eq!(
+        String::from_utf8(out.stdout).unwrap(),
+        "load-conn reply {\n  success = yes\n}\n"
+    );
+    let (_, req) = &server.requests()[0];
+    let conn = req.tree().unwrap();
+    let conn = conn.section("conn").unwrap();
+    assert_eq!(conn.str("version"), Some("2"));
+    assert_eq!(conn.str_list("local_addrs"), ["10.0.0.1"]);
+}
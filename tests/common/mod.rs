@@ -0,0 +1,34 @@
+//! Raw VICI framing shared by the integration tests that play the daemon
+//! themselves.
+#![allow(dead_code)]
+
+use std::io::{Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+
+/// Bind a listener on a socket path private to this process and test.
+pub fn listen(name: &str) -> (PathBuf, UnixListener) {
+    let path = std::env::temp_dir().join(format!("rustici-{name}-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).unwrap();
+    (path, listener)
+}
+
+pub fn read_frame(stream: &mut UnixStream) -> std::io::Result<Vec<u8>> {
+    let mut len = [0u8; 4];
+    stream.read_exact(&mut len)?;
+    let mut data = vec![0u8; u32::from_be_bytes(len) as usize];
+    stream.read_exact(&mut data)?;
+    Ok(data)
+}
+
+pub fn write_frame(stream: &mut UnixStream, data: &[u8]) {
+    stream.write_all(&frame(data)).unwrap();
+}
+
+/// `data` prefixed with its length.
+pub fn frame(data: &[u8]) -> Vec<u8> {
+    let mut frame = (data.len() as u32).to_be_bytes().to_vec();
+    frame.extend_from_slice(data);
+    frame
+}
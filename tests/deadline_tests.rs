@@ -0,0 +1,121 @@
+//! Integration tests for per-call deadlines and cancellation.
+//!
+//! The fake daemon answers every command after a delay taken from the
+//! command name (`sleep-<ms>`), replying with `cmd = <name>` so tests can
+//! tell which reply they got.
+
+mod common;
+
+use common::{frame, listen, read_frame};
+use rustici::client::{CallOptions, CancelToken};
+use rustici::{error::Error, Client, Message};
+use std::io::Write;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn serve(name: &str) -> (PathBuf, thread::JoinHandle<()>) {
+    let (socket_path, listener) = listen(name);
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        while let Ok(pkt) = read_frame(&mut stream) {
+            assert_eq!(pkt[0], 0, "expected CmdRequest");
+            let name = String::from_utf8(pkt[2..2 + pkt[1] as usize].to_vec()).unwrap();
+            let delay = name
+                .strip_prefix("sleep-")
+                .map_or(0, |ms| ms.parse().unwrap());
+            thread::sleep(Duration::from_millis(delay));
+
+            // CmdResponse carrying `cmd = <name>`
+            let mut resp = vec![1, 3, 3, b'c', b'm', b'd'];
+            resp.extend_from_slice(&(name.len() as u16).to_be_bytes());
+            resp.extend_from_slice(name.as_bytes());
+            // Split the frame to exercise frame reassembly.
+            let frame = frame(&resp);
+            let (head, tail) = frame.split_at(3);
+            stream.write_all(head).unwrap();
+            thread::sleep(Duration::from_millis(5));
+            stream.write_all(tail).unwrap();
+        }
+    });
+    (socket_path, server)
+}
+
+fn cmd(resp: &Message) -> String {
+    resp.tree().unwrap().str("cmd").unwrap().to_string()
+}
+
+#[test]
+fn deadline_expires_and_connection_resyncs() {
+    let (socket_path, server) = serve("deadline");
+    let mut client = Client::connect(&socket_path).unwrap();
+
+    let start = Instant::now();
+    let deadline = start + Duration::from_millis(100);
+    let res = client.call_with_deadline("sleep-300", &Message::new(), deadline);
+    assert!(matches!(res, Err(Error::Timeout)), "got {res:?}");
+    assert!(start.elapsed() < Duration::from_millis(250));
+
+    // The late reply to sleep-300 must not be mistaken for this one.
+    let resp = client.call("fast", &Message::new()).unwrap();
+    assert_eq!(cmd(&resp), "fast");
+    assert!(!client.is_poisoned());
+
+    drop(client);
+    server.join().unwrap();
+    let _ = std::fs::remove_file(&socket_path);
+}
+
+#[test]
+fn cancel_from_another_thread() {
+    let (socket_path, server) = serve("cancel");
+    let mut client = Client::connect(&socket_path).unwrap();
+
+    let token = CancelToken::new();
+    let remote = token.clone();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        remote.cancel();
+    });
+
+    let start = Instant::now();
+    let opts = CallOptions::new().cancel(token);
+    let res = client.call_with("sleep-300", &Message::new(), &opts);
+    assert!(matches!(res, Err(Error::Cancelled)), "got {res:?}");
+    assert!(start.elapsed() < Duration::from_millis(250));
+
+    let resp = client
+        .call_with(
+            "fast",
+            &Message::new(),
+            &CallOptions::new().timeout(Duration::from_secs(2)),
+        )
+        .unwrap();
+    assert_eq!(cmd(&resp), "fast");
+
+    drop(client);
+    server.join().unwrap();
+    let _ = std::fs::remove_file(&socket_path);
+}
+
+#[test]
+fn cancelled_token_fails_before_sending() {
+    let (socket_path, server) = serve("precancel");
+    let mut client = Client::connect(&socket_path).unwrap();
+
+    let token = CancelToken::new();
+    token.cancel();
+    let opts = CallOptions::new().cancel(token);
+    assert!(matches!(
+        client.call_with("fast", &Message::new(), &opts),
+        Err(Error::Cancelled)
+    ));
+
+    // Nothing was sent, so no reply is owed.
+    let resp = client.call("second", &Message::new()).unwrap();
+    assert_eq!(cmd(&resp), "second");
+
+    drop(client);
+    server.join().unwrap();
+    let _ = std::fs::remove_file(&socket_path);
+}
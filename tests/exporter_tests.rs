@@ -0,0 +1,121 @@
+//! Scrape `Exporter` backed by a `MockServer`.
+#![cfg(feature = "testing")]
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rustici::exporter::{collect, Exporter};
+use rustici::testing::{MockServer, Reply};
+use rustici::{Client, Message};
+
+fn mock() -> MockServer {
+    let server = MockServer::start().unwrap();
+    server.on_command("stats", |_| {
+        Reply::Response(
+            "workers { total = 16 idle = 12 active { critical = 4 high = 0 medium = 0 low = 0 } }
+             queues { critical = 0 high = 0 medium = 1 low = 0 }
+             scheduled = 2
+             ikesas { total = 2 half-open = 0 }"
+                .parse()
+                .unwrap(),
+        )
+    });
+    server.on_command("get-counters", |_| {
+        Reply::Response(
+            Message::new()
+                .kv_str("success", "yes")
+                .section_start("counters")
+                .section_start("")
+                .kv_str("ike-rekey-init", "7")
+                .section_end()
+                .section_end(),
+        )
+    });
+    server.on_command("list-sas", |_| Reply::Stream {
+        event: "list-sa".into(),
+        events: vec![
+            "gw { uniqueid = 1 state = ESTABLISHED child-sas { net-1 { name = net uniqueid = 3 \
+             state = INSTALLED bytes-in = 100 packets-in = 2 bytes-out = 50 packets-out = 1 } } }"
+                .parse()
+                .unwrap(),
+            "rw { uniqueid = 2 state = CONNECTING }".parse().unwrap(),
+        ],
+        response: Message::new(),
+    });
+    server.on_command("get-pools", |_| {
+        Reply::Response(
+            "rw { base = 10.3.0.0 size = 4 online = 1 offline = 1 }"
+                .parse()
+                .unwrap(),
+        )
+    });
+    server
+}
+
+#[test]
+fn collects_all_sources() {
+    let server = mock();
+    let text = collect(&mut server.connect().unwrap()).unwrap();
+    for line in [
+        "strongswan_workers 16\n",
+        "strongswan_workers_active{priority=\"critical\"} 4\n",
+        "strongswan_queued_jobs{priority=\"medium\"} 1\n",
+        "strongswan_ike_messages_total{counter=\"ike-rekey-init\"} 7\n",
+        "strongswan_ike_sas{state=\"ESTABLISHED\"} 1\n",
+        "strongswan_ike_sas{state=\"CONNECTING\"} 1\n",
+        "strongswan_child_sa_bytes_total{ike=\"gw\",child=\"net\",child_id=\"3\",direction=\"in\"} 100\n",
+        "strongswan_child_sa_packets_total{ike=\"gw\",child=\"net\",child_id=\"3\",direction=\"out\"} 1\n",
+        "strongswan_pool_leases{pool=\"rw\",status=\"offline\"} 1\n",
+        "strongswan_pool_utilization{pool=\"rw\"} 0.25\n",
+    ] {
+        assert!(text.contains(line), "missing {line:?} in\n{text}");
+    }
+}
+
+fn get(exporter: &Exporter, path: &str) -> String {
+    let mut stream = TcpStream::connect(exporter.local_addr()).unwrap();
+    write!(stream, "GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+    let mut resp = String::new();
+    stream.read_to_string(&mut resp).unwrap();
+    resp
+}
+
+fn wait_for(exporter: &Exporter, line: &str) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !exporter.metrics().contains(line) {
+        assert!(Instant::now() < deadline, "never saw {line:?}");
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+#[test]
+fn serves_metrics_over_http() {
+    let server = mock();
+    let builder = Client::builder().path(server.path());
+    let exporter = Exporter::bind("127.0.0.1:0", builder, Duration::from_millis(50)).unwrap();
+    assert!(exporter.metrics().contains("strongswan_up 0\n"));
+    let runner = {
+        let exporter = exporter.clone();
+        thread::spawn(move || exporter.run())
+    };
+
+    wait_for(&exporter, "strongswan_up 1");
+    // A client that never sends its request does not hold up others.
+    let _idle = TcpStream::connect(exporter.local_addr()).unwrap();
+    let start = Instant::now();
+    let resp = get(&exporter, "/metrics");
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert!(resp.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(resp.contains("strongswan_pool_size{pool=\"rw\"} 4\n"));
+    assert!(get(&exporter, "/nope").starts_with("HTTP/1.1 404"));
+
+    // Failures are reported through strongswan_up, and recovered from.
+    server.disconnect_all();
+    wait_for(&exporter, "strongswan_up 0");
+    wait_for(&exporter, "strongswan_up 1");
+
+    exporter.shutdown();
+    runner.join().unwrap().unwrap();
+}
@@ -0,0 +1,57 @@
+//! Log `MockServer` events as JSON lines across a simulated restart.
+#![cfg(feature = "testing")]
+
+use std::fs;
+use std::time::Duration;
+
+use rustici::jsonlog::{EventLogger, JsonLinesWriter};
+use rustici::reconnect::Backoff;
+use rustici::testing::MockServer;
+use rustici::{Client, Message};
+
+#[test]
+fn logs_rotates_and_survives_restarts() {
+    let server = MockServer::start().unwrap();
+    let dir = std::env::temp_dir().join(format!("rustici-jsonlog-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("events.jsonl");
+
+    let client = Client::builder()
+        .path(server.path())
+        .reconnect(Backoff::new().initial(Duration::from_millis(10)))
+        .build_reconnecting()
+        .unwrap();
+    let writer = JsonLinesWriter::file(&path).unwrap().rotate(200, 2);
+    let mut logger = EventLogger::new(client, &["ike-updown", "log"], writer).unwrap();
+
+    let updown = Message::new()
+        .kv_str("up", "yes")
+        .section_start("gw")
+        .kv_str("uniqueid", "1")
+        .section_end();
+    assert_eq!(server.emit("ike-updown", &updown), 1);
+    logger.log_next().unwrap();
+    server.emit("log", &Message::new().kv_str("msg", "say \"hi\""));
+    logger.log_next().unwrap();
+
+    server.disconnect_all();
+    logger.log_next().unwrap();
+    assert!(server.is_registered("log"));
+    server.emit("log", &Message::new().kv_str("msg", "back"));
+    logger.log_next().unwrap();
+
+    // 4 lines of ~100 bytes rotated at 200 bytes: 2 files of 2 lines each.
+    let current = fs::read_to_string(&path).unwrap();
+    let rotated = fs::read_to_string(dir.join("events.jsonl.1")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    let lines: Vec<&str> = rotated.lines().chain(current.lines()).collect();
+    assert_eq!(lines.len(), 4, "{lines:?}");
+    assert!(lines[0].starts_with("{\"time\":\""));
+    assert!(lines[0].ends_with(
+        "\"event\":\"ike-updown\",\"message\":{\"up\":\"yes\",\"gw\":{\"uniqueid\":\"1\"}}}"
+    ));
+    assert!(lines[1].ends_with("\"event\":\"log\",\"message\":{\"msg\":\"say \\\"hi\\\"\"}}"));
+    assert!(lines[2].ends_with("\"event\":\"reconnected\",\"attempts\":1}"));
+    assert!(lines[3].contains("\"msg\":\"back\""));
+}
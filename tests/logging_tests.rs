@@ -0,0 +1,124 @@
+//! `LogSubscriber` and `LogForwarder` against a `MockServer` emitting logs.
+#![cfg(feature = "testing")]
+
+use rustici::logging::LogFilter;
+use rustici::testing::MockServer;
+use rustici::Message;
+use std::time::Duration;
+
+fn log(group: &str, level: i32, msg: &str) -> Message {
+    Message::new()
+        .kv_str("group", group)
+        .kv_str("level", level.to_string())
+        .kv_str("ikesa-name", "gw")
+        .kv_str("ikesa-uniqueid", "1")
+        .kv_str("msg", msg)
+}
+
+#[test]
+fn subscriber_filters_and_unregisters() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.connect().unwrap();
+    {
+        let mut logs = client
+            .subscribe_logs(LogFilter::new().group("IKE").max_level(1))
+            .unwrap();
+        assert!(server.is_registered("log"));
+        server.emit("log", &log("cfg", 0, "loaded"));
+        server.emit("log", &log("ike", 2, "too verbose"));
+        server.emit("log", &log("ike", 1, "establishing"));
+        let rec = logs.try_next_record(Duration::from_secs(2)).unwrap();
+        assert_eq!(
+            (rec.group.as_str(), rec.msg.as_str()),
+            ("ike", "establishing")
+        );
+    }
+    assert!(!server.is_registered("log"));
+
+    // A registration made by the caller outlives the subscriber.
+    client.register_event("log").unwrap();
+    drop(client.subscribe_logs(LogFilter::new()).unwrap());
+    assert!(server.is_registered("log"));
+    assert!(client.is_registered("log"));
+}
+
+#[test]
+fn subscriber_ends_after_disconnect() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.connect().unwrap();
+    let mut logs = client.subscribe_logs(LogFilter::new()).unwrap();
+    // A malformed record is reported without ending the stream.
+    server.emit("log", &Message::new().kv_str("msg", "no group"));
+    server.emit("log", &log("ike", 0, "still here"));
+    assert!(logs.next().unwrap().is_err());
+    assert_eq!(logs.next().unwrap().unwrap().msg, "still here");
+
+    server.disconnect_all();
+    assert!(logs.next().unwrap().unwrap_err().is_disconnect());
+    assert!(logs.next().is_none());
+}
+
+#[cfg(feature = "log")]
+mod forwarder {
+    use super::*;
+    use rustici::logging::{LogForwarder, LogRecord, LogSink};
+    use std::sync::Mutex;
+
+    struct Capture(Mutex<Vec<(log::Level, String, String)>>);
+
+    impl log::Log for Capture {
+        fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record<'_>) {
+            let entry = (
+                record.level(),
+                record.target().to_string(),
+                record.args().to_string(),
+            );
+            self.0.lock().unwrap().push(entry);
+        }
+
+        fn flush(&self) {}
+    }
+
+    static CAPTURE: Capture = Capture(Mutex::new(Vec::new()));
+
+    #[test]
+    fn forwards_to_log_crate() {
+        log::set_logger(&CAPTURE).unwrap();
+        log::set_max_level(log::LevelFilter::Trace);
+
+        let mut sink = LogForwarder;
+        sink.log(&LogRecord::from_message(&log("ike", 1, "establishing")).unwrap());
+        sink.log(
+            &LogRecord::from_message(
+                &Message::new()
+                    .kv_str("group", "cfg")
+                    .kv_str("level", "0")
+                    .kv_str("msg", "loaded"),
+            )
+            .unwrap(),
+        );
+
+        let logged = CAPTURE.0.lock().unwrap().clone();
+        assert_eq!(
+            logged,
+            [
+                (
+                    log::Level::Debug,
+                    "charon::ike".to_string(),
+                    "gw[1] establishing".to_string()
+                ),
+                (
+                    log::Level::Info,
+                    "charon::cfg".to_string(),
+                    "loaded".to_string()
+                ),
+            ]
+        );
+        assert_eq!(LogForwarder::level(-1), log::Level::Info);
+        assert_eq!(LogForwarder::level(4), log::Level::Trace);
+    }
+}
@@ -0,0 +1,67 @@
+//! Tests for `rustici::testing::MockServer`, exercising the client against it.
+#![cfg(feature = "testing")]
+
+use rustici::testing::{MockServer, Reply};
+use rustici::{error::Error, Message};
+use std::time::Duration;
+
+fn ike_sa(name: &str, id: &str) -> Message {
+    Message::new()
+        .section_start(name)
+        .kv_str("uniqueid", id)
+        .kv_str("state", "ESTABLISHED")
+        .section_end()
+}
+
+#[test]
+fn streams_list_sa_before_responding() {
+    let server = MockServer::start().unwrap();
+    server.on_command("list-sas", |_| Reply::Stream {
+        event: "list-sa".into(),
+        events: vec![ike_sa("gw", "1"), ike_sa("home", "2")],
+        response: Message::new(),
+    });
+
+    let mut client = server.connect().unwrap();
+    let sas = client.list_sas(None).unwrap();
+    let names: Vec<_> = sas.iter().map(|sa| sa.name.as_str()).collect();
+    assert_eq!(names, ["gw", "home"]);
+    // list_sas registers list-sa only for the duration of the call.
+    assert!(!server.is_registered("list-sa"));
+    assert_eq!(server.requests()[0].0, "list-sas");
+}
+
+#[test]
+fn emits_events_to_registered_clients() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.connect().unwrap();
+    client.register_event("ike-updown").unwrap();
+    assert!(server.registrations().contains("ike-updown"));
+
+    let up = ike_sa("gw", "7").kv_str("up", "yes");
+    assert_eq!(server.emit("ike-updown", &up), 1);
+    assert_eq!(server.emit("child-updown", &up), 0);
+
+    let (name, _) = client.try_next_event(Duration::from_secs(2)).unwrap();
+    assert_eq!(name, "ike-updown");
+
+    server.reject_event("log");
+    assert!(client.register_event("log").is_err());
+}
+
+#[test]
+fn injects_faults() {
+    let server = MockServer::start().unwrap();
+    server.on_command("truncated", |_| {
+        Reply::Truncated(Message::new().kv_str("success", "yes"), 6)
+    });
+
+    let mut client = server.connect().unwrap();
+    assert!(matches!(
+        client.call("no-such-command", &Message::new()),
+        Err(Error::UnknownCommand(cmd)) if cmd == "no-such-command"
+    ));
+
+    let err = client.call("truncated", &Message::new()).unwrap_err();
+    assert!(err.is_disconnect(), "unexpected error: {err}");
+}
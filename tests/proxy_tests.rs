@@ -0,0 +1,116 @@
+//! Tests for `rustici::proxy::Proxy` in front of a `MockServer`.
+#![cfg(all(feature = "proxy", feature = "testing"))]
+
+use rustici::proxy::{Policy, Proxy, Rule, DENIED};
+use rustici::testing::{MockServer, Reply};
+use rustici::{error::Error, Client, Message};
+use std::os::unix::fs::MetadataExt;
+use std::thread;
+use std::time::Duration;
+
+fn start(upstream: &MockServer, name: &str, policy: Policy) -> Proxy {
+    let path =
+        std::env::temp_dir().join(format!("rustici-proxy-{}-{name}.sock", std::process::id()));
+    let proxy = Proxy::bind(path, upstream.path(), policy).unwrap();
+    let runner = proxy.clone();
+    thread::spawn(move || runner.run());
+    proxy
+}
+
+#[test]
+fn read_only_rule_blocks_writes() {
+    let upstream = MockServer::start().unwrap();
+    upstream.on_command("list-conns", |_| Reply::Response(Message::new()));
+    let proxy = start(
+        &upstream,
+        "ro",
+        Policy::deny_all().rule(Rule::any().read_only()),
+    );
+
+    let mut client = Client::connect(proxy.path()).unwrap();
+    client.call("list-conns", &Message::new()).unwrap();
+    assert!(matches!(
+        client.unload_conn("gw"),
+        Err(Error::CommandFailed(msg)) if msg == DENIED
+    ));
+    assert!(client.register_event("ike-updown").is_err());
+    // Charon never saw the denied command or registration.
+    let seen: Vec<_> = upstream.requests().into_iter().map(|(c, _)| c).collect();
+    assert_eq!(seen, ["list-conns"]);
+    assert!(upstream.registrations().is_empty());
+
+    // Allowed registrations and events pass through.
+    client.register_event("list-sa").unwrap();
+    assert!(upstream.is_registered("list-sa"));
+    proxy.shutdown();
+}
+
+#[test]
+fn rules_match_peer_uid() {
+    let upstream = MockServer::start().unwrap();
+    let proxy = start(&upstream, "uid", Policy::deny_all());
+    let uid = std::fs::metadata(proxy.path()).unwrap().uid();
+
+    let mut client = Client::connect(proxy.path()).unwrap();
+    let resp = client.call("version", &Message::new()).unwrap();
+    assert_eq!(resp.tree().unwrap().str("errmsg"), Some(DENIED));
+    proxy.set_policy(Policy::deny_all().rule(Rule::uid(uid.wrapping_add(1)).allow_event("*")));
+    assert!(client.register_event("ike-updown").is_err());
+    proxy.set_policy(Policy::deny_all().rule(Rule::uid(uid).allow_event("*")));
+    client.register_event("ike-updown").unwrap();
+    assert_eq!(
+        upstream.emit("ike-updown", &Message::new().kv_str("up", "yes")),
+        1
+    );
+    let (name, _) = client.next_event().unwrap();
+    assert_eq!(name, "ike-updown");
+    proxy.shutdown();
+}
+
+#[test]
+fn does_not_take_over_a_live_socket() {
+    let upstream = MockServer::start().unwrap();
+    // Binding on top of charon's own socket must not unlink it.
+    let err = Proxy::bind(upstream.path(), upstream.path(), Policy::allow_all())
+        .err()
+        .unwrap();
+    assert!(matches!(err, Error::Io(e) if e.kind() == std::io::ErrorKind::AddrInUse));
+    assert!(upstream.connect().is_ok());
+
+    let proxy = start(&upstream, "live", Policy::allow_all());
+    assert!(Proxy::bind(proxy.path(), upstream.path(), Policy::allow_all()).is_err());
+    assert!(Client::connect(proxy.path()).is_ok());
+    proxy.shutdown();
+}
+
+#[test]
+fn denials_wait_for_earlier_replies() {
+    let upstream = MockServer::start().unwrap();
+    upstream.on_command("list-conns", |_| {
+        thread::sleep(Duration::from_millis(300));
+        Reply::Response(Message::new().kv_str("late", "yes"))
+    });
+    upstream.on_command("version", |_| Reply::Response(Message::new()));
+    let proxy = start(
+        &upstream,
+        "order",
+        Policy::allow_all().rule(Rule::any().deny_command("unload-conn")),
+    );
+
+    let mut client = Client::connect(proxy.path()).unwrap();
+    client
+        .set_read_timeout(Some(Duration::from_millis(100)))
+        .unwrap();
+    let err = client.call("list-conns", &Message::new()).unwrap_err();
+    assert!(matches!(err, Error::Timeout), "unexpected error: {err}");
+
+    // The denial follows charon's late reply, which is discarded as stale.
+    client.set_read_timeout(None).unwrap();
+    assert!(matches!(
+        client.unload_conn("gw"),
+        Err(Error::CommandFailed(msg)) if msg == DENIED
+    ));
+    let resp = client.call("version", &Message::new()).unwrap();
+    assert_eq!(resp.tree().unwrap().str("late"), None);
+    proxy.shutdown();
+}
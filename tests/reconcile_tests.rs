@@ -0,0 +1,162 @@
+//! Reconcile against a `MockServer` keeping charon-like state.
+#![cfg(feature = "testing")]
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use rustici::conn::ConnectionConfig;
+use rustici::creds::{SharedSecret, SharedType};
+use rustici::pools::PoolConfig;
+use rustici::reconcile::{Action, Desired, Kind, Reconciler};
+use rustici::testing::{MockServer, Reply};
+use rustici::Message;
+
+type State = Arc<Mutex<BTreeMap<(&'static str, String), Message>>>;
+
+fn ok() -> Reply {
+    Reply::Response(Message::new().kv_str("success", "yes"))
+}
+
+/// A daemon holding conns, pools and shared secrets, counting loads.
+fn daemon() -> (MockServer, State) {
+    let server = MockServer::start().unwrap();
+    let state: State = Arc::default();
+    for (kind, by) in [("conn", None), ("pool", None), ("shared", Some("id"))] {
+        let s = state.clone();
+        server.on_command(&format!("load-{kind}"), move |msg| {
+            let tree = msg.tree().unwrap();
+            let name = match by {
+                Some(key) => tree.str(key).unwrap().to_string(),
+                None => tree.sections().next().unwrap().0.to_string(),
+            };
+            s.lock().unwrap().insert((kind, name), msg.clone());
+            ok()
+        });
+        let s = state.clone();
+        let key = if kind == "shared" { "id" } else { "name" };
+        server.on_command(&format!("unload-{kind}"), move |msg| {
+            let name = msg.tree().unwrap().str(key).unwrap().to_string();
+            s.lock().unwrap().remove(&(kind, name));
+            ok()
+        });
+    }
+    let names = |state: &State, kind| -> Vec<String> {
+        state
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|(k, _)| *k == kind)
+            .map(|(_, n)| n.clone())
+            .collect()
+    };
+    let s = state.clone();
+    server.on_command("get-conns", move |_| {
+        let mut msg = Message::new().list_start("conns");
+        for name in names(&s, "conn") {
+            msg = msg.list_item_str(name);
+        }
+        Reply::Response(msg.list_end())
+    });
+    let s = state.clone();
+    server.on_command("get-shared", move |_| {
+        let mut msg = Message::new().list_start("keys");
+        for name in names(&s, "shared") {
+            msg = msg.list_item_str(name);
+        }
+        Reply::Response(msg.list_end())
+    });
+    let s = state.clone();
+    server.on_command("get-pools", move |_| {
+        let mut msg = Message::new();
+        for name in names(&s, "pool") {
+            msg = msg
+                .section_start(name)
+                .kv_str("base", "10.3.0.0")
+                .section_end();
+        }
+        Reply::Response(msg)
+    });
+    server.on_command("get-keys", |_| {
+        Reply::Response(Message::new().list_start("keys").list_end())
+    });
+    server.on_command("list-certs", |_| Reply::Stream {
+        event: "list-cert".into(),
+        events: Vec::new(),
+        response: Message::new(),
+    });
+    (server, state)
+}
+
+fn steps(plan: &rustici::reconcile::Plan) -> Vec<String> {
+    plan.steps.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn converges_with_minimal_calls() {
+    let (server, state) = daemon();
+    state
+        .lock()
+        .unwrap()
+        .insert(("conn", "stale".into()), Message::new());
+    state
+        .lock()
+        .unwrap()
+        .insert(("conn", "gw".into()), Message::new());
+    let mut client = server.connect().unwrap();
+
+    let gw = ConnectionConfig::new("gw").version(2);
+    let desired = Desired::new()
+        .conn(&gw)
+        .pool(&PoolConfig::new("rw", "10.3.0.0/24"))
+        .shared("psk", SharedSecret::new(SharedType::Ike, "secret"));
+    let mut reconciler = Reconciler::new();
+
+    // gw exists but was not loaded by us, so it is reloaded.
+    let plan = reconciler.plan(&mut client, &desired).unwrap();
+    assert_eq!(
+        steps(&plan),
+        [
+            "load shared psk",
+            "load pool rw",
+            "reload conn gw",
+            "unload conn stale"
+        ]
+    );
+    let summary = reconciler.apply(&mut client, &desired, &plan).unwrap();
+    assert!(summary.is_ok());
+    assert_eq!(
+        (
+            summary.conns.loaded,
+            summary.conns.unloaded,
+            summary.pools.loaded
+        ),
+        (1, 1, 1)
+    );
+
+    // Converged: nothing to do, no load calls made.
+    let before = server.requests().len();
+    let (plan, _) = reconciler.reconcile(&mut client, &desired).unwrap();
+    assert!(plan.is_empty(), "{plan}");
+    assert_eq!(plan.unchanged.len(), 3);
+    assert!(server.requests()[before..]
+        .iter()
+        .all(|(cmd, _)| !cmd.starts_with("load-") && !cmd.starts_with("unload-")));
+
+    // A changed connection is reloaded, a dropped pool unloaded.
+    let desired = Desired::new()
+        .conn(&gw.clone().version(1))
+        .shared("psk", SharedSecret::new(SharedType::Ike, "secret"));
+    let (plan, summary) = reconciler.reconcile(&mut client, &desired).unwrap();
+    assert_eq!(steps(&plan), ["reload conn gw", "unload pool rw"]);
+    assert!(summary.is_ok());
+    assert!(!state.lock().unwrap().contains_key(&("pool", "rw".into())));
+
+    // After a daemon restart, everything is loaded again.
+    state.lock().unwrap().clear();
+    let plan = reconciler.plan(&mut client, &desired).unwrap();
+    assert!(plan
+        .steps
+        .iter()
+        .all(|s| s.action == Action::Load && s.kind != Kind::Pool));
+    assert_eq!(plan.steps.len(), 2);
+}
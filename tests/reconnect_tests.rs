@@ -0,0 +1,150 @@
+//! Integration tests for `ReconnectingClient` against a fake daemon that
+//! drops the first connection, as happens when charon restarts.
+
+mod common;
+
+use common::{listen, read_frame, write_frame};
+use rustici::reconnect::{Backoff, Notification, ReconnectingClient};
+use std::os::unix::net::UnixStream;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// Confirm one event registration and return the registered name.
+fn confirm_registration(stream: &mut UnixStream) -> String {
+    let pkt = read_frame(stream).unwrap();
+    assert_eq!(pkt[0], 3, "expected EventRegister");
+    let name = String::from_utf8(pkt[2..2 + pkt[1] as usize].to_vec()).unwrap();
+    write_frame(stream, &[5]);
+    name
+}
+
+#[test]
+fn reconnects_and_reregisters_events() {
+    let (socket_path, listener) = listen("reconnect");
+    let (tx, rx) = mpsc::channel();
+
+    let server = thread::spawn(move || {
+        // First "daemon": accept the registration, then go away.
+        let (mut stream, _) = listener.accept().unwrap();
+        tx.send(confirm_registration(&mut stream)).unwrap();
+        drop(stream);
+
+        // Restarted daemon: expect the same registration, then emit an event.
+        let (mut stream, _) = listener.accept().unwrap();
+        tx.send(confirm_registration(&mut stream)).unwrap();
+        let name = b"ike-updown";
+        let mut pkt = vec![7, name.len() as u8];
+        pkt.extend_from_slice(name);
+        pkt.extend_from_slice(&[3, 2, b'u', b'p', 0, 3, b'y', b'e', b's']);
+        write_frame(&mut stream, &pkt);
+        thread::sleep(Duration::from_millis(200));
+    });
+
+    let mut client = ReconnectingClient::connect(&socket_path)
+        .unwrap()
+        .with_backoff(Backoff::new().initial(Duration::from_millis(10)));
+    client.register_event("ike-updown").unwrap();
+    assert_eq!(rx.recv().unwrap(), "ike-updown");
+
+    match client.next_event().unwrap() {
+        Notification::Reconnected { attempts } => assert!(attempts >= 1),
+        other => panic!("expected reconnect, got {other:?}"),
+    }
+    assert_eq!(rx.recv().unwrap(), "ike-updown");
+    assert!(client.client().is_registered("ike-updown"));
+
+    match client.next_event().unwrap() {
+        Notification::Event(name, _) => assert_eq!(name, "ike-updown"),
+        other => panic!("expected event, got {other:?}"),
+    }
+
+    server.join().unwrap();
+    let _ = std::fs::remove_file(&socket_path);
+}
+
+#[test]
+fn gives_up_after_max_attempts() {
+    let (socket_path, listener) = listen("reconnect-gone");
+
+    let gone = socket_path.clone();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        drop(stream);
+        // The listener goes away with the thread; the socket file is removed
+        // so reconnection attempts fail.
+        let _ = std::fs::remove_file(gone);
+    });
+
+    let mut client = ReconnectingClient::connect(&socket_path)
+        .unwrap()
+        .with_backoff(
+            Backoff::new()
+                .initial(Duration::from_millis(5))
+                .max_attempts(3),
+        );
+    server.join().unwrap();
+
+    let err = client.next_event().unwrap_err();
+    assert!(err.is_disconnect(), "unexpected error: {err}");
+}
+
+#[cfg(feature = "testing")]
+mod mock {
+    use super::*;
+    use rustici::testing::{MockServer, Reply};
+    use rustici::Message;
+
+    #[test]
+    fn reconnect_during_call_is_reported() {
+        let server = MockServer::start().unwrap();
+        // Charon going away while answering.
+        server.on_command("version", |_| Reply::Close);
+
+        let mut client = ReconnectingClient::connect(server.path())
+            .unwrap()
+            .with_backoff(Backoff::new().initial(Duration::from_millis(10)));
+        client.register_event("ike-updown").unwrap();
+        let err = client.call("version", &Message::new()).unwrap_err();
+        assert!(err.is_disconnect(), "unexpected error: {err}");
+
+        match client.try_next_event(Duration::from_secs(2)).unwrap() {
+            Notification::Reconnected { attempts } => assert_eq!(attempts, 1),
+            other => panic!("expected reconnect, got {other:?}"),
+        }
+        server.emit("ike-updown", &Message::new().kv_str("up", "yes"));
+        match client.next_event().unwrap() {
+            Notification::Event(name, _) => assert_eq!(name, "ike-updown"),
+            other => panic!("expected event, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn aborted_stream_registrations_are_not_restored() {
+        let server = MockServer::start().unwrap();
+        server.on_command("list-sas", |_| {
+            std::thread::sleep(Duration::from_millis(300));
+            Reply::Stream {
+                event: "list-sa".into(),
+                events: vec![],
+                response: Message::new(),
+            }
+        });
+
+        let mut client = ReconnectingClient::connect(server.path())
+            .unwrap()
+            .with_backoff(Backoff::new().initial(Duration::from_millis(10)));
+        client.register_event("ike-updown").unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_millis(100)))
+            .unwrap();
+        assert!(client.client().list_sas(None).is_err());
+        assert!(client.client().is_registered("list-sa"));
+
+        server.disconnect_all();
+        client.reconnect().unwrap();
+        assert_eq!(client.client().registered_events(), ["ike-updown"]);
+        assert!(!server.is_registered("list-sa"));
+        assert!(server.is_registered("ike-updown"));
+    }
+}
@@ -0,0 +1,187 @@
+//! Typed listings and tracker resyncs must register for the event charon
+//! streams them with.
+#![cfg(feature = "testing")]
+
+use rustici::certs::{CertFilter, CertKind};
+use rustici::control::InitiateRequest;
+use rustici::error::Error;
+use rustici::logging::LogRecord;
+use rustici::policies::PolicyFilter;
+use rustici::testing::{MockServer, Reply};
+use rustici::tracker::SaTracker;
+use rustici::Message;
+use std::thread;
+use std::time::Duration;
+
+fn stream(server: &MockServer, command: &str, event: &str, events: Vec<Message>) {
+    let event = event.to_string();
+    server.on_command(command, move |_| Reply::Stream {
+        event: event.clone(),
+        events: events.clone(),
+        response: Message::new(),
+    });
+}
+
+#[test]
+fn list_conns_registers_list_conn() {
+    let server = MockServer::start().unwrap();
+    let conn = Message::new()
+        .section_start("gw")
+        .list_start("local_addrs")
+        .list_item_str("192.0.2.1")
+        .list_end()
+        .section_end();
+    stream(&server, "list-conns", "list-conn", vec![conn]);
+
+    let mut client = server.connect().unwrap();
+    let conns = client.list_conns(None).unwrap();
+    assert_eq!(conns.len(), 1);
+    assert_eq!(conns[0].name, "gw");
+    assert!(!server.is_registered("list-conn"));
+
+    // An existing registration is left in place.
+    client.register_event("list-conn").unwrap();
+    assert_eq!(client.list_conns(None).unwrap().len(), 1);
+    assert!(server.is_registered("list-conn"));
+}
+
+#[test]
+fn list_certs_registers_list_cert() {
+    let server = MockServer::start().unwrap();
+    let cert = Message::new()
+        .kv_str("type", "X509")
+        .kv_str("flag", "CA")
+        .kv_bytes("data", [0x30, 0x03, 0x02, 0x01, 0x01]);
+    stream(&server, "list-certs", "list-cert", vec![cert]);
+
+    let mut client = server.connect().unwrap();
+    let certs = client.list_certs(&CertFilter::new()).unwrap();
+    assert_eq!(certs.len(), 1);
+    assert_eq!(certs[0].kind(), Some(CertKind::X509));
+    assert!(!server.is_registered("list-cert"));
+}
+
+#[test]
+fn list_policies_registers_list_policy() {
+    let server = MockServer::start().unwrap();
+    let policy = Message::new()
+        .section_start("gw/trap")
+        .kv_str("child", "trap")
+        .kv_str("ike", "gw")
+        .kv_str("mode", "TUNNEL")
+        .section_end();
+    stream(&server, "list-policies", "list-policy", vec![policy]);
+
+    let mut client = server.connect().unwrap();
+    let policies = client.list_policies(&PolicyFilter::new()).unwrap();
+    assert_eq!(policies.len(), 1);
+    assert_eq!(policies[0].child, "trap");
+    assert!(!server.is_registered("list-policy"));
+}
+
+#[test]
+fn aborted_control_log_stream_is_unregistered() {
+    let server = MockServer::start().unwrap();
+    server.on_command("initiate", |_| {
+        thread::sleep(Duration::from_millis(300));
+        Reply::Stream {
+            event: "control-log".into(),
+            events: vec![Message::new()
+                .kv_str("group", "IKE")
+                .kv_str("level", "1")
+                .kv_str("msg", "initiating IKE_SA gw[1]")],
+            response: Message::new().kv_str("success", "yes"),
+        }
+    });
+    server.on_command("version", |_| Reply::Response(Message::new()));
+
+    let mut client = server.connect().unwrap();
+    client
+        .set_read_timeout(Some(Duration::from_millis(100)))
+        .unwrap();
+    let req = InitiateRequest::new().child("net");
+    let err = client
+        .initiate_with_log(&req, |_: &LogRecord| {})
+        .unwrap_err();
+    assert!(matches!(err, Error::Timeout), "unexpected error: {err}");
+
+    // The next request unregisters first; the late log line is dropped.
+    client.set_read_timeout(None).unwrap();
+    client.call("version", &Message::new()).unwrap();
+    assert!(client.registered_events().is_empty());
+    assert!(!server.is_registered("control-log"));
+    assert!(matches!(
+        client.try_next_event(Duration::from_millis(100)),
+        Err(Error::Timeout)
+    ));
+}
+
+#[test]
+fn aborted_listing_is_unregistered() {
+    let server = MockServer::start().unwrap();
+    let conn = Message::new().section_start("gw").section_end();
+    let events = vec![conn];
+    server.on_command("list-conns", move |_| {
+        thread::sleep(Duration::from_millis(300));
+        Reply::Stream {
+            event: "list-conn".into(),
+            events: events.clone(),
+            response: Message::new(),
+        }
+    });
+
+    let mut client = server.connect().unwrap();
+    client
+        .set_read_timeout(Some(Duration::from_millis(100)))
+        .unwrap();
+    let err = client.list_conns(None).unwrap_err();
+    assert!(matches!(err, Error::Timeout), "unexpected error: {err}");
+
+    // Once the stale stream is drained, a new listing registers afresh.
+    client.set_read_timeout(None).unwrap();
+    assert_eq!(client.list_conns(None).unwrap().len(), 1);
+    assert!(client.registered_events().is_empty());
+    assert!(!server.is_registered("list-conn"));
+    assert!(matches!(
+        client.try_next_event(Duration::from_millis(100)),
+        Err(Error::Timeout)
+    ));
+}
+
+#[test]
+fn aborted_tracker_resync_is_unregistered() {
+    let server = MockServer::start().unwrap();
+    let sa = Message::new()
+        .section_start("gw")
+        .kv_str("uniqueid", "1")
+        .kv_str("state", "ESTABLISHED")
+        .section_end();
+    let events = vec![sa];
+    server.on_command("list-sas", move |_| {
+        thread::sleep(Duration::from_millis(300));
+        Reply::Stream {
+            event: "list-sa".into(),
+            events: events.clone(),
+            response: Message::new(),
+        }
+    });
+
+    let mut client = server.connect().unwrap();
+    client
+        .set_read_timeout(Some(Duration::from_millis(100)))
+        .unwrap();
+    let err = SaTracker::attach(&mut client).unwrap_err();
+    assert!(matches!(err, Error::Timeout), "unexpected error: {err}");
+
+    // The late listing is dropped rather than queued as unsolicited events.
+    client.set_read_timeout(None).unwrap();
+    let mut tracker = SaTracker::new();
+    tracker.resync(&mut client).unwrap();
+    assert_eq!(tracker.len(), 1);
+    assert!(!client.is_registered("list-sa"));
+    assert!(!server.is_registered("list-sa"));
+    assert!(matches!(
+        client.try_next_event(Duration::from_millis(100)),
+        Err(Error::Timeout)
+    ));
+}
@@ -0,0 +1,148 @@
+//! Supervise tunnels against a `MockServer` emitting updown events.
+#![cfg(feature = "testing")]
+
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rustici::reconnect::Backoff;
+use rustici::supervisor::{Supervisor, TunnelState};
+use rustici::testing::{MockServer, Reply};
+use rustici::{Client, Message};
+
+fn child_updown(up: bool, id: u32) -> Message {
+    format!(
+        "up = {} gw {{ uniqueid = 1 state = ESTABLISHED \
+         child-sas {{ net-{id} {{ name = net uniqueid = {id} state = INSTALLED }} }} }}",
+        if up { "yes" } else { "no" }
+    )
+    .parse()
+    .unwrap()
+}
+
+#[test]
+fn reinitiates_until_terminated() {
+    let server = MockServer::start().unwrap();
+    server.on_command("list-sas", |_| Reply::Stream {
+        event: "list-sa".into(),
+        events: vec![child_updown(true, 3)],
+        response: Message::new(),
+    });
+    let initiated = Arc::new(AtomicU32::new(0));
+    let fail = Arc::new(AtomicBool::new(false));
+    {
+        let (initiated, fail) = (initiated.clone(), fail.clone());
+        server.on_command("initiate", move |req| {
+            assert_eq!(req.tree().unwrap().str("child"), Some("net"));
+            initiated.fetch_add(1, Ordering::SeqCst);
+            Reply::Response(if fail.load(Ordering::SeqCst) {
+                "success = no errmsg = \"peer unreachable\""
+                    .parse()
+                    .unwrap()
+            } else {
+                "success = yes".parse().unwrap()
+            })
+        });
+    }
+    server.on_command("terminate", |_| {
+        Reply::Response("success = yes matches = 1 terminated = 1".parse().unwrap())
+    });
+
+    let mut client = server.connect().unwrap();
+    let mut sup = Supervisor::new()
+        .backoff(
+            Backoff::new()
+                .initial(Duration::from_millis(10))
+                .max_attempts(2),
+        )
+        .tunnel("gw", "net");
+    sup.attach(&mut client).unwrap();
+    let health = sup.health("gw", "net").unwrap();
+    assert_eq!((health.state, health.child_id), (TunnelState::Up, Some(3)));
+
+    // Unexpected down: re-initiated after the backoff delay.
+    server.emit("child-updown", &child_updown(false, 3));
+    let wait = Duration::from_secs(1);
+    assert!(sup.run_once(&mut client, wait).unwrap().is_some());
+    assert_eq!(sup.health("gw", "net").unwrap().state, TunnelState::Down);
+    sup.run_once(&mut client, wait).unwrap();
+    assert_eq!(initiated.load(Ordering::SeqCst), 1);
+    let health = sup.health("gw", "net").unwrap();
+    assert_eq!((health.state, health.restarts), (TunnelState::Up, 1));
+    server.emit("child-updown", &child_updown(true, 4));
+    sup.run_once(&mut client, wait).unwrap();
+    assert_eq!(sup.health("gw", "net").unwrap().child_id, Some(4));
+
+    // Failing initiations back off and give up after max_attempts.
+    fail.store(true, Ordering::SeqCst);
+    server.emit("child-updown", &child_updown(false, 4));
+    for _ in 0..4 {
+        sup.run_once(&mut client, Duration::from_millis(100))
+            .unwrap();
+    }
+    assert_eq!(initiated.load(Ordering::SeqCst), 3);
+    let health = sup.health("gw", "net").unwrap();
+    assert_eq!(health.state, TunnelState::Failed);
+    assert_eq!(health.last_error.as_deref(), Some("peer unreachable"));
+
+    // start() resumes; our own terminate is not undone.
+    fail.store(false, Ordering::SeqCst);
+    sup.start("gw", "net");
+    sup.run_once(&mut client, wait).unwrap();
+    assert_eq!(sup.health("gw", "net").unwrap().state, TunnelState::Up);
+    assert!(sup.terminate(&mut client, "gw", "net").unwrap().success);
+    server.emit("child-updown", &child_updown(false, 5));
+    for _ in 0..3 {
+        sup.run_once(&mut client, Duration::from_millis(50))
+            .unwrap();
+    }
+    assert_eq!(initiated.load(Ordering::SeqCst), 4);
+    let health = sup.health("gw", "net").unwrap();
+    assert!(health.stopped);
+    assert_eq!(health.state, TunnelState::Down);
+}
+
+#[test]
+fn terminate_marks_down_until_started() {
+    let server = MockServer::start().unwrap();
+    server.on_command("list-sas", |_| Reply::Stream {
+        event: "list-sa".into(),
+        events: vec![child_updown(true, 3)],
+        response: Message::new(),
+    });
+    // charon reports the CHILD_SA down while terminate is still running.
+    server.on_command("terminate", |_| Reply::Stream {
+        event: "child-updown".into(),
+        events: vec![child_updown(false, 3)],
+        response: "success = yes matches = 1 terminated = 1".parse().unwrap(),
+    });
+    let initiated = Arc::new(AtomicU32::new(0));
+    {
+        let initiated = initiated.clone();
+        server.on_command("initiate", move |_| {
+            initiated.fetch_add(1, Ordering::SeqCst);
+            Reply::Response("success = yes".parse().unwrap())
+        });
+    }
+
+    let mut client = Client::builder()
+        .path(server.path())
+        .event_buffer(8)
+        .build()
+        .unwrap();
+    let mut sup = Supervisor::new().tunnel("gw", "net");
+    sup.attach(&mut client).unwrap();
+    assert!(sup.terminate(&mut client, "gw", "net").unwrap().success);
+    let health = sup.health("gw", "net").unwrap();
+    assert_eq!((health.state, health.stopped), (TunnelState::Down, true));
+
+    // The down event was kept for run_once, and is not undone.
+    let wait = Duration::from_millis(100);
+    assert!(sup.run_once(&mut client, wait).unwrap().is_some());
+    assert_eq!(initiated.load(Ordering::SeqCst), 0);
+
+    sup.start("gw", "net");
+    sup.run_once(&mut client, wait).unwrap();
+    assert_eq!(initiated.load(Ordering::SeqCst), 1);
+    assert_eq!(sup.health("gw", "net").unwrap().state, TunnelState::Up);
+}